@@ -1,18 +1,18 @@
 #[allow(clippy::pedantic, clippy::nursery)]
 pub mod pb {
     pub mod common {
-        pub mod v1 {
-            tonic::include_proto!("common.v1");
+        pub mod v2 {
+            tonic::include_proto!("common.v2");
         }
     }
     pub mod crud {
-        pub mod v1 {
-            tonic::include_proto!("crud.v1");
+        pub mod v2 {
+            tonic::include_proto!("crud.v2");
         }
     }
 }
 
-use pb::crud::v1::{ListFortressesRequest, fortress_service_client::FortressServiceClient};
+use pb::crud::v2::{ListFortressesRequest, fortress_service_client::FortressServiceClient};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -33,9 +33,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // println!("{:?}", response.into_inner().fortress);
 
     let response = client
-        .list_fortresses(tonic::Request::new(ListFortressesRequest {
-            owner_id: None,
-        }))
+        .list_fortresses(tonic::Request::new(ListFortressesRequest::default()))
         .await?;
     println!("{:?}", response.into_inner().fortresses);
 
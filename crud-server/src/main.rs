@@ -1,34 +1,36 @@
 #[allow(clippy::pedantic, clippy::nursery)]
 pub mod pb {
     pub mod common {
-        pub mod v1 {
-            tonic::include_proto!("common.v1");
+        pub mod v2 {
+            tonic::include_proto!("common.v2");
         }
     }
     pub mod crud {
-        pub mod v1 {
-            tonic::include_proto!("crud.v1");
+        pub mod v2 {
+            tonic::include_proto!("crud.v2");
         }
     }
 }
 
+mod repository;
 pub mod service;
 
-use diesel::{
-    PgConnection,
-    r2d2::{ConnectionManager, Pool},
+use pb::crud::v2::{
+    building_service_server::BuildingServiceServer, clock_service_server::ClockServiceServer,
+    fortress_service_server::FortressServiceServer,
 };
-use pb::crud::v1::{
-    building_service_server::BuildingServiceServer, fortress_service_server::FortressServiceServer,
+use repository::{
+    Repository,
+    memory::MemoryRepository,
+    sql::{PoolConfig, SqlRepository, build_pool},
 };
-use service::{MyBuildingService, MyFortressService};
-use std::sync::Arc;
+use rusty::clock::{SkewedClock, SystemClock};
+use service::{MyBuildingService, MyClockService, MyFortressService};
+use std::{fmt::Display, str::FromStr, sync::Arc, time::Duration};
 use tokio::signal::unix::{SignalKind, signal};
 use tonic::transport::Server;
 use tracing::{error, info, warn};
 
-pub type DbPool = Pool<ConnectionManager<PgConnection>>;
-
 async fn shutdown_signal() {
     let sigterm = signal(SignalKind::terminate());
     let sigint = signal(SignalKind::interrupt());
@@ -46,22 +48,79 @@ async fn shutdown_signal() {
     }
 }
 
+fn env_var<T>(name: &str) -> Result<Option<T>, String>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    std::env::var(name).map_or(Ok(None), |value| {
+        value.parse().map(Some).map_err(|e| format!("{name} {e}"))
+    })
+}
+
+/// Permanently removes fortresses whose restore grace period is over.
+async fn purge_deleted_fortresses(
+    repository: Arc<dyn Repository>,
+    grace_period: Duration,
+    every: Duration,
+) {
+    let mut interval = tokio::time::interval(every);
+    loop {
+        interval.tick().await;
+        match repository.purge_deleted_fortresses(grace_period).await {
+            Ok(0) => {}
+            Ok(purged) => info!("Purged {purged} deleted fortresses"),
+            Err(e) => error!("Failed to purge deleted fortresses: {e}"),
+        }
+    }
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     tracing_subscriber::fmt::init();
     let addr = "[::]:3000".parse()?;
     let database_url = std::env::var("DATABASE_URL").map_err(|e| format!("DATABASE_URL {e}"))?;
-    let manager = ConnectionManager::<PgConnection>::new(database_url);
-    let pool = Pool::builder().build(manager)?;
-    let pool = Arc::new(pool);
-    let building_service = MyBuildingService::new(pool.clone());
-    let fortress_service = MyFortressService::new(pool);
+    let clock = Arc::new(SkewedClock::new(SystemClock));
+    let repository: Arc<dyn Repository> = if database_url.starts_with("memory:") {
+        warn!("Using the in-memory backend, data will be lost on shutdown");
+        Arc::new(MemoryRepository::new(clock.clone()))
+    } else {
+        let config = PoolConfig {
+            max_size: env_var("DATABASE_POOL_SIZE")?.unwrap_or(10),
+            connection_timeout: Duration::from_secs(
+                env_var("DATABASE_CONNECTION_TIMEOUT_SECS")?.unwrap_or(30),
+            ),
+            statement_timeout: env_var("DATABASE_STATEMENT_TIMEOUT_MS")?.map(Duration::from_millis),
+        };
+        let pool = build_pool(database_url, &config)?;
+        Arc::new(SqlRepository::new(pool, clock.clone()))
+    };
+    let restore_grace_period =
+        Duration::from_secs(env_var("FORTRESS_RESTORE_GRACE_SECS")?.unwrap_or(7 * 24 * 60 * 60));
+    let purge_interval =
+        Duration::from_secs(env_var("FORTRESS_PURGE_INTERVAL_SECS")?.unwrap_or(60 * 60));
+    if purge_interval.is_zero() {
+        return Err("FORTRESS_PURGE_INTERVAL_SECS must be greater than 0".into());
+    }
+    tokio::spawn(purge_deleted_fortresses(
+        repository.clone(),
+        restore_grace_period,
+        purge_interval,
+    ));
+    let building_service = MyBuildingService::new(repository.clone());
+    let fortress_service = MyFortressService::new(repository, restore_grace_period);
+    // Anyone reaching crud-server could move the game clock, so only
+    // deployments where game-server alone reaches it opt in.
+    let clock_service = env_var("CLOCK_SERVICE_ENABLED")?
+        .unwrap_or(false)
+        .then(|| ClockServiceServer::new(MyClockService::new(clock)));
 
     info!("Listening on {addr}");
 
     Server::builder()
         .add_service(BuildingServiceServer::new(building_service))
         .add_service(FortressServiceServer::new(fortress_service))
+        .add_optional_service(clock_service)
         .serve_with_shutdown(addr, shutdown_signal())
         .await?;
 
@@ -0,0 +1,1061 @@
+use super::{
+    BuildingQuery, Cursor, EventKind, FortressQuery, Repository, RepositoryError,
+    ScoutReportWithBuildings, SortDirection, after_gain, after_raid, check_non_negative,
+    check_version, claim_streak, grace_period_start, missing_resources, new_event, resource_delta,
+    respawn_time, timestamp,
+};
+use rusty::{
+    Costs,
+    clock::Clock,
+    models::{
+        Building, Camp, DailyReward, Fortress, FortressEvent, NewBuilding, NewCamp, NewFortress,
+        NewFortressEvent, ScoutReport, ScoutReportBuilding, UpdateBuilding, UpdateFortress,
+    },
+    rules::{self, Resource},
+};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    ops::Bound,
+    sync::{Arc, Mutex, MutexGuard},
+    time::Duration,
+};
+use time::PrimitiveDateTime;
+
+#[derive(Default)]
+struct State {
+    fortresses: BTreeMap<i32, Fortress>,
+    buildings: BTreeMap<i32, Building>,
+    events: BTreeMap<i64, FortressEvent>,
+    scout_reports: BTreeMap<i64, ScoutReportWithBuildings>,
+    camps: BTreeMap<i32, Camp>,
+    daily_rewards: BTreeMap<String, DailyReward>,
+    last_fortress_id: i32,
+    last_building_id: i32,
+    last_event_id: i64,
+    last_scout_report_id: i64,
+    last_camp_id: i32,
+}
+
+impl State {
+    fn insert_fortress(
+        &mut self,
+        new_fortress: NewFortress,
+        now: PrimitiveDateTime,
+    ) -> Result<Fortress, RepositoryError> {
+        check_non_negative(&[
+            new_fortress.gold,
+            new_fortress.food,
+            new_fortress.wood,
+            new_fortress.energy,
+        ])?;
+        self.last_fortress_id = self
+            .last_fortress_id
+            .checked_add(1)
+            .ok_or_else(out_of_range)?;
+        let fortress = Fortress {
+            id: self.last_fortress_id,
+            owner_id: new_fortress.owner_id,
+            owner_name: new_fortress.owner_name,
+            gold: new_fortress.gold,
+            food: new_fortress.food,
+            wood: new_fortress.wood,
+            energy: new_fortress.energy,
+            deleted_at: None,
+            version: 0,
+            created_at: now,
+            updated_at: now,
+        };
+        self.fortresses.insert(fortress.id, fortress.clone());
+        let starting_resources = Costs {
+            gold: fortress.gold,
+            food: fortress.food,
+            wood: fortress.wood,
+            energy: fortress.energy,
+        };
+        self.insert_event(new_event(
+            EventKind::Create,
+            fortress.id,
+            None,
+            &starting_resources,
+            now,
+        ))?;
+
+        Ok(fortress)
+    }
+
+    fn insert_building(
+        &mut self,
+        new_building: NewBuilding,
+        now: PrimitiveDateTime,
+    ) -> Result<Building, RepositoryError> {
+        if !self.is_live_fortress(new_building.fortress_id) {
+            return Err(RepositoryError::FortressNotFound);
+        }
+        check_non_negative(&[i64::from(new_building.level)])?;
+        if self.has_building(new_building.fortress_id, &new_building.name, None) {
+            return Err(RepositoryError::DuplicateBuilding);
+        }
+        self.last_building_id = self
+            .last_building_id
+            .checked_add(1)
+            .ok_or_else(out_of_range)?;
+        let building = Building {
+            id: self.last_building_id,
+            name: new_building.name,
+            level: new_building.level,
+            fortress_id: new_building.fortress_id,
+            version: 0,
+            created_at: now,
+            updated_at: now,
+        };
+        self.buildings.insert(building.id, building.clone());
+
+        Ok(building)
+    }
+
+    fn insert_event(&mut self, new_event: NewFortressEvent) -> Result<(), RepositoryError> {
+        self.last_event_id = self.last_event_id.checked_add(1).ok_or_else(out_of_range)?;
+        let event = FortressEvent {
+            id: self.last_event_id,
+            fortress_id: Some(new_event.fortress_id),
+            building_id: new_event.building_id,
+            kind: new_event.kind,
+            gold: new_event.gold,
+            food: new_event.food,
+            wood: new_event.wood,
+            energy: new_event.energy,
+            created_at: new_event.created_at,
+            other_fortress_id: new_event.other_fortress_id,
+        };
+        self.events.insert(event.id, event);
+
+        Ok(())
+    }
+
+    /// Whether the fortress holds a building with that name, besides `except`.
+    fn has_building(&self, fortress_id: i32, name: &str, except: Option<i32>) -> bool {
+        self.buildings.values().any(|building| {
+            building.fortress_id == fortress_id
+                && building.name == name
+                && Some(building.id) != except
+        })
+    }
+
+    fn live_fortress_count(&self, owner_id: &str) -> usize {
+        self.fortresses
+            .values()
+            .filter(|fortress| fortress.owner_id == owner_id && fortress.deleted_at.is_none())
+            .count()
+    }
+
+    fn is_live_fortress(&self, id: i32) -> bool {
+        self.fortresses
+            .get(&id)
+            .is_some_and(|fortress| fortress.deleted_at.is_none())
+    }
+
+    fn live_fortress_mut(&mut self, id: i32) -> Result<&mut Fortress, RepositoryError> {
+        self.fortresses
+            .get_mut(&id)
+            .filter(|fortress| fortress.deleted_at.is_none())
+            .ok_or(RepositoryError::FortressNotFound)
+    }
+
+    fn live_buildings(&self) -> impl Iterator<Item = &Building> {
+        self.buildings
+            .values()
+            .filter(|building| self.is_live_fortress(building.fortress_id))
+    }
+
+    fn live_building_mut(&mut self, id: i32) -> Result<&mut Building, RepositoryError> {
+        let live = self
+            .buildings
+            .get(&id)
+            .is_some_and(|building| self.is_live_fortress(building.fortress_id));
+        if !live {
+            return Err(RepositoryError::BuildingNotFound);
+        }
+        self.buildings
+            .get_mut(&id)
+            .ok_or(RepositoryError::BuildingNotFound)
+    }
+}
+
+/// Keeps every row behind a single lock, so each method observes and mutates
+/// the whole dataset atomically, like a serializable transaction would.
+pub struct MemoryRepository {
+    state: Mutex<State>,
+    clock: Arc<dyn Clock>,
+}
+
+impl MemoryRepository {
+    #[must_use]
+    pub fn new(clock: Arc<dyn Clock>) -> Self {
+        Self {
+            state: Mutex::default(),
+            clock,
+        }
+    }
+
+    fn now(&self) -> PrimitiveDateTime {
+        timestamp(self.clock.now())
+    }
+
+    fn state(&self) -> Result<MutexGuard<'_, State>, RepositoryError> {
+        self.state
+            .lock()
+            .map_err(|e| RepositoryError::Backend(e.to_string()))
+    }
+}
+
+fn out_of_range() -> RepositoryError {
+    RepositoryError::Backend("integer out of range".to_owned())
+}
+
+/// Same order and cursor semantics as the keyset queries of `SqlRepository`.
+fn page<T>(
+    mut rows: Vec<T>,
+    key: impl Fn(&T) -> (i64, i32),
+    direction: SortDirection,
+    after: Option<Cursor>,
+    limit: i64,
+) -> Vec<T> {
+    rows.sort_by_key(&key);
+    if direction == SortDirection::Descending {
+        rows.reverse();
+    }
+    let after = after.map(|cursor| (cursor.key, cursor.id));
+    rows.into_iter()
+        .filter(|row| {
+            after.is_none_or(|after| match direction {
+                SortDirection::Ascending => key(row) > after,
+                SortDirection::Descending => key(row) < after,
+            })
+        })
+        .take(usize::try_from(limit).unwrap_or(0))
+        .collect()
+}
+
+#[tonic::async_trait]
+impl Repository for MemoryRepository {
+    async fn create_building(
+        &self,
+        new_building: NewBuilding,
+    ) -> Result<Building, RepositoryError> {
+        let now = self.now();
+        self.state()?.insert_building(new_building, now)
+    }
+
+    async fn get_building(&self, id: i32) -> Result<Building, RepositoryError> {
+        self.state()?
+            .live_buildings()
+            .find(|building| building.id == id)
+            .cloned()
+            .ok_or(RepositoryError::BuildingNotFound)
+    }
+
+    async fn get_buildings(&self, ids: Vec<i32>) -> Result<Vec<Building>, RepositoryError> {
+        let state = self.state()?;
+        let buildings = ids
+            .into_iter()
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .filter_map(|id| state.buildings.get(&id))
+            .filter(|building| state.is_live_fortress(building.fortress_id))
+            .cloned()
+            .collect();
+        drop(state);
+
+        Ok(buildings)
+    }
+
+    async fn get_building_with_fortress(
+        &self,
+        id: i32,
+    ) -> Result<(Building, Fortress), RepositoryError> {
+        let state = self.state()?;
+        let building = state
+            .buildings
+            .get(&id)
+            .ok_or(RepositoryError::BuildingNotFound)?;
+        let fortress = state
+            .fortresses
+            .get(&building.fortress_id)
+            .filter(|fortress| fortress.deleted_at.is_none())
+            .ok_or(RepositoryError::BuildingNotFound)?;
+        let found = (building.clone(), fortress.clone());
+        drop(state);
+
+        Ok(found)
+    }
+
+    async fn update_building(
+        &self,
+        id: i32,
+        update_building: UpdateBuilding,
+        expected_version: Option<i64>,
+    ) -> Result<Building, RepositoryError> {
+        let now = self.now();
+        let mut state = self.state()?;
+        check_version(expected_version, state.live_building_mut(id)?.version)?;
+        if let Some(fortress_id) = update_building.fortress_id
+            && !state.is_live_fortress(fortress_id)
+        {
+            return Err(RepositoryError::FortressNotFound);
+        }
+        if let Some(level) = update_building.level {
+            check_non_negative(&[i64::from(level)])?;
+        }
+        let building = state.live_building_mut(id)?;
+        let fortress_id = update_building.fortress_id.unwrap_or(building.fortress_id);
+        let name = update_building
+            .name
+            .clone()
+            .unwrap_or_else(|| building.name.clone());
+        if state.has_building(fortress_id, &name, Some(id)) {
+            return Err(RepositoryError::DuplicateBuilding);
+        }
+        let building = state.live_building_mut(id)?;
+        if let Some(name) = update_building.name {
+            building.name = name;
+        }
+        if let Some(level) = update_building.level {
+            building.level = level;
+        }
+        if let Some(fortress_id) = update_building.fortress_id {
+            building.fortress_id = fortress_id;
+        }
+        building.version += 1;
+        building.updated_at = now;
+        let building = building.clone();
+        drop(state);
+
+        Ok(building)
+    }
+
+    async fn delete_building(&self, id: i32) -> Result<bool, RepositoryError> {
+        let mut state = self.state()?;
+        if state.live_building_mut(id).is_err() {
+            return Ok(false);
+        }
+        for event in state.events.values_mut() {
+            if event.building_id == Some(id) {
+                event.building_id = None;
+            }
+        }
+
+        Ok(state.buildings.remove(&id).is_some())
+    }
+
+    async fn list_buildings(&self, query: BuildingQuery) -> Result<Vec<Building>, RepositoryError> {
+        let state = self.state()?;
+        let buildings = state
+            .live_buildings()
+            .filter(|building| {
+                query
+                    .name
+                    .as_ref()
+                    .is_none_or(|name| &building.name == name)
+                    && query.min_level.is_none_or(|min| building.level >= min)
+                    && (query.fortress_ids.is_empty()
+                        || query.fortress_ids.contains(&building.fortress_id))
+                    && query.owner_id.as_ref().is_none_or(|owner| {
+                        state
+                            .fortresses
+                            .get(&building.fortress_id)
+                            .is_some_and(|fortress| &fortress.owner_id == owner)
+                    })
+            })
+            .cloned()
+            .collect();
+        drop(state);
+
+        Ok(page(
+            buildings,
+            |building| (query.sort.key(building), building.id),
+            query.direction,
+            query.after,
+            query.limit,
+        ))
+    }
+
+    async fn list_buildings_by_fortress(
+        &self,
+        fortress_id: i32,
+    ) -> Result<Vec<Building>, RepositoryError> {
+        Ok(self
+            .state()?
+            .live_buildings()
+            .filter(|building| building.fortress_id == fortress_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn upgrade_building_atomic(
+        &self,
+        building_id: i32,
+        costs: Costs,
+        expected_level: Option<i32>,
+        expected_version: Option<i64>,
+        max_level: i32,
+    ) -> Result<(Fortress, Building), RepositoryError> {
+        let now = self.now();
+        let mut state = self.state()?;
+        let building = state.live_building_mut(building_id)?.clone();
+        if let Some(expected) = expected_level
+            && expected != building.level
+        {
+            return Err(RepositoryError::ConcurrentUpdate);
+        }
+        check_version(expected_version, building.version)?;
+        if building.level >= max_level {
+            return Err(RepositoryError::MaxLevel);
+        }
+        let fortress = state.live_fortress_mut(building.fortress_id)?;
+        if fortress.gold < costs.gold
+            || fortress.food < costs.food
+            || fortress.wood < costs.wood
+            || fortress.energy < costs.energy
+        {
+            return Err(RepositoryError::InsufficientResources {
+                missing: missing_resources(&costs, fortress),
+            });
+        }
+        let level = building.level.checked_add(1).ok_or_else(out_of_range)?;
+        fortress.gold -= costs.gold;
+        fortress.food -= costs.food;
+        fortress.wood -= costs.wood;
+        fortress.energy -= costs.energy;
+        fortress.version += 1;
+        fortress.updated_at = now;
+        let fortress = fortress.clone();
+        let building = state.live_building_mut(building_id)?;
+        building.level = level;
+        building.version += 1;
+        building.updated_at = now;
+        let upgraded = (fortress, building.clone());
+        let spent = Costs {
+            gold: -costs.gold,
+            food: -costs.food,
+            wood: -costs.wood,
+            energy: -costs.energy,
+        };
+        state.insert_event(new_event(
+            EventKind::Upgrade,
+            upgraded.0.id,
+            Some(building_id),
+            &spent,
+            now,
+        ))?;
+        drop(state);
+
+        Ok(upgraded)
+    }
+
+    async fn create_fortress(
+        &self,
+        new_fortress: NewFortress,
+    ) -> Result<Fortress, RepositoryError> {
+        let now = self.now();
+        self.state()?.insert_fortress(new_fortress, now)
+    }
+
+    async fn create_fortress_with_buildings(
+        &self,
+        new_fortress: NewFortress,
+        building_names: Vec<String>,
+        max_fortresses_per_owner: Option<i64>,
+    ) -> Result<(Fortress, Vec<Building>), RepositoryError> {
+        let now = self.now();
+        let mut state = self.state()?;
+        if let Some(max) = max_fortresses_per_owner {
+            let count = state.live_fortress_count(&new_fortress.owner_id);
+            if i64::try_from(count).map_or(true, |count| count >= max) {
+                return Err(RepositoryError::FortressLimitReached);
+            }
+        }
+        // Nothing can be rolled back here, so every building is checked first.
+        let mut names = BTreeSet::new();
+        if !building_names.iter().all(|name| names.insert(name)) {
+            return Err(RepositoryError::DuplicateBuilding);
+        }
+        let fortress = state.insert_fortress(new_fortress, now)?;
+        let buildings = building_names
+            .into_iter()
+            .map(|name| state.insert_building(NewBuilding::new(name, fortress.id), now))
+            .collect::<Result<Vec<_>, _>>()?;
+        drop(state);
+
+        Ok((fortress, buildings))
+    }
+
+    async fn get_fortress(&self, id: i32) -> Result<Fortress, RepositoryError> {
+        Ok(self.state()?.live_fortress_mut(id)?.clone())
+    }
+
+    async fn get_fortresses(&self, ids: Vec<i32>) -> Result<Vec<Fortress>, RepositoryError> {
+        let state = self.state()?;
+        let fortresses = ids
+            .into_iter()
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .filter_map(|id| state.fortresses.get(&id))
+            .filter(|fortress| fortress.deleted_at.is_none())
+            .cloned()
+            .collect();
+        drop(state);
+
+        Ok(fortresses)
+    }
+
+    async fn get_fortress_with_buildings(
+        &self,
+        id: i32,
+    ) -> Result<(Fortress, Vec<Building>), RepositoryError> {
+        let mut state = self.state()?;
+        let fortress = state.live_fortress_mut(id)?.clone();
+        let buildings = state
+            .buildings
+            .values()
+            .filter(|building| building.fortress_id == id)
+            .cloned()
+            .collect();
+        drop(state);
+
+        Ok((fortress, buildings))
+    }
+
+    async fn update_fortress(
+        &self,
+        id: i32,
+        update_fortress: UpdateFortress,
+        expected_version: Option<i64>,
+    ) -> Result<Fortress, RepositoryError> {
+        let now = self.now();
+        let mut state = self.state()?;
+        let fortress = state.live_fortress_mut(id)?;
+        check_version(expected_version, fortress.version)?;
+        check_non_negative(
+            &[
+                update_fortress.gold,
+                update_fortress.food,
+                update_fortress.wood,
+                update_fortress.energy,
+            ]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>(),
+        )?;
+        if let Some(gold) = update_fortress.gold {
+            fortress.gold = gold;
+        }
+        if let Some(food) = update_fortress.food {
+            fortress.food = food;
+        }
+        if let Some(wood) = update_fortress.wood {
+            fortress.wood = wood;
+        }
+        if let Some(energy) = update_fortress.energy {
+            fortress.energy = energy;
+        }
+        fortress.version += 1;
+        fortress.updated_at = now;
+        let fortress = fortress.clone();
+        drop(state);
+
+        Ok(fortress)
+    }
+
+    async fn delete_fortress(&self, id: i32) -> Result<bool, RepositoryError> {
+        let now = self.now();
+        let mut state = self.state()?;
+        let Ok(fortress) = state.live_fortress_mut(id) else {
+            return Ok(false);
+        };
+        fortress.deleted_at = Some(now);
+        fortress.version += 1;
+        fortress.updated_at = now;
+        state.insert_event(new_event(EventKind::Delete, id, None, &Costs::ZERO, now))?;
+        drop(state);
+
+        Ok(true)
+    }
+
+    async fn restore_fortress(
+        &self,
+        id: i32,
+        owner_id: Option<String>,
+        grace_period: Duration,
+        max_fortresses_per_owner: Option<i64>,
+    ) -> Result<Fortress, RepositoryError> {
+        let now = self.now();
+        let mut state = self.state()?;
+        let owner_count = state
+            .fortresses
+            .get(&id)
+            .map(|fortress| state.live_fortress_count(&fortress.owner_id));
+        let fortress = state
+            .fortresses
+            .get_mut(&id)
+            .filter(|fortress| {
+                owner_id
+                    .as_ref()
+                    .is_none_or(|owner| &fortress.owner_id == owner)
+            })
+            .ok_or(RepositoryError::FortressNotFound)?;
+        let deleted_at = fortress
+            .deleted_at
+            .ok_or(RepositoryError::FortressNotDeleted)?;
+        if grace_period_start(now, grace_period).is_some_and(|start| deleted_at < start) {
+            return Err(RepositoryError::RestorePeriodExpired);
+        }
+        if let (Some(max), Some(count)) = (max_fortresses_per_owner, owner_count)
+            && i64::try_from(count).map_or(true, |count| count >= max)
+        {
+            return Err(RepositoryError::FortressLimitReached);
+        }
+        fortress.deleted_at = None;
+        fortress.version += 1;
+        fortress.updated_at = now;
+        let fortress = fortress.clone();
+        drop(state);
+
+        Ok(fortress)
+    }
+
+    async fn purge_deleted_fortresses(
+        &self,
+        grace_period: Duration,
+    ) -> Result<usize, RepositoryError> {
+        let now = self.now();
+        let Some(start) = grace_period_start(now, grace_period) else {
+            return Ok(0);
+        };
+        let mut state = self.state()?;
+        let count = state.fortresses.len();
+        state.fortresses.retain(|_, fortress| {
+            fortress
+                .deleted_at
+                .is_none_or(|deleted_at| deleted_at >= start)
+        });
+        let purged = count - state.fortresses.len();
+        let State {
+            fortresses,
+            buildings,
+            events,
+            scout_reports,
+            ..
+        } = &mut *state;
+        buildings.retain(|_, building| fortresses.contains_key(&building.fortress_id));
+        for event in events.values_mut() {
+            event.fortress_id = event.fortress_id.filter(|id| fortresses.contains_key(id));
+            event.building_id = event.building_id.filter(|id| buildings.contains_key(id));
+            event.other_fortress_id = event
+                .other_fortress_id
+                .filter(|id| fortresses.contains_key(id));
+        }
+        scout_reports.retain(|_, (report, _)| {
+            fortresses.contains_key(&report.spy_fortress_id)
+                && fortresses.contains_key(&report.target_fortress_id)
+        });
+        drop(state);
+
+        Ok(purged)
+    }
+
+    async fn list_fortresses(
+        &self,
+        query: FortressQuery,
+    ) -> Result<Vec<Fortress>, RepositoryError> {
+        let fortresses = self
+            .state()?
+            .fortresses
+            .values()
+            .filter(|fortress| {
+                fortress.deleted_at.is_none()
+                    && query
+                        .owner_id
+                        .as_ref()
+                        .is_none_or(|owner| &fortress.owner_id == owner)
+            })
+            .cloned()
+            .collect();
+
+        Ok(page(
+            fortresses,
+            |fortress| (query.sort.key(fortress), fortress.id),
+            query.direction,
+            query.after,
+            query.limit,
+        ))
+    }
+
+    async fn collect_fortress_resource(
+        &self,
+        id: i32,
+        resource: Resource,
+        base: i64,
+        bonus_building_name: String,
+        expected_version: Option<i64>,
+    ) -> Result<Fortress, RepositoryError> {
+        let now = self.now();
+        let mut state = self.state()?;
+        let State {
+            fortresses,
+            buildings,
+            ..
+        } = &mut *state;
+        let fortress = fortresses
+            .get_mut(&id)
+            .filter(|fortress| fortress.deleted_at.is_none())
+            .ok_or(RepositoryError::FortressNotFound)?;
+        check_version(expected_version, fortress.version)?;
+        let collected = rules::collect_yield(
+            base,
+            buildings
+                .values()
+                .filter(|building| {
+                    building.fortress_id == id && building.name == bonus_building_name
+                })
+                .map(|building| building.level),
+        );
+        let amount = match resource {
+            Resource::Gold => &mut fortress.gold,
+            Resource::Food => &mut fortress.food,
+            Resource::Wood => &mut fortress.wood,
+            Resource::Energy => &mut fortress.energy,
+        };
+        let before = *amount;
+        *amount = amount.saturating_add(collected);
+        // What the capped stock actually gained.
+        let gained = *amount - before;
+        fortress.version += 1;
+        fortress.updated_at = now;
+        let fortress = fortress.clone();
+        state.insert_event(new_event(
+            EventKind::Collect,
+            id,
+            None,
+            &resource.delta(gained),
+            now,
+        ))?;
+        drop(state);
+
+        Ok(fortress)
+    }
+
+    async fn list_fortress_events(
+        &self,
+        fortress_id: i32,
+        before_id: Option<i64>,
+        limit: i64,
+    ) -> Result<Vec<FortressEvent>, RepositoryError> {
+        let state = self.state()?;
+        if !state.is_live_fortress(fortress_id) {
+            return Err(RepositoryError::FortressNotFound);
+        }
+        let events = state
+            .events
+            .range((
+                Bound::Unbounded,
+                before_id.map_or(Bound::Unbounded, Bound::Excluded),
+            ))
+            .rev()
+            .map(|(_, event)| event)
+            .filter(|event| event.fortress_id == Some(fortress_id))
+            .take(usize::try_from(limit).unwrap_or(0))
+            .cloned()
+            .collect();
+        drop(state);
+
+        Ok(events)
+    }
+
+    async fn scout_fortress(
+        &self,
+        spy_fortress_id: i32,
+        target_fortress_id: i32,
+        energy_cost: i64,
+        success: bool,
+        detected: bool,
+    ) -> Result<(Fortress, Option<ScoutReportWithBuildings>), RepositoryError> {
+        let now = self.now();
+        check_non_negative(&[energy_cost])?;
+        let mut state = self.state()?;
+        if !state.is_live_fortress(target_fortress_id) {
+            return Err(RepositoryError::FortressNotFound);
+        }
+        let spy = state.live_fortress_mut(spy_fortress_id)?;
+        if spy.energy < energy_cost {
+            return Err(RepositoryError::InsufficientResources {
+                missing: missing_resources(&Resource::Energy.delta(energy_cost), spy),
+            });
+        }
+        spy.energy -= energy_cost;
+        spy.version += 1;
+        spy.updated_at = now;
+        let spy = spy.clone();
+        state.insert_event(NewFortressEvent {
+            other_fortress_id: Some(target_fortress_id),
+            ..new_event(
+                EventKind::Scout,
+                spy_fortress_id,
+                None,
+                &Resource::Energy.delta(-energy_cost),
+                now,
+            )
+        })?;
+        if detected {
+            state.insert_event(NewFortressEvent {
+                other_fortress_id: Some(spy_fortress_id),
+                ..new_event(
+                    EventKind::Scouted,
+                    target_fortress_id,
+                    None,
+                    &Costs::ZERO,
+                    now,
+                )
+            })?;
+        }
+        if !success {
+            return Ok((spy, None));
+        }
+
+        let target = state
+            .fortresses
+            .get(&target_fortress_id)
+            .cloned()
+            .ok_or(RepositoryError::FortressNotFound)?;
+        state.last_scout_report_id = state
+            .last_scout_report_id
+            .checked_add(1)
+            .ok_or_else(out_of_range)?;
+        let report = ScoutReport {
+            id: state.last_scout_report_id,
+            spy_fortress_id,
+            target_fortress_id,
+            gold: target.gold,
+            food: target.food,
+            wood: target.wood,
+            energy: target.energy,
+            created_at: now,
+        };
+        let mut report_buildings: Vec<ScoutReportBuilding> = state
+            .buildings
+            .values()
+            .filter(|building| building.fortress_id == target_fortress_id)
+            .map(|building| ScoutReportBuilding {
+                report_id: report.id,
+                name: building.name.clone(),
+                level: building.level,
+            })
+            .collect();
+        report_buildings.sort_by(|a, b| a.name.cmp(&b.name));
+        let report = (report, report_buildings);
+        state.scout_reports.insert(report.0.id, report.clone());
+        drop(state);
+
+        Ok((spy, Some(report)))
+    }
+
+    async fn list_scout_reports(
+        &self,
+        spy_fortress_id: i32,
+        before_id: Option<i64>,
+        limit: i64,
+    ) -> Result<Vec<ScoutReportWithBuildings>, RepositoryError> {
+        let state = self.state()?;
+        if !state.is_live_fortress(spy_fortress_id) {
+            return Err(RepositoryError::FortressNotFound);
+        }
+        let reports = state
+            .scout_reports
+            .range((
+                Bound::Unbounded,
+                before_id.map_or(Bound::Unbounded, Bound::Excluded),
+            ))
+            .rev()
+            .map(|(_, report)| report)
+            .filter(|(report, _)| report.spy_fortress_id == spy_fortress_id)
+            .take(usize::try_from(limit).unwrap_or(0))
+            .cloned()
+            .collect();
+        drop(state);
+
+        Ok(reports)
+    }
+
+    async fn list_camps(&self, owner_id: String) -> Result<Vec<Camp>, RepositoryError> {
+        let mut camps: Vec<Camp> = self
+            .state()?
+            .camps
+            .values()
+            .filter(|camp| camp.owner_id == owner_id)
+            .cloned()
+            .collect();
+        camps.sort_by_key(|camp| camp.slot);
+
+        Ok(camps)
+    }
+
+    async fn spawn_camp(&self, new_camp: NewCamp) -> Result<Camp, RepositoryError> {
+        let now = self.now();
+        check_non_negative(&[
+            i64::from(new_camp.slot),
+            i64::from(new_camp.strength),
+            new_camp.gold,
+            new_camp.food,
+            new_camp.wood,
+            new_camp.energy,
+        ])?;
+        let mut state = self.state()?;
+        let existing = state
+            .camps
+            .values_mut()
+            .find(|camp| camp.owner_id == new_camp.owner_id && camp.slot == new_camp.slot);
+        if let Some(camp) = existing {
+            if camp.respawn_at.is_some_and(|respawn_at| respawn_at <= now) {
+                camp.strength = new_camp.strength;
+                camp.gold = new_camp.gold;
+                camp.food = new_camp.food;
+                camp.wood = new_camp.wood;
+                camp.energy = new_camp.energy;
+                camp.respawn_at = None;
+                camp.version += 1;
+                camp.updated_at = now;
+            }
+            return Ok(camp.clone());
+        }
+        state.last_camp_id = state.last_camp_id.checked_add(1).ok_or_else(out_of_range)?;
+        let camp = Camp {
+            id: state.last_camp_id,
+            owner_id: new_camp.owner_id,
+            slot: new_camp.slot,
+            strength: new_camp.strength,
+            gold: new_camp.gold,
+            food: new_camp.food,
+            wood: new_camp.wood,
+            energy: new_camp.energy,
+            respawn_at: None,
+            version: 0,
+            created_at: now,
+            updated_at: now,
+        };
+        state.camps.insert(camp.id, camp.clone());
+        drop(state);
+
+        Ok(camp)
+    }
+
+    async fn raid_camp(
+        &self,
+        camp_id: i32,
+        fortress_id: i32,
+        energy_cost: i64,
+        success: bool,
+        respawn_after: Duration,
+    ) -> Result<(Fortress, Camp), RepositoryError> {
+        let now = self.now();
+        check_non_negative(&[energy_cost])?;
+        let mut state = self.state()?;
+        let camp = state
+            .camps
+            .get(&camp_id)
+            .cloned()
+            .ok_or(RepositoryError::CampNotFound)?;
+        if camp.respawn_at.is_some() {
+            return Err(RepositoryError::CampNotAvailable);
+        }
+        let fortress = state.live_fortress_mut(fortress_id)?;
+        if fortress.energy < energy_cost {
+            return Err(RepositoryError::InsufficientResources {
+                missing: missing_resources(&Resource::Energy.delta(energy_cost), fortress),
+            });
+        }
+        let resources = after_raid(fortress, energy_cost, success.then_some(&camp));
+        let delta = resource_delta(fortress, &resources);
+        fortress.gold = resources.gold;
+        fortress.food = resources.food;
+        fortress.wood = resources.wood;
+        fortress.energy = resources.energy;
+        fortress.version += 1;
+        fortress.updated_at = now;
+        let fortress = fortress.clone();
+        state.insert_event(new_event(EventKind::Raid, fortress_id, None, &delta, now))?;
+        let camp = if success {
+            let camp = state
+                .camps
+                .get_mut(&camp_id)
+                .ok_or(RepositoryError::CampNotFound)?;
+            camp.respawn_at = Some(respawn_time(now, respawn_after));
+            camp.version += 1;
+            camp.updated_at = now;
+            camp.clone()
+        } else {
+            camp
+        };
+        drop(state);
+
+        Ok((fortress, camp))
+    }
+
+    async fn get_daily_reward(
+        &self,
+        owner_id: String,
+    ) -> Result<Option<DailyReward>, RepositoryError> {
+        Ok(self.state()?.daily_rewards.get(&owner_id).cloned())
+    }
+
+    async fn claim_daily_reward(
+        &self,
+        owner_id: String,
+        fortress_id: i32,
+        rewards: Vec<Costs>,
+    ) -> Result<(Fortress, DailyReward, Costs), RepositoryError> {
+        let now = self.now();
+        let mut state = self.state()?;
+        if state.live_fortress_mut(fortress_id)?.owner_id != owner_id {
+            return Err(RepositoryError::FortressNotFound);
+        }
+        let previous = state.daily_rewards.get(&owner_id);
+        let (streak, reward) = claim_streak(previous, now, &rewards)?;
+        let daily_reward = previous.map_or_else(
+            || DailyReward {
+                owner_id: owner_id.clone(),
+                streak,
+                last_claimed_at: now,
+                version: 0,
+                created_at: now,
+                updated_at: now,
+            },
+            |previous| DailyReward {
+                streak,
+                last_claimed_at: now,
+                version: previous.version + 1,
+                updated_at: now,
+                ..previous.clone()
+            },
+        );
+        let fortress = state.live_fortress_mut(fortress_id)?;
+        let resources = after_gain(fortress, &reward);
+        let delta = resource_delta(fortress, &resources);
+        fortress.gold = resources.gold;
+        fortress.food = resources.food;
+        fortress.wood = resources.wood;
+        fortress.energy = resources.energy;
+        fortress.version += 1;
+        fortress.updated_at = now;
+        let fortress = fortress.clone();
+        state.insert_event(new_event(
+            EventKind::DailyReward,
+            fortress_id,
+            None,
+            &delta,
+            now,
+        ))?;
+        state.daily_rewards.insert(owner_id, daily_reward.clone());
+        drop(state);
+
+        Ok((fortress, daily_reward, reward))
+    }
+}
@@ -0,0 +1,551 @@
+pub mod memory;
+pub mod sql;
+
+use rusty::{
+    Costs,
+    error_details::with_error_info,
+    models::{
+        Building, Camp, DailyReward, Fortress, FortressEvent, NewBuilding, NewCamp, NewFortress,
+        NewFortressEvent, ScoutReport, ScoutReportBuilding, UpdateBuilding, UpdateFortress,
+    },
+    rules,
+};
+use std::{
+    collections::HashMap,
+    fmt,
+    time::{Duration, SystemTime},
+};
+use time::{OffsetDateTime, PrimitiveDateTime};
+use tonic::{Code, Status};
+use tracing::{error, warn};
+
+/// What happened to a fortress, as stored in `fortress_events.kind`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventKind {
+    Create,
+    Collect,
+    Upgrade,
+    Delete,
+    Scout,
+    Scouted,
+    Raid,
+    DailyReward,
+}
+
+impl EventKind {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Create => "create",
+            Self::Collect => "collect",
+            Self::Upgrade => "upgrade",
+            Self::Delete => "delete",
+            Self::Scout => "scout",
+            Self::Scouted => "scouted",
+            Self::Raid => "raid",
+            Self::DailyReward => "daily_reward",
+        }
+    }
+
+    #[must_use]
+    pub fn parse(kind: &str) -> Option<Self> {
+        match kind {
+            "create" => Some(Self::Create),
+            "collect" => Some(Self::Collect),
+            "upgrade" => Some(Self::Upgrade),
+            "delete" => Some(Self::Delete),
+            "scout" => Some(Self::Scout),
+            "scouted" => Some(Self::Scouted),
+            "raid" => Some(Self::Raid),
+            "daily_reward" => Some(Self::DailyReward),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SortDirection {
+    #[default]
+    Ascending,
+    Descending,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BuildingSort {
+    #[default]
+    Id,
+    Level,
+}
+
+impl BuildingSort {
+    #[must_use]
+    pub fn key(self, building: &Building) -> i64 {
+        match self {
+            Self::Id => i64::from(building.id),
+            Self::Level => i64::from(building.level),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FortressSort {
+    #[default]
+    Id,
+    Gold,
+}
+
+impl FortressSort {
+    #[must_use]
+    pub fn key(self, fortress: &Fortress) -> i64 {
+        match self {
+            Self::Id => i64::from(fortress.id),
+            Self::Gold => fortress.gold,
+        }
+    }
+}
+
+/// Sort key and id of the last row of the previous page. Lists resume right
+/// after it, so rows inserted or deleted meanwhile don't shift the pages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cursor {
+    pub key: i64,
+    pub id: i32,
+}
+
+/// A report and the buildings it saw, ordered by name.
+pub type ScoutReportWithBuildings = (ScoutReport, Vec<ScoutReportBuilding>);
+
+#[derive(Debug, Clone, Default)]
+pub struct BuildingQuery {
+    pub name: Option<String>,
+    pub min_level: Option<i32>,
+    pub owner_id: Option<String>,
+    /// Any fortress when empty.
+    pub fortress_ids: Vec<i32>,
+    pub sort: BuildingSort,
+    pub direction: SortDirection,
+    pub after: Option<Cursor>,
+    pub limit: i64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct FortressQuery {
+    pub owner_id: Option<String>,
+    pub sort: FortressSort,
+    pub direction: SortDirection,
+    pub after: Option<Cursor>,
+    pub limit: i64,
+}
+
+/// History row for a `delta` in the fortress resources, negative when spent.
+fn new_event(
+    kind: EventKind,
+    fortress_id: i32,
+    building_id: Option<i32>,
+    delta: &Costs,
+    created_at: PrimitiveDateTime,
+) -> NewFortressEvent {
+    NewFortressEvent {
+        fortress_id,
+        building_id,
+        kind: kind.as_str().to_owned(),
+        gold: delta.gold,
+        food: delta.food,
+        wood: delta.wood,
+        energy: delta.energy,
+        created_at,
+        other_fortress_id: None,
+    }
+}
+
+#[derive(Debug)]
+pub enum RepositoryError {
+    BuildingNotFound,
+    FortressNotFound,
+    CampNotFound,
+    MaxLevel,
+    InsufficientResources {
+        missing: Costs,
+    },
+    ConcurrentUpdate,
+    FortressLimitReached,
+    FortressNotDeleted,
+    RestorePeriodExpired,
+    DuplicateBuilding,
+    /// A unique constraint the caller didn't name.
+    UniqueViolation,
+    NegativeValue,
+    CampNotAvailable,
+    DailyRewardClaimed,
+    Unavailable(String),
+    Backend(String),
+}
+
+impl RepositoryError {
+    const fn code(&self) -> Code {
+        match self {
+            Self::BuildingNotFound | Self::FortressNotFound | Self::CampNotFound => Code::NotFound,
+            Self::MaxLevel
+            | Self::InsufficientResources { .. }
+            | Self::FortressNotDeleted
+            | Self::RestorePeriodExpired
+            | Self::CampNotAvailable
+            | Self::DailyRewardClaimed => Code::FailedPrecondition,
+            Self::ConcurrentUpdate => Code::Aborted,
+            Self::FortressLimitReached => Code::ResourceExhausted,
+            Self::DuplicateBuilding | Self::UniqueViolation => Code::AlreadyExists,
+            Self::NegativeValue => Code::InvalidArgument,
+            Self::Unavailable(_) => Code::Unavailable,
+            Self::Backend(_) => Code::Internal,
+        }
+    }
+
+    /// `ErrorInfo.reason` of the status sent to clients.
+    const fn reason(&self) -> &'static str {
+        match self {
+            Self::BuildingNotFound => "BUILDING_NOT_FOUND",
+            Self::FortressNotFound => "FORTRESS_NOT_FOUND",
+            Self::CampNotFound => "CAMP_NOT_FOUND",
+            Self::MaxLevel => "MAX_LEVEL",
+            Self::InsufficientResources { .. } => "INSUFFICIENT_RESOURCES",
+            Self::ConcurrentUpdate => "CONCURRENT_UPDATE",
+            Self::FortressLimitReached => "FORTRESS_LIMIT_REACHED",
+            Self::FortressNotDeleted => "FORTRESS_NOT_DELETED",
+            Self::RestorePeriodExpired => "RESTORE_PERIOD_EXPIRED",
+            Self::DuplicateBuilding => "DUPLICATE_BUILDING",
+            Self::UniqueViolation => "ALREADY_EXISTS",
+            Self::NegativeValue => "NEGATIVE_VALUE",
+            Self::CampNotAvailable => "CAMP_NOT_AVAILABLE",
+            Self::DailyRewardClaimed => "DAILY_REWARD_CLAIMED",
+            Self::Unavailable(_) => "DATABASE_UNAVAILABLE",
+            Self::Backend(_) => "INTERNAL",
+        }
+    }
+}
+
+impl fmt::Display for RepositoryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BuildingNotFound => write!(f, "building not found"),
+            Self::FortressNotFound => write!(f, "fortress not found"),
+            Self::CampNotFound => write!(f, "camp not found"),
+            Self::MaxLevel => write!(f, "building already at max level"),
+            Self::InsufficientResources { .. } => write!(f, "insufficient resources"),
+            Self::ConcurrentUpdate => write!(f, "concurrent update; retry"),
+            Self::FortressLimitReached => write!(f, "fortress limit reached"),
+            Self::FortressNotDeleted => write!(f, "fortress is not deleted"),
+            Self::RestorePeriodExpired => write!(f, "fortress restore period expired"),
+            Self::DuplicateBuilding => {
+                write!(
+                    f,
+                    "a building with this name already exists in the fortress"
+                )
+            }
+            Self::UniqueViolation => write!(f, "already exists"),
+            Self::NegativeValue => write!(f, "resources and levels must not be negative"),
+            Self::CampNotAvailable => write!(f, "camp already raided"),
+            Self::DailyRewardClaimed => write!(f, "daily reward already claimed today"),
+            Self::Unavailable(e) => write!(f, "database unavailable: {e}"),
+            Self::Backend(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for RepositoryError {}
+
+impl From<RepositoryError> for Status {
+    fn from(value: RepositoryError) -> Self {
+        let mut metadata = HashMap::new();
+        let message = match &value {
+            RepositoryError::InsufficientResources { missing } => {
+                metadata.extend([
+                    ("missing_gold".to_owned(), missing.gold.to_string()),
+                    ("missing_food".to_owned(), missing.food.to_string()),
+                    ("missing_wood".to_owned(), missing.wood.to_string()),
+                    ("missing_energy".to_owned(), missing.energy.to_string()),
+                ]);
+                value.to_string()
+            }
+            // Database details stay in the logs, they would only leak the
+            // schema to players.
+            RepositoryError::Unavailable(e) => {
+                warn!("database unavailable: {e}");
+                "database unavailable; retry later".to_owned()
+            }
+            RepositoryError::Backend(e) => {
+                error!("database error: {e}");
+                "internal error".to_owned()
+            }
+            _ => value.to_string(),
+        };
+
+        with_error_info(value.code(), message, value.reason(), metadata)
+    }
+}
+
+/// What the fortress lacks to pay `costs`, 0 for the resources it has enough of.
+fn missing_resources(costs: &Costs, fortress: &Fortress) -> Costs {
+    Costs {
+        gold: costs.gold.saturating_sub(fortress.gold).max(0),
+        food: costs.food.saturating_sub(fortress.food).max(0),
+        wood: costs.wood.saturating_sub(fortress.wood).max(0),
+        energy: costs.energy.saturating_sub(fortress.energy).max(0),
+    }
+}
+
+/// The fortress resources once it paid `energy_cost` for a raid and took the
+/// loot of `camp` if any. Stocks are capped at `i64::MAX`.
+fn after_raid(fortress: &Fortress, energy_cost: i64, camp: Option<&Camp>) -> Costs {
+    let loot = |amount: fn(&Camp) -> i64| camp.map_or(0, amount);
+    Costs {
+        gold: fortress.gold.saturating_add(loot(|camp| camp.gold)),
+        food: fortress.food.saturating_add(loot(|camp| camp.food)),
+        wood: fortress.wood.saturating_add(loot(|camp| camp.wood)),
+        energy: (fortress.energy - energy_cost).saturating_add(loot(|camp| camp.energy)),
+    }
+}
+
+/// The fortress resources once credited `gain`, capped at `i64::MAX`.
+const fn after_gain(fortress: &Fortress, gain: &Costs) -> Costs {
+    Costs {
+        gold: fortress.gold.saturating_add(gain.gold),
+        food: fortress.food.saturating_add(gain.food),
+        wood: fortress.wood.saturating_add(gain.wood),
+        energy: fortress.energy.saturating_add(gain.energy),
+    }
+}
+
+/// The streak reached by claiming at `now` after the `previous` claims of the
+/// player, and its reward among `rewards`.
+fn claim_streak(
+    previous: Option<&DailyReward>,
+    now: PrimitiveDateTime,
+    rewards: &[Costs],
+) -> Result<(i32, Costs), RepositoryError> {
+    let day = |at: PrimitiveDateTime| rules::utc_day(at.assume_utc().unix_timestamp());
+    let streak = previous
+        .map_or(Some(1), |previous| {
+            rules::next_streak(previous.streak, day(previous.last_claimed_at), day(now))
+        })
+        .ok_or(RepositoryError::DailyRewardClaimed)?;
+    let reward = rules::daily_reward(rewards, streak);
+    check_non_negative(&[reward.gold, reward.food, reward.wood, reward.energy])?;
+
+    Ok((streak, reward))
+}
+
+/// Change from the resources of `fortress` to `resources`.
+const fn resource_delta(fortress: &Fortress, resources: &Costs) -> Costs {
+    Costs {
+        gold: resources.gold - fortress.gold,
+        food: resources.food - fortress.food,
+        wood: resources.wood - fortress.wood,
+        energy: resources.energy - fortress.energy,
+    }
+}
+
+/// UTC time as stored in timestamp columns.
+fn timestamp(at: SystemTime) -> PrimitiveDateTime {
+    let at = OffsetDateTime::from(at);
+    PrimitiveDateTime::new(at.date(), at.time())
+}
+
+/// Fortresses deleted before the returned instant are past their grace period.
+/// `None` means the grace period reaches further back than any representable
+/// time, so every deleted fortress can still be restored.
+fn grace_period_start(now: PrimitiveDateTime, grace_period: Duration) -> Option<PrimitiveDateTime> {
+    let grace_period = time::Duration::try_from(grace_period).ok()?;
+    now.checked_sub(grace_period)
+}
+
+/// When a camp raided at `now` can respawn, saturating at the latest
+/// representable time.
+fn respawn_time(now: PrimitiveDateTime, respawn_after: Duration) -> PrimitiveDateTime {
+    time::Duration::try_from(respawn_after)
+        .ok()
+        .and_then(|respawn_after| now.checked_add(respawn_after))
+        .unwrap_or(PrimitiveDateTime::MAX)
+}
+
+/// Same rule as the `CHECK` constraints of the database.
+fn check_non_negative(values: &[i64]) -> Result<(), RepositoryError> {
+    if values.iter().any(|value| *value < 0) {
+        return Err(RepositoryError::NegativeValue);
+    }
+
+    Ok(())
+}
+
+/// Rejects the write if the row is no longer at the version the caller read.
+const fn check_version(expected: Option<i64>, actual: i64) -> Result<(), RepositoryError> {
+    match expected {
+        Some(expected) if expected != actual => Err(RepositoryError::ConcurrentUpdate),
+        _ => Ok(()),
+    }
+}
+
+/// Persistence used by the crud services.
+///
+/// `upgrade_building_atomic`, `collect_fortress_resource`,
+/// `create_fortress_with_buildings`, `scout_fortress`, `spawn_camp`,
+/// `raid_camp` and `claim_daily_reward` must behave as a single transaction:
+/// either every row is written or none is.
+///
+/// Every write increments the `version` of the rows it touches and sets their
+/// `updated_at`. Methods taking an `expected_version` fail with
+/// `ConcurrentUpdate` when it doesn't match.
+///
+/// Creating, collecting, upgrading, scouting, raiding, claiming daily rewards
+/// and deleting also append a `FortressEvent` in the same transaction. Events
+/// are kept forever and never modified afterwards, except for their fortress,
+/// building and other fortress ids, which are cleared once those rows are
+/// purged.
+///
+/// Fortress resources, building levels and camp values are never negative, a
+/// fortress holds at most one building of each name and a player at most one
+/// camp per slot.
+///
+/// Batch reads return rows ordered by id and leave out the ids that don't
+/// match a live row, rather than failing.
+///
+/// `delete_fortress` only marks the fortress as deleted. Deleted fortresses
+/// and their buildings are hidden from every other method until
+/// `restore_fortress` brings them back, or `purge_deleted_fortresses` removes
+/// them for good once the grace period is over.
+#[tonic::async_trait]
+pub trait Repository: Send + Sync {
+    async fn create_building(&self, new_building: NewBuilding)
+    -> Result<Building, RepositoryError>;
+    async fn get_building(&self, id: i32) -> Result<Building, RepositoryError>;
+    async fn get_buildings(&self, ids: Vec<i32>) -> Result<Vec<Building>, RepositoryError>;
+    async fn get_building_with_fortress(
+        &self,
+        id: i32,
+    ) -> Result<(Building, Fortress), RepositoryError>;
+    async fn update_building(
+        &self,
+        id: i32,
+        update_building: UpdateBuilding,
+        expected_version: Option<i64>,
+    ) -> Result<Building, RepositoryError>;
+    async fn delete_building(&self, id: i32) -> Result<bool, RepositoryError>;
+    /// At most `query.limit` buildings, ordered by the sort key then by id.
+    async fn list_buildings(&self, query: BuildingQuery) -> Result<Vec<Building>, RepositoryError>;
+    async fn list_buildings_by_fortress(
+        &self,
+        fortress_id: i32,
+    ) -> Result<Vec<Building>, RepositoryError>;
+    async fn upgrade_building_atomic(
+        &self,
+        building_id: i32,
+        costs: Costs,
+        expected_level: Option<i32>,
+        expected_version: Option<i64>,
+        max_level: i32,
+    ) -> Result<(Fortress, Building), RepositoryError>;
+
+    async fn create_fortress(&self, new_fortress: NewFortress)
+    -> Result<Fortress, RepositoryError>;
+    async fn create_fortress_with_buildings(
+        &self,
+        new_fortress: NewFortress,
+        building_names: Vec<String>,
+        max_fortresses_per_owner: Option<i64>,
+    ) -> Result<(Fortress, Vec<Building>), RepositoryError>;
+    async fn get_fortress(&self, id: i32) -> Result<Fortress, RepositoryError>;
+    async fn get_fortresses(&self, ids: Vec<i32>) -> Result<Vec<Fortress>, RepositoryError>;
+    async fn get_fortress_with_buildings(
+        &self,
+        id: i32,
+    ) -> Result<(Fortress, Vec<Building>), RepositoryError>;
+    async fn update_fortress(
+        &self,
+        id: i32,
+        update_fortress: UpdateFortress,
+        expected_version: Option<i64>,
+    ) -> Result<Fortress, RepositoryError>;
+    async fn delete_fortress(&self, id: i32) -> Result<bool, RepositoryError>;
+    async fn restore_fortress(
+        &self,
+        id: i32,
+        owner_id: Option<String>,
+        grace_period: Duration,
+        max_fortresses_per_owner: Option<i64>,
+    ) -> Result<Fortress, RepositoryError>;
+    async fn purge_deleted_fortresses(
+        &self,
+        grace_period: Duration,
+    ) -> Result<usize, RepositoryError>;
+    /// At most `query.limit` fortresses, ordered by the sort key then by id.
+    async fn list_fortresses(&self, query: FortressQuery)
+    -> Result<Vec<Fortress>, RepositoryError>;
+    async fn collect_fortress_resource(
+        &self,
+        id: i32,
+        resource: rules::Resource,
+        base: i64,
+        bonus_building_name: String,
+        expected_version: Option<i64>,
+    ) -> Result<Fortress, RepositoryError>;
+    /// Newest first, starting below `before_id` when it is set.
+    async fn list_fortress_events(
+        &self,
+        fortress_id: i32,
+        before_id: Option<i64>,
+        limit: i64,
+    ) -> Result<Vec<FortressEvent>, RepositoryError>;
+    /// Takes `energy_cost` from the spying fortress and appends a `Scout`
+    /// event to it, plus a `Scouted` event to the target when `detected`. On
+    /// `success`, also stores a report of the target as it is now.
+    async fn scout_fortress(
+        &self,
+        spy_fortress_id: i32,
+        target_fortress_id: i32,
+        energy_cost: i64,
+        success: bool,
+        detected: bool,
+    ) -> Result<(Fortress, Option<ScoutReportWithBuildings>), RepositoryError>;
+    /// Reports of the spying fortress, newest first, starting below
+    /// `before_id` when it is set.
+    async fn list_scout_reports(
+        &self,
+        spy_fortress_id: i32,
+        before_id: Option<i64>,
+        limit: i64,
+    ) -> Result<Vec<ScoutReportWithBuildings>, RepositoryError>;
+
+    /// Camps of the player, ordered by slot.
+    async fn list_camps(&self, owner_id: String) -> Result<Vec<Camp>, RepositoryError>;
+    /// Puts `new_camp` in its slot when the slot is empty or holds a camp
+    /// past its respawn time. Otherwise returns the camp in the slot as is.
+    async fn spawn_camp(&self, new_camp: NewCamp) -> Result<Camp, RepositoryError>;
+    /// Takes `energy_cost` from the fortress and appends a `Raid` event to it.
+    /// On `success`, also credits the camp loot to the fortress and sets the
+    /// camp `respawn_at` to `respawn_after` from now. Fails with
+    /// `CampNotAvailable` once the camp has been raided.
+    async fn raid_camp(
+        &self,
+        camp_id: i32,
+        fortress_id: i32,
+        energy_cost: i64,
+        success: bool,
+        respawn_after: Duration,
+    ) -> Result<(Fortress, Camp), RepositoryError>;
+
+    /// Daily reward streak of the player, `None` before their first claim.
+    async fn get_daily_reward(
+        &self,
+        owner_id: String,
+    ) -> Result<Option<DailyReward>, RepositoryError>;
+    /// Extends the streak of the player, or starts a new one after a missed
+    /// UTC day, credits the reward of the reached day among `rewards` to the
+    /// fortress and appends a `DailyReward` event to it. Fails with
+    /// `FortressNotFound` unless the fortress belongs to the player, and with
+    /// `DailyRewardClaimed` once the current UTC day is claimed.
+    async fn claim_daily_reward(
+        &self,
+        owner_id: String,
+        fortress_id: i32,
+        rewards: Vec<Costs>,
+    ) -> Result<(Fortress, DailyReward, Costs), RepositoryError>;
+}
@@ -0,0 +1,1285 @@
+use super::{
+    BuildingQuery, BuildingSort, EventKind, FortressQuery, FortressSort, Repository,
+    RepositoryError, ScoutReportWithBuildings, SortDirection, after_gain, after_raid,
+    check_non_negative, check_version, claim_streak, grace_period_start, missing_resources,
+    new_event, resource_delta, respawn_time, timestamp,
+};
+use diesel::{
+    connection::SimpleConnection,
+    dsl,
+    prelude::*,
+    r2d2::{self, CustomizeConnection, Pool},
+    result::{DatabaseErrorKind, Error},
+    sql_types::Text,
+};
+use rusty::{
+    Costs,
+    clock::Clock,
+    connection::{AnyConnection, AnyConnectionManager},
+    models::{
+        Building, Camp, DailyReward, Fortress, FortressEvent, NewBuilding, NewCamp, NewFortress,
+        NewFortressEvent, NewScoutReport, ScoutReport, ScoutReportBuilding, UpdateBuilding,
+        UpdateFortress,
+    },
+    rules::{self, Resource},
+    schema::{
+        buildings, camps, daily_rewards, fortress_events, fortresses, scout_report_buildings,
+        scout_reports,
+    },
+};
+use std::{sync::Arc, time::Duration};
+use time::PrimitiveDateTime;
+
+pub type DbPool = Pool<AnyConnectionManager>;
+
+impl From<diesel::result::Error> for RepositoryError {
+    fn from(value: diesel::result::Error) -> Self {
+        // `SQLite` doesn't name the violated constraint, so unique violations
+        // are named by the callers writing the rows, see
+        // `building_name_taken`. Every check constraint is about negative
+        // values, see the migrations.
+        match value {
+            Error::DatabaseError(DatabaseErrorKind::UniqueViolation, _) => Self::UniqueViolation,
+            Error::DatabaseError(DatabaseErrorKind::CheckViolation, _) => Self::NegativeValue,
+            Error::DatabaseError(DatabaseErrorKind::ForeignKeyViolation, _) => {
+                Self::FortressNotFound
+            }
+            Error::DatabaseError(DatabaseErrorKind::SerializationFailure, _) => {
+                Self::ConcurrentUpdate
+            }
+            value => Self::Backend(value.to_string()),
+        }
+    }
+}
+
+pub struct PoolConfig {
+    pub max_size: u32,
+    pub connection_timeout: Duration,
+    pub statement_timeout: Option<Duration>,
+}
+
+/// Postgres aborts statements running longer than the timeout. `SQLite` has
+/// no such setting, the closest is how long a statement waits for a lock.
+#[derive(Debug)]
+struct StatementTimeout(Duration);
+
+impl CustomizeConnection<AnyConnection, r2d2::Error> for StatementTimeout {
+    fn on_acquire(&self, conn: &mut AnyConnection) -> Result<(), r2d2::Error> {
+        let millis = self.0.as_millis();
+        let sql = match conn {
+            AnyConnection::Postgresql(_) => format!("SET statement_timeout = {millis}"),
+            AnyConnection::Sqlite(_) => format!("PRAGMA busy_timeout = {millis}"),
+        };
+        conn.batch_execute(&sql).map_err(r2d2::Error::QueryError)
+    }
+}
+
+/// # Errors
+///
+/// Returns an error if the pool can't open its first connections.
+pub fn build_pool(database_url: String, config: &PoolConfig) -> Result<DbPool, r2d2::PoolError> {
+    let manager = AnyConnectionManager::new(database_url);
+    let mut builder = Pool::builder()
+        .max_size(config.max_size)
+        .connection_timeout(config.connection_timeout);
+    if let Some(statement_timeout) = config.statement_timeout {
+        builder = builder.connection_customizer(Box::new(StatementTimeout(statement_timeout)));
+    }
+
+    builder.build(manager)
+}
+
+#[dsl::auto_type]
+fn live_fortresses() -> _ {
+    fortresses::table.filter(fortresses::deleted_at.is_null())
+}
+
+#[dsl::auto_type]
+fn live_buildings() -> _ {
+    buildings::table.filter(buildings::fortress_id.eq_any(live_fortresses().select(fortresses::id)))
+}
+
+fn ensure_live_fortress(conn: &mut AnyConnection, id: i32) -> Result<(), RepositoryError> {
+    let count: i64 = live_fortresses()
+        .filter(fortresses::id.eq(id))
+        .count()
+        .get_result(conn)?;
+    if count == 0 {
+        return Err(RepositoryError::FortressNotFound);
+    }
+
+    Ok(())
+}
+
+fn insert_event(conn: &mut AnyConnection, event: NewFortressEvent) -> Result<(), RepositoryError> {
+    diesel::insert_into(fortress_events::table)
+        .values(event)
+        .execute(conn)?;
+
+    Ok(())
+}
+
+/// Writes the streak reached by a claim, inserting the row on the first claim
+/// of the player.
+fn save_daily_reward(
+    conn: &mut AnyConnection,
+    owner_id: &str,
+    previous: Option<DailyReward>,
+    streak: i32,
+    now: PrimitiveDateTime,
+) -> Result<DailyReward, RepositoryError> {
+    let Some(previous) = previous else {
+        let daily_reward = diesel::insert_into(daily_rewards::table)
+            .values((
+                daily_rewards::owner_id.eq(owner_id),
+                daily_rewards::streak.eq(streak),
+                daily_rewards::last_claimed_at.eq(now),
+                daily_rewards::created_at.eq(now),
+                daily_rewards::updated_at.eq(now),
+            ))
+            .get_result::<DailyReward>(conn)?;
+        return Ok(daily_reward);
+    };
+    diesel::update(daily_rewards::table)
+        .filter(daily_rewards::owner_id.eq(owner_id))
+        .filter(daily_rewards::version.eq(previous.version))
+        .set((
+            daily_rewards::streak.eq(streak),
+            daily_rewards::last_claimed_at.eq(now),
+            daily_rewards::version.eq(daily_rewards::version + 1),
+            daily_rewards::updated_at.eq(now),
+        ))
+        .get_result::<DailyReward>(conn)
+        .optional()?
+        .ok_or(RepositoryError::ConcurrentUpdate)
+}
+
+fn camp_in_slot(
+    conn: &mut AnyConnection,
+    owner_id: &str,
+    slot: i32,
+) -> Result<Option<Camp>, RepositoryError> {
+    let camp = camps::table
+        .filter(camps::owner_id.eq(owner_id))
+        .filter(camps::slot.eq(slot))
+        .select(Camp::as_select())
+        .first(conn)
+        .optional()?;
+
+    Ok(camp)
+}
+
+fn spawn_camp(
+    conn: &mut AnyConnection,
+    new_camp: NewCamp,
+    now: PrimitiveDateTime,
+) -> Result<Camp, RepositoryError> {
+    let Some(camp) = camp_in_slot(conn, &new_camp.owner_id, new_camp.slot)? else {
+        let camp = diesel::insert_into(camps::table)
+            .values((
+                new_camp,
+                camps::created_at.eq(now),
+                camps::updated_at.eq(now),
+            ))
+            .get_result::<Camp>(conn)?;
+        return Ok(camp);
+    };
+    if camp.respawn_at.is_none_or(|respawn_at| respawn_at > now) {
+        return Ok(camp);
+    }
+    let respawned = diesel::update(camps::table)
+        .filter(camps::id.eq(camp.id))
+        .filter(camps::version.eq(camp.version))
+        .set((
+            new_camp,
+            camps::respawn_at.eq(None::<PrimitiveDateTime>),
+            camps::version.eq(camps::version + 1),
+            camps::updated_at.eq(now),
+        ))
+        .get_result::<Camp>(conn)
+        .optional()?;
+    match respawned {
+        Some(camp) => Ok(camp),
+        // Respawned by another call meanwhile.
+        None => {
+            camp_in_slot(conn, &camp.owner_id, camp.slot)?.ok_or(RepositoryError::ConcurrentUpdate)
+        }
+    }
+}
+
+fn insert_fortress(
+    conn: &mut AnyConnection,
+    new_fortress: NewFortress,
+    now: PrimitiveDateTime,
+) -> Result<Fortress, RepositoryError> {
+    let fortress = diesel::insert_into(fortresses::table)
+        .values((
+            new_fortress,
+            fortresses::created_at.eq(now),
+            fortresses::updated_at.eq(now),
+        ))
+        .get_result::<Fortress>(conn)?;
+    let starting_resources = Costs {
+        gold: fortress.gold,
+        food: fortress.food,
+        wood: fortress.wood,
+        energy: fortress.energy,
+    };
+    insert_event(
+        conn,
+        new_event(
+            EventKind::Create,
+            fortress.id,
+            None,
+            &starting_resources,
+            now,
+        ),
+    )?;
+
+    Ok(fortress)
+}
+
+/// The only unique constraint of buildings is on their name in the fortress.
+fn building_name_taken(error: diesel::result::Error) -> RepositoryError {
+    match RepositoryError::from(error) {
+        RepositoryError::UniqueViolation => RepositoryError::DuplicateBuilding,
+        error => error,
+    }
+}
+
+fn insert_building(
+    conn: &mut AnyConnection,
+    new_building: NewBuilding,
+    now: PrimitiveDateTime,
+) -> Result<Building, RepositoryError> {
+    let building = diesel::insert_into(buildings::table)
+        .values((
+            new_building,
+            buildings::created_at.eq(now),
+            buildings::updated_at.eq(now),
+        ))
+        .get_result::<Building>(conn)
+        .map_err(building_name_taken)?;
+
+    Ok(building)
+}
+
+pub struct SqlRepository {
+    pool: DbPool,
+    clock: Arc<dyn Clock>,
+}
+
+impl SqlRepository {
+    #[must_use]
+    pub fn new(pool: DbPool, clock: Arc<dyn Clock>) -> Self {
+        Self { pool, clock }
+    }
+
+    fn now(&self) -> PrimitiveDateTime {
+        timestamp(self.clock.now())
+    }
+
+    /// Diesel is synchronous, so queries run on the blocking thread pool to
+    /// keep the tokio workers free while waiting on the database.
+    async fn run<T, F>(&self, f: F) -> Result<T, RepositoryError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut AnyConnection) -> Result<T, RepositoryError> + Send + 'static,
+    {
+        let pool = self.pool.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut conn = pool
+                .get()
+                .map_err(|e| RepositoryError::Unavailable(e.to_string()))?;
+            f(&mut conn)
+        })
+        .await
+        .map_err(|e| RepositoryError::Backend(e.to_string()))?
+    }
+}
+
+#[tonic::async_trait]
+impl Repository for SqlRepository {
+    async fn create_building(
+        &self,
+        new_building: NewBuilding,
+    ) -> Result<Building, RepositoryError> {
+        let now = self.now();
+        self.run(move |conn| {
+            conn.transaction(|conn| {
+                ensure_live_fortress(conn, new_building.fortress_id)?;
+                insert_building(conn, new_building, now)
+            })
+        })
+        .await
+    }
+
+    async fn get_building(&self, id: i32) -> Result<Building, RepositoryError> {
+        self.run(move |conn| {
+            live_buildings()
+                .filter(buildings::id.eq(id))
+                .select(Building::as_select())
+                .first(conn)
+                .optional()?
+                .ok_or(RepositoryError::BuildingNotFound)
+        })
+        .await
+    }
+
+    async fn get_buildings(&self, ids: Vec<i32>) -> Result<Vec<Building>, RepositoryError> {
+        self.run(move |conn| {
+            let buildings = live_buildings()
+                .filter(buildings::id.eq_any(ids))
+                .order(buildings::id)
+                .select(Building::as_select())
+                .load(conn)?;
+
+            Ok(buildings)
+        })
+        .await
+    }
+
+    async fn get_building_with_fortress(
+        &self,
+        id: i32,
+    ) -> Result<(Building, Fortress), RepositoryError> {
+        self.run(move |conn| {
+            buildings::table
+                .inner_join(fortresses::table)
+                .filter(buildings::id.eq(id))
+                .filter(fortresses::deleted_at.is_null())
+                .select((Building::as_select(), Fortress::as_select()))
+                .first(conn)
+                .optional()?
+                .ok_or(RepositoryError::BuildingNotFound)
+        })
+        .await
+    }
+
+    async fn update_building(
+        &self,
+        id: i32,
+        update_building: UpdateBuilding,
+        expected_version: Option<i64>,
+    ) -> Result<Building, RepositoryError> {
+        let now = self.now();
+        self.run(move |conn| {
+            conn.transaction(|conn| {
+                let building = live_buildings()
+                    .filter(buildings::id.eq(id))
+                    .select(Building::as_select())
+                    .first(conn)
+                    .optional()?
+                    .ok_or(RepositoryError::BuildingNotFound)?;
+                check_version(expected_version, building.version)?;
+                if let Some(fortress_id) = update_building.fortress_id {
+                    ensure_live_fortress(conn, fortress_id)?;
+                }
+                diesel::update(buildings::table)
+                    .filter(buildings::id.eq(id))
+                    .filter(buildings::version.eq(building.version))
+                    .set((
+                        update_building,
+                        buildings::version.eq(buildings::version + 1),
+                        buildings::updated_at.eq(now),
+                    ))
+                    .get_result::<Building>(conn)
+                    .optional()
+                    .map_err(building_name_taken)?
+                    .ok_or(RepositoryError::ConcurrentUpdate)
+            })
+        })
+        .await
+    }
+
+    async fn delete_building(&self, id: i32) -> Result<bool, RepositoryError> {
+        self.run(move |conn| {
+            let building_delete_result = diesel::delete(live_buildings())
+                .filter(buildings::id.eq(id))
+                .execute(conn)?;
+
+            Ok(building_delete_result != 0)
+        })
+        .await
+    }
+
+    async fn list_buildings(&self, query: BuildingQuery) -> Result<Vec<Building>, RepositoryError> {
+        self.run(move |conn| {
+            let mut statement = live_buildings()
+                .select(Building::as_select())
+                .limit(query.limit)
+                .into_boxed();
+            if let Some(name) = query.name {
+                statement = statement.filter(buildings::name.eq(name));
+            }
+            if let Some(min_level) = query.min_level {
+                statement = statement.filter(buildings::level.ge(min_level));
+            }
+            if let Some(owner) = query.owner_id {
+                statement = statement.filter(
+                    buildings::fortress_id.eq_any(
+                        fortresses::table
+                            .filter(fortresses::owner_id.eq(owner))
+                            .select(fortresses::id),
+                    ),
+                );
+            }
+            if !query.fortress_ids.is_empty() {
+                statement = statement.filter(buildings::fortress_id.eq_any(query.fortress_ids));
+            }
+            // `key >= k AND (key > k OR id > i)` rather than a plain `OR`,
+            // so the database can start a range scan of `(key, id)` at `k`.
+            if let Some(after) = query.after {
+                let level = i32::try_from(after.key).unwrap_or(if after.key < 0 {
+                    i32::MIN
+                } else {
+                    i32::MAX
+                });
+                statement = match (query.sort, query.direction) {
+                    (BuildingSort::Id, SortDirection::Ascending) => {
+                        statement.filter(buildings::id.gt(after.id))
+                    }
+                    (BuildingSort::Id, SortDirection::Descending) => {
+                        statement.filter(buildings::id.lt(after.id))
+                    }
+                    (BuildingSort::Level, SortDirection::Ascending) => statement.filter(
+                        buildings::level
+                            .ge(level)
+                            .and(buildings::level.gt(level).or(buildings::id.gt(after.id))),
+                    ),
+                    (BuildingSort::Level, SortDirection::Descending) => statement.filter(
+                        buildings::level
+                            .le(level)
+                            .and(buildings::level.lt(level).or(buildings::id.lt(after.id))),
+                    ),
+                };
+            }
+            statement = match (query.sort, query.direction) {
+                (BuildingSort::Id, SortDirection::Ascending) => {
+                    statement.order(buildings::id.asc())
+                }
+                (BuildingSort::Id, SortDirection::Descending) => {
+                    statement.order(buildings::id.desc())
+                }
+                (BuildingSort::Level, SortDirection::Ascending) => {
+                    statement.order((buildings::level.asc(), buildings::id.asc()))
+                }
+                (BuildingSort::Level, SortDirection::Descending) => {
+                    statement.order((buildings::level.desc(), buildings::id.desc()))
+                }
+            };
+            let buildings = statement.load(conn)?;
+
+            Ok(buildings)
+        })
+        .await
+    }
+
+    async fn list_buildings_by_fortress(
+        &self,
+        fortress_id: i32,
+    ) -> Result<Vec<Building>, RepositoryError> {
+        self.run(move |conn| {
+            let buildings = live_buildings()
+                .filter(buildings::fortress_id.eq(fortress_id))
+                .select(Building::as_select())
+                .get_results(conn)?;
+
+            Ok(buildings)
+        })
+        .await
+    }
+
+    async fn upgrade_building_atomic(
+        &self,
+        building_id: i32,
+        costs: Costs,
+        expected_level: Option<i32>,
+        expected_version: Option<i64>,
+        max_level: i32,
+    ) -> Result<(Fortress, Building), RepositoryError> {
+        let now = self.now();
+        self.run(move |conn| {
+            conn.transaction(|conn| {
+                let building = live_buildings()
+                    .filter(buildings::id.eq(building_id))
+                    .select(Building::as_select())
+                    .first(conn)
+                    .optional()?
+                    .ok_or(RepositoryError::BuildingNotFound)?;
+                if let Some(expected) = expected_level
+                    && expected != building.level
+                {
+                    return Err(RepositoryError::ConcurrentUpdate);
+                }
+                check_version(expected_version, building.version)?;
+                if building.level >= max_level {
+                    return Err(RepositoryError::MaxLevel);
+                }
+                let fortress_id = building.fortress_id;
+                let fortress = diesel::update(live_fortresses())
+                    .filter(fortresses::id.eq(fortress_id))
+                    .filter(fortresses::gold.ge(costs.gold))
+                    .filter(fortresses::food.ge(costs.food))
+                    .filter(fortresses::wood.ge(costs.wood))
+                    .filter(fortresses::energy.ge(costs.energy))
+                    .set((
+                        fortresses::gold.eq(fortresses::gold - costs.gold),
+                        fortresses::food.eq(fortresses::food - costs.food),
+                        fortresses::wood.eq(fortresses::wood - costs.wood),
+                        fortresses::energy.eq(fortresses::energy - costs.energy),
+                        fortresses::version.eq(fortresses::version + 1),
+                        fortresses::updated_at.eq(now),
+                    ))
+                    .get_result::<Fortress>(conn)
+                    .optional()?;
+                let Some(fortress) = fortress else {
+                    let fortress = live_fortresses()
+                        .filter(fortresses::id.eq(fortress_id))
+                        .select(Fortress::as_select())
+                        .first(conn)
+                        .optional()?
+                        .ok_or(RepositoryError::FortressNotFound)?;
+                    return Err(RepositoryError::InsufficientResources {
+                        missing: missing_resources(&costs, &fortress),
+                    });
+                };
+                let upgraded_building = diesel::update(buildings::table)
+                    .filter(buildings::id.eq(building_id))
+                    .filter(buildings::version.eq(building.version))
+                    .set((
+                        buildings::level.eq(buildings::level + 1),
+                        buildings::version.eq(buildings::version + 1),
+                        buildings::updated_at.eq(now),
+                    ))
+                    .get_result::<Building>(conn)
+                    .optional()?
+                    .ok_or(RepositoryError::ConcurrentUpdate)?;
+                let spent = Costs {
+                    gold: -costs.gold,
+                    food: -costs.food,
+                    wood: -costs.wood,
+                    energy: -costs.energy,
+                };
+                insert_event(
+                    conn,
+                    new_event(
+                        EventKind::Upgrade,
+                        fortress_id,
+                        Some(building_id),
+                        &spent,
+                        now,
+                    ),
+                )?;
+
+                Ok((fortress, upgraded_building))
+            })
+        })
+        .await
+    }
+
+    async fn create_fortress(
+        &self,
+        new_fortress: NewFortress,
+    ) -> Result<Fortress, RepositoryError> {
+        let now = self.now();
+        self.run(move |conn| conn.transaction(|conn| insert_fortress(conn, new_fortress, now)))
+            .await
+    }
+
+    async fn create_fortress_with_buildings(
+        &self,
+        new_fortress: NewFortress,
+        building_names: Vec<String>,
+        max_fortresses_per_owner: Option<i64>,
+    ) -> Result<(Fortress, Vec<Building>), RepositoryError> {
+        let now = self.now();
+        self.run(move |conn| {
+            conn.transaction(|conn| {
+                let owner_id = new_fortress.owner_id.clone();
+                // SQLite already serializes writers from the insert onward,
+                // Postgres needs a lock so concurrent creates see each other.
+                if let AnyConnection::Postgresql(conn) = conn {
+                    diesel::sql_query("SELECT pg_advisory_xact_lock(hashtext($1))")
+                        .bind::<Text, _>(&owner_id)
+                        .execute(conn)?;
+                }
+                let fortress = insert_fortress(conn, new_fortress, now)?;
+                if let Some(max) = max_fortresses_per_owner {
+                    let count: i64 = live_fortresses()
+                        .filter(fortresses::owner_id.eq(owner_id))
+                        .count()
+                        .get_result(conn)?;
+                    if count > max {
+                        return Err(RepositoryError::FortressLimitReached);
+                    }
+                }
+                let buildings = building_names
+                    .into_iter()
+                    .map(|name| insert_building(conn, NewBuilding::new(name, fortress.id), now))
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                Ok((fortress, buildings))
+            })
+        })
+        .await
+    }
+
+    async fn get_fortress(&self, id: i32) -> Result<Fortress, RepositoryError> {
+        self.run(move |conn| {
+            live_fortresses()
+                .filter(fortresses::id.eq(id))
+                .select(Fortress::as_select())
+                .first(conn)
+                .optional()?
+                .ok_or(RepositoryError::FortressNotFound)
+        })
+        .await
+    }
+
+    async fn get_fortresses(&self, ids: Vec<i32>) -> Result<Vec<Fortress>, RepositoryError> {
+        self.run(move |conn| {
+            let fortresses = live_fortresses()
+                .filter(fortresses::id.eq_any(ids))
+                .order(fortresses::id)
+                .select(Fortress::as_select())
+                .load(conn)?;
+
+            Ok(fortresses)
+        })
+        .await
+    }
+
+    async fn get_fortress_with_buildings(
+        &self,
+        id: i32,
+    ) -> Result<(Fortress, Vec<Building>), RepositoryError> {
+        self.run(move |conn| {
+            // A fortress without buildings still comes back as one row.
+            let rows: Vec<(Fortress, Option<Building>)> = fortresses::table
+                .left_join(buildings::table)
+                .filter(fortresses::id.eq(id))
+                .filter(fortresses::deleted_at.is_null())
+                .order(buildings::id)
+                .select((Fortress::as_select(), Option::<Building>::as_select()))
+                .load(conn)?;
+            let mut rows = rows.into_iter();
+            let (fortress, first) = rows.next().ok_or(RepositoryError::FortressNotFound)?;
+            let buildings = first
+                .into_iter()
+                .chain(rows.filter_map(|(_, building)| building))
+                .collect();
+
+            Ok((fortress, buildings))
+        })
+        .await
+    }
+
+    async fn update_fortress(
+        &self,
+        id: i32,
+        update_fortress: UpdateFortress,
+        expected_version: Option<i64>,
+    ) -> Result<Fortress, RepositoryError> {
+        let now = self.now();
+        self.run(move |conn| {
+            conn.transaction(|conn| {
+                let fortress = live_fortresses()
+                    .filter(fortresses::id.eq(id))
+                    .select(Fortress::as_select())
+                    .first(conn)
+                    .optional()?
+                    .ok_or(RepositoryError::FortressNotFound)?;
+                check_version(expected_version, fortress.version)?;
+                diesel::update(fortresses::table)
+                    .filter(fortresses::id.eq(id))
+                    .filter(fortresses::version.eq(fortress.version))
+                    .set((
+                        update_fortress,
+                        fortresses::version.eq(fortresses::version + 1),
+                        fortresses::updated_at.eq(now),
+                    ))
+                    .get_result::<Fortress>(conn)
+                    .optional()?
+                    .ok_or(RepositoryError::ConcurrentUpdate)
+            })
+        })
+        .await
+    }
+
+    async fn delete_fortress(&self, id: i32) -> Result<bool, RepositoryError> {
+        let now = self.now();
+        self.run(move |conn| {
+            conn.transaction(|conn| {
+                let fortress_delete_result = diesel::update(live_fortresses())
+                    .filter(fortresses::id.eq(id))
+                    .set((
+                        fortresses::deleted_at.eq(now),
+                        fortresses::version.eq(fortresses::version + 1),
+                        fortresses::updated_at.eq(now),
+                    ))
+                    .execute(conn)?;
+                if fortress_delete_result == 0 {
+                    return Ok(false);
+                }
+                insert_event(
+                    conn,
+                    new_event(EventKind::Delete, id, None, &Costs::ZERO, now),
+                )?;
+
+                Ok(true)
+            })
+        })
+        .await
+    }
+
+    async fn restore_fortress(
+        &self,
+        id: i32,
+        owner_id: Option<String>,
+        grace_period: Duration,
+        max_fortresses_per_owner: Option<i64>,
+    ) -> Result<Fortress, RepositoryError> {
+        let now = self.now();
+        self.run(move |conn| {
+            conn.transaction(|conn| {
+                let mut query = fortresses::table
+                    .filter(fortresses::id.eq(id))
+                    .select(Fortress::as_select())
+                    .into_boxed();
+                if let Some(owner) = owner_id {
+                    query = query.filter(fortresses::owner_id.eq(owner));
+                }
+                let fortress = query
+                    .first(conn)
+                    .optional()?
+                    .ok_or(RepositoryError::FortressNotFound)?;
+                let deleted_at = fortress
+                    .deleted_at
+                    .ok_or(RepositoryError::FortressNotDeleted)?;
+                if grace_period_start(now, grace_period).is_some_and(|start| deleted_at < start) {
+                    return Err(RepositoryError::RestorePeriodExpired);
+                }
+                // Same lock as `create_fortress_with_buildings`.
+                if let AnyConnection::Postgresql(conn) = conn {
+                    diesel::sql_query("SELECT pg_advisory_xact_lock(hashtext($1))")
+                        .bind::<Text, _>(&fortress.owner_id)
+                        .execute(conn)?;
+                }
+                let fortress = diesel::update(fortresses::table)
+                    .filter(fortresses::id.eq(id))
+                    .set((
+                        fortresses::deleted_at.eq(None::<time::PrimitiveDateTime>),
+                        fortresses::version.eq(fortresses::version + 1),
+                        fortresses::updated_at.eq(now),
+                    ))
+                    .get_result::<Fortress>(conn)?;
+                if let Some(max) = max_fortresses_per_owner {
+                    // Counted after the update, like on create, so that
+                    // SQLite already holds the write lock.
+                    let count: i64 = live_fortresses()
+                        .filter(fortresses::owner_id.eq(&fortress.owner_id))
+                        .count()
+                        .get_result(conn)?;
+                    if count > max {
+                        return Err(RepositoryError::FortressLimitReached);
+                    }
+                }
+
+                Ok(fortress)
+            })
+        })
+        .await
+    }
+
+    async fn purge_deleted_fortresses(
+        &self,
+        grace_period: Duration,
+    ) -> Result<usize, RepositoryError> {
+        let now = self.now();
+        let Some(start) = grace_period_start(now, grace_period) else {
+            return Ok(0);
+        };
+        self.run(move |conn| {
+            // Buildings go with their fortress through `ON DELETE CASCADE`.
+            let purged = diesel::delete(fortresses::table)
+                .filter(fortresses::deleted_at.lt(start))
+                .execute(conn)?;
+
+            Ok(purged)
+        })
+        .await
+    }
+
+    async fn list_fortresses(
+        &self,
+        query: FortressQuery,
+    ) -> Result<Vec<Fortress>, RepositoryError> {
+        self.run(move |conn| {
+            let mut statement = live_fortresses()
+                .select(Fortress::as_select())
+                .limit(query.limit)
+                .into_boxed();
+            if let Some(owner) = query.owner_id {
+                statement = statement.filter(fortresses::owner_id.eq(owner));
+            }
+            // Same keyset conditions as `list_buildings`.
+            if let Some(after) = query.after {
+                statement = match (query.sort, query.direction) {
+                    (FortressSort::Id, SortDirection::Ascending) => {
+                        statement.filter(fortresses::id.gt(after.id))
+                    }
+                    (FortressSort::Id, SortDirection::Descending) => {
+                        statement.filter(fortresses::id.lt(after.id))
+                    }
+                    (FortressSort::Gold, SortDirection::Ascending) => statement.filter(
+                        fortresses::gold.ge(after.key).and(
+                            fortresses::gold
+                                .gt(after.key)
+                                .or(fortresses::id.gt(after.id)),
+                        ),
+                    ),
+                    (FortressSort::Gold, SortDirection::Descending) => statement.filter(
+                        fortresses::gold.le(after.key).and(
+                            fortresses::gold
+                                .lt(after.key)
+                                .or(fortresses::id.lt(after.id)),
+                        ),
+                    ),
+                };
+            }
+            statement = match (query.sort, query.direction) {
+                (FortressSort::Id, SortDirection::Ascending) => {
+                    statement.order(fortresses::id.asc())
+                }
+                (FortressSort::Id, SortDirection::Descending) => {
+                    statement.order(fortresses::id.desc())
+                }
+                (FortressSort::Gold, SortDirection::Ascending) => {
+                    statement.order((fortresses::gold.asc(), fortresses::id.asc()))
+                }
+                (FortressSort::Gold, SortDirection::Descending) => {
+                    statement.order((fortresses::gold.desc(), fortresses::id.desc()))
+                }
+            };
+            let fortresses = statement.load(conn)?;
+
+            Ok(fortresses)
+        })
+        .await
+    }
+
+    async fn collect_fortress_resource(
+        &self,
+        id: i32,
+        resource: Resource,
+        base: i64,
+        bonus_building_name: String,
+        expected_version: Option<i64>,
+    ) -> Result<Fortress, RepositoryError> {
+        let now = self.now();
+        self.run(move |conn| {
+            conn.transaction(|conn| {
+                let levels: Vec<i32> = buildings::table
+                    .filter(buildings::fortress_id.eq(id))
+                    .filter(buildings::name.eq(bonus_building_name))
+                    .select(buildings::level)
+                    .load(conn)?;
+                let amount = rules::collect_yield(base, levels);
+                let mut target = diesel::update(live_fortresses())
+                    .filter(fortresses::id.eq(id))
+                    .into_boxed();
+                if let Some(expected) = expected_version {
+                    target = target.filter(fortresses::version.eq(expected));
+                }
+                // Bumping the version first locks the fortress, so the stock it
+                // returns is the one the collect adds to.
+                let Some(fortress) = target
+                    .set((
+                        fortresses::version.eq(fortresses::version + 1),
+                        fortresses::updated_at.eq(now),
+                    ))
+                    .get_result::<Fortress>(conn)
+                    .optional()?
+                else {
+                    ensure_live_fortress(conn, id)?;
+                    return Err(RepositoryError::ConcurrentUpdate);
+                };
+                let resources = after_gain(&fortress, &resource.delta(amount));
+                let fortress_after = diesel::update(fortresses::table)
+                    .filter(fortresses::id.eq(id))
+                    .set((
+                        fortresses::gold.eq(resources.gold),
+                        fortresses::food.eq(resources.food),
+                        fortresses::wood.eq(resources.wood),
+                        fortresses::energy.eq(resources.energy),
+                    ))
+                    .get_result::<Fortress>(conn)?;
+                // What the capped stock actually gained.
+                insert_event(
+                    conn,
+                    new_event(
+                        EventKind::Collect,
+                        id,
+                        None,
+                        &resource_delta(&fortress, &resources),
+                        now,
+                    ),
+                )?;
+
+                Ok(fortress_after)
+            })
+        })
+        .await
+    }
+
+    async fn list_fortress_events(
+        &self,
+        fortress_id: i32,
+        before_id: Option<i64>,
+        limit: i64,
+    ) -> Result<Vec<FortressEvent>, RepositoryError> {
+        self.run(move |conn| {
+            ensure_live_fortress(conn, fortress_id)?;
+            let mut query = fortress_events::table
+                .filter(fortress_events::fortress_id.eq(fortress_id))
+                .select(FortressEvent::as_select())
+                .order(fortress_events::id.desc())
+                .limit(limit)
+                .into_boxed();
+            if let Some(before_id) = before_id {
+                query = query.filter(fortress_events::id.lt(before_id));
+            }
+            let events = query.load(conn)?;
+
+            Ok(events)
+        })
+        .await
+    }
+
+    async fn scout_fortress(
+        &self,
+        spy_fortress_id: i32,
+        target_fortress_id: i32,
+        energy_cost: i64,
+        success: bool,
+        detected: bool,
+    ) -> Result<(Fortress, Option<ScoutReportWithBuildings>), RepositoryError> {
+        let now = self.now();
+        check_non_negative(&[energy_cost])?;
+        self.run(move |conn| {
+            conn.transaction(|conn| {
+                ensure_live_fortress(conn, target_fortress_id)?;
+                let spy = diesel::update(live_fortresses())
+                    .filter(fortresses::id.eq(spy_fortress_id))
+                    .filter(fortresses::energy.ge(energy_cost))
+                    .set((
+                        fortresses::energy.eq(fortresses::energy - energy_cost),
+                        fortresses::version.eq(fortresses::version + 1),
+                        fortresses::updated_at.eq(now),
+                    ))
+                    .get_result::<Fortress>(conn)
+                    .optional()?;
+                let Some(spy) = spy else {
+                    let fortress = live_fortresses()
+                        .filter(fortresses::id.eq(spy_fortress_id))
+                        .select(Fortress::as_select())
+                        .first(conn)
+                        .optional()?
+                        .ok_or(RepositoryError::FortressNotFound)?;
+                    return Err(RepositoryError::InsufficientResources {
+                        missing: missing_resources(&Resource::Energy.delta(energy_cost), &fortress),
+                    });
+                };
+                insert_event(
+                    conn,
+                    NewFortressEvent {
+                        other_fortress_id: Some(target_fortress_id),
+                        ..new_event(
+                            EventKind::Scout,
+                            spy_fortress_id,
+                            None,
+                            &Resource::Energy.delta(-energy_cost),
+                            now,
+                        )
+                    },
+                )?;
+                if detected {
+                    insert_event(
+                        conn,
+                        NewFortressEvent {
+                            other_fortress_id: Some(spy_fortress_id),
+                            ..new_event(
+                                EventKind::Scouted,
+                                target_fortress_id,
+                                None,
+                                &Costs::ZERO,
+                                now,
+                            )
+                        },
+                    )?;
+                }
+                if !success {
+                    return Ok((spy, None));
+                }
+
+                let target = live_fortresses()
+                    .filter(fortresses::id.eq(target_fortress_id))
+                    .select(Fortress::as_select())
+                    .first(conn)?;
+                let report = diesel::insert_into(scout_reports::table)
+                    .values(NewScoutReport {
+                        spy_fortress_id,
+                        target_fortress_id,
+                        gold: target.gold,
+                        food: target.food,
+                        wood: target.wood,
+                        energy: target.energy,
+                        created_at: now,
+                    })
+                    .get_result::<ScoutReport>(conn)?;
+                let seen: Vec<(String, i32)> = buildings::table
+                    .filter(buildings::fortress_id.eq(target_fortress_id))
+                    .order(buildings::name)
+                    .select((buildings::name, buildings::level))
+                    .load(conn)?;
+                let mut report_buildings = Vec::with_capacity(seen.len());
+                for (name, level) in seen {
+                    let building = ScoutReportBuilding {
+                        report_id: report.id,
+                        name,
+                        level,
+                    };
+                    diesel::insert_into(scout_report_buildings::table)
+                        .values(&building)
+                        .execute(conn)?;
+                    report_buildings.push(building);
+                }
+
+                Ok((spy, Some((report, report_buildings))))
+            })
+        })
+        .await
+    }
+
+    async fn list_scout_reports(
+        &self,
+        spy_fortress_id: i32,
+        before_id: Option<i64>,
+        limit: i64,
+    ) -> Result<Vec<ScoutReportWithBuildings>, RepositoryError> {
+        self.run(move |conn| {
+            ensure_live_fortress(conn, spy_fortress_id)?;
+            let mut query = scout_reports::table
+                .filter(scout_reports::spy_fortress_id.eq(spy_fortress_id))
+                .select(ScoutReport::as_select())
+                .order(scout_reports::id.desc())
+                .limit(limit)
+                .into_boxed();
+            if let Some(before_id) = before_id {
+                query = query.filter(scout_reports::id.lt(before_id));
+            }
+            let reports = query.load(conn)?;
+            let report_buildings = ScoutReportBuilding::belonging_to(&reports)
+                .order(scout_report_buildings::name)
+                .select(ScoutReportBuilding::as_select())
+                .load(conn)?
+                .grouped_by(&reports);
+
+            Ok(reports.into_iter().zip(report_buildings).collect())
+        })
+        .await
+    }
+
+    async fn list_camps(&self, owner_id: String) -> Result<Vec<Camp>, RepositoryError> {
+        self.run(move |conn| {
+            let camps = camps::table
+                .filter(camps::owner_id.eq(owner_id))
+                .order(camps::slot)
+                .select(Camp::as_select())
+                .load(conn)?;
+
+            Ok(camps)
+        })
+        .await
+    }
+
+    async fn spawn_camp(&self, new_camp: NewCamp) -> Result<Camp, RepositoryError> {
+        let now = self.now();
+        check_non_negative(&[
+            i64::from(new_camp.slot),
+            i64::from(new_camp.strength),
+            new_camp.gold,
+            new_camp.food,
+            new_camp.wood,
+            new_camp.energy,
+        ])?;
+        self.run(move |conn| {
+            let owner_id = new_camp.owner_id.clone();
+            let slot = new_camp.slot;
+            match conn.transaction(|conn| spawn_camp(conn, new_camp, now)) {
+                // Another call filled the empty slot meanwhile.
+                Err(RepositoryError::UniqueViolation) => {
+                    camp_in_slot(conn, &owner_id, slot)?.ok_or(RepositoryError::ConcurrentUpdate)
+                }
+                spawned => spawned,
+            }
+        })
+        .await
+    }
+
+    async fn raid_camp(
+        &self,
+        camp_id: i32,
+        fortress_id: i32,
+        energy_cost: i64,
+        success: bool,
+        respawn_after: Duration,
+    ) -> Result<(Fortress, Camp), RepositoryError> {
+        let now = self.now();
+        check_non_negative(&[energy_cost])?;
+        self.run(move |conn| {
+            conn.transaction(|conn| {
+                let camp = camps::table
+                    .filter(camps::id.eq(camp_id))
+                    .select(Camp::as_select())
+                    .first(conn)
+                    .optional()?
+                    .ok_or(RepositoryError::CampNotFound)?;
+                if camp.respawn_at.is_some() {
+                    return Err(RepositoryError::CampNotAvailable);
+                }
+                let fortress = live_fortresses()
+                    .filter(fortresses::id.eq(fortress_id))
+                    .select(Fortress::as_select())
+                    .first(conn)
+                    .optional()?
+                    .ok_or(RepositoryError::FortressNotFound)?;
+                if fortress.energy < energy_cost {
+                    return Err(RepositoryError::InsufficientResources {
+                        missing: missing_resources(&Resource::Energy.delta(energy_cost), &fortress),
+                    });
+                }
+                let camp = if success {
+                    diesel::update(camps::table)
+                        .filter(camps::id.eq(camp_id))
+                        .filter(camps::respawn_at.is_null())
+                        .set((
+                            camps::respawn_at.eq(respawn_time(now, respawn_after)),
+                            camps::version.eq(camps::version + 1),
+                            camps::updated_at.eq(now),
+                        ))
+                        .get_result::<Camp>(conn)
+                        .optional()?
+                        .ok_or(RepositoryError::CampNotAvailable)?
+                } else {
+                    camp
+                };
+                let resources = after_raid(&fortress, energy_cost, success.then_some(&camp));
+                let raider = diesel::update(fortresses::table)
+                    .filter(fortresses::id.eq(fortress_id))
+                    .filter(fortresses::version.eq(fortress.version))
+                    .set((
+                        fortresses::gold.eq(resources.gold),
+                        fortresses::food.eq(resources.food),
+                        fortresses::wood.eq(resources.wood),
+                        fortresses::energy.eq(resources.energy),
+                        fortresses::version.eq(fortresses::version + 1),
+                        fortresses::updated_at.eq(now),
+                    ))
+                    .get_result::<Fortress>(conn)
+                    .optional()?
+                    .ok_or(RepositoryError::ConcurrentUpdate)?;
+                insert_event(
+                    conn,
+                    new_event(
+                        EventKind::Raid,
+                        fortress_id,
+                        None,
+                        &resource_delta(&fortress, &resources),
+                        now,
+                    ),
+                )?;
+
+                Ok((raider, camp))
+            })
+        })
+        .await
+    }
+
+    async fn get_daily_reward(
+        &self,
+        owner_id: String,
+    ) -> Result<Option<DailyReward>, RepositoryError> {
+        self.run(move |conn| {
+            let daily_reward = daily_rewards::table
+                .find(owner_id)
+                .select(DailyReward::as_select())
+                .first(conn)
+                .optional()?;
+
+            Ok(daily_reward)
+        })
+        .await
+    }
+
+    async fn claim_daily_reward(
+        &self,
+        owner_id: String,
+        fortress_id: i32,
+        rewards: Vec<Costs>,
+    ) -> Result<(Fortress, DailyReward, Costs), RepositoryError> {
+        let now = self.now();
+        self.run(move |conn| {
+            let claimed = conn.transaction(|conn| {
+                let fortress = live_fortresses()
+                    .filter(fortresses::id.eq(fortress_id))
+                    .filter(fortresses::owner_id.eq(&owner_id))
+                    .select(Fortress::as_select())
+                    .first(conn)
+                    .optional()?
+                    .ok_or(RepositoryError::FortressNotFound)?;
+                let previous = daily_rewards::table
+                    .find(&owner_id)
+                    .select(DailyReward::as_select())
+                    .first(conn)
+                    .optional()?;
+                let (streak, reward) = claim_streak(previous.as_ref(), now, &rewards)?;
+                let daily_reward = save_daily_reward(conn, &owner_id, previous, streak, now)?;
+                let resources = after_gain(&fortress, &reward);
+                let fortress_after = diesel::update(fortresses::table)
+                    .filter(fortresses::id.eq(fortress_id))
+                    .filter(fortresses::version.eq(fortress.version))
+                    .set((
+                        fortresses::gold.eq(resources.gold),
+                        fortresses::food.eq(resources.food),
+                        fortresses::wood.eq(resources.wood),
+                        fortresses::energy.eq(resources.energy),
+                        fortresses::version.eq(fortresses::version + 1),
+                        fortresses::updated_at.eq(now),
+                    ))
+                    .get_result::<Fortress>(conn)
+                    .optional()?
+                    .ok_or(RepositoryError::ConcurrentUpdate)?;
+                insert_event(
+                    conn,
+                    new_event(
+                        EventKind::DailyReward,
+                        fortress_id,
+                        None,
+                        &resource_delta(&fortress, &resources),
+                        now,
+                    ),
+                )?;
+
+                Ok((fortress_after, daily_reward, reward))
+            });
+            match claimed {
+                // Another claim made the first one of the player meanwhile.
+                Err(RepositoryError::UniqueViolation) => Err(RepositoryError::ConcurrentUpdate),
+                claimed => claimed,
+            }
+        })
+        .await
+    }
+}
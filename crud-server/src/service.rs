@@ -1,41 +1,164 @@
 use crate::{
-    DbPool,
-    pb::crud::v1::{
-        CollectFortressResourceRequest, CollectFortressResourceResponse, CreateBuildingRequest,
-        CreateBuildingResponse, CreateFortressRequest, CreateFortressResponse,
-        DeleteBuildingRequest, DeleteBuildingResponse, DeleteFortressRequest,
-        DeleteFortressResponse, GetBuildingRequest, GetBuildingResponse, GetFortressRequest,
-        GetFortressResponse, ListBuildingsByFortressRequest, ListBuildingsByFortressResponse,
-        ListBuildingsRequest, ListBuildingsResponse, ListFortressesRequest, ListFortressesResponse,
-        ResourceKind, UpdateBuildingRequest, UpdateBuildingResponse, UpdateFortressRequest,
-        UpdateFortressResponse, UpgradeBuildingAtomicRequest, UpgradeBuildingAtomicResponse,
-        building_service_server::BuildingService, fortress_service_server::FortressService,
+    pb::common::v2::FortressEventKind,
+    pb::crud::v2::{
+        ClaimDailyRewardRequest, ClaimDailyRewardResponse, CollectFortressResourceRequest,
+        CollectFortressResourceResponse, CreateBuildingRequest, CreateBuildingResponse,
+        CreateFortressRequest, CreateFortressResponse, CreateFortressWithBuildingsRequest,
+        CreateFortressWithBuildingsResponse, DeleteBuildingRequest, DeleteBuildingResponse,
+        DeleteFortressRequest, DeleteFortressResponse, GetBuildingRequest, GetBuildingResponse,
+        GetBuildingWithFortressRequest, GetBuildingWithFortressResponse, GetBuildingsRequest,
+        GetBuildingsResponse, GetClockRequest, GetClockResponse, GetDailyRewardRequest,
+        GetDailyRewardResponse, GetFortressHistoryRequest, GetFortressHistoryResponse,
+        GetFortressRequest, GetFortressResponse, GetFortressWithBuildingsRequest,
+        GetFortressWithBuildingsResponse, GetFortressesRequest, GetFortressesResponse,
+        ListBuildingsByFortressRequest, ListBuildingsByFortressResponse, ListBuildingsRequest,
+        ListBuildingsResponse, ListCampsRequest, ListCampsResponse, ListFortressesRequest,
+        ListFortressesResponse, ListScoutReportsRequest, ListScoutReportsResponse, RaidCampRequest,
+        RaidCampResponse, ResourceKind, RestoreFortressRequest, RestoreFortressResponse,
+        ScoutFortressRequest, ScoutFortressResponse, SetClockSkewRequest, SetClockSkewResponse,
+        SpawnCampRequest, SpawnCampResponse, UpdateBuildingRequest, UpdateBuildingResponse,
+        UpdateFortressRequest, UpdateFortressResponse, UpgradeBuildingAtomicRequest,
+        UpgradeBuildingAtomicResponse, building_service_server::BuildingService,
+        clock_service_server::ClockService, fortress_service_server::FortressService,
+    },
+    repository::{
+        BuildingQuery, BuildingSort, Cursor, EventKind, FortressQuery, FortressSort, Repository,
+        ScoutReportWithBuildings, SortDirection,
     },
-};
-use diesel::{
-    prelude::*,
-    sql_types::{Integer, Text},
 };
 use rusty::{
-    models::{Building, Fortress, NewBuilding, NewFortress, UpdateBuilding, UpdateFortress},
-    schema::{buildings, fortresses},
+    Costs,
+    clock::{Clock, SkewedClock},
+    models::{
+        Building, Camp, DailyReward, Fortress, FortressEvent, NewBuilding, NewCamp, NewFortress,
+        UpdateBuilding, UpdateFortress,
+    },
+    rules::Resource,
 };
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
+use time::PrimitiveDateTime;
 use tonic::{Request, Response, Status};
 
-impl From<Building> for crate::pb::common::v1::Building {
+const DEFAULT_PAGE_SIZE: i32 = 50;
+const MAX_PAGE_SIZE: i32 = 200;
+const MAX_BATCH_SIZE: usize = 100;
+
+/// `page_size` of paginated requests, where 0 asks for the default.
+fn page_size(requested: i32) -> Result<i32, Status> {
+    match requested {
+        0 => Ok(DEFAULT_PAGE_SIZE),
+        size if size < 0 => Err(Status::invalid_argument("page_size must not be negative")),
+        size => Ok(size.min(MAX_PAGE_SIZE)),
+    }
+}
+
+/// Paginated queries ask for one more row than `page_size`, to tell whether
+/// another page follows. Drops that row and returns the last one kept when
+/// there is a next page.
+fn truncate_page<T>(rows: &mut Vec<T>, page_size: i32) -> Option<&T> {
+    let page_size = usize::try_from(page_size).unwrap_or_default();
+    if rows.len() <= page_size {
+        return None;
+    }
+    rows.truncate(page_size);
+    rows.last()
+}
+
+/// List page tokens hold the sort key and id of the last row returned.
+fn parse_cursor(page_token: &str) -> Result<Option<Cursor>, Status> {
+    if page_token.is_empty() {
+        return Ok(None);
+    }
+    let cursor = page_token
+        .split_once(':')
+        .and_then(|(key, id)| {
+            Some(Cursor {
+                key: key.parse().ok()?,
+                id: id.parse().ok()?,
+            })
+        })
+        .ok_or_else(|| Status::invalid_argument("invalid page_token"))?;
+
+    Ok(Some(cursor))
+}
+
+fn cursor_token(key: i64, id: i32) -> String {
+    format!("{key}:{id}")
+}
+
+fn sort_direction(direction: i32) -> Result<SortDirection, Status> {
+    use crate::pb::common::v2::SortDirection as Pb;
+
+    match Pb::try_from(direction) {
+        Ok(Pb::Unspecified | Pb::Ascending) => Ok(SortDirection::Ascending),
+        Ok(Pb::Descending) => Ok(SortDirection::Descending),
+        Err(_) => Err(Status::invalid_argument("invalid sort_direction")),
+    }
+}
+
+fn building_sort(field: i32) -> Result<BuildingSort, Status> {
+    use crate::pb::common::v2::BuildingSortField as Pb;
+
+    match Pb::try_from(field) {
+        Ok(Pb::Unspecified | Pb::Id) => Ok(BuildingSort::Id),
+        Ok(Pb::Level) => Ok(BuildingSort::Level),
+        Err(_) => Err(Status::invalid_argument("invalid sort_field")),
+    }
+}
+
+fn fortress_sort(field: i32) -> Result<FortressSort, Status> {
+    use crate::pb::common::v2::FortressSortField as Pb;
+
+    match Pb::try_from(field) {
+        Ok(Pb::Unspecified | Pb::Id) => Ok(FortressSort::Id),
+        Ok(Pb::Gold) => Ok(FortressSort::Gold),
+        Err(_) => Err(Status::invalid_argument("invalid sort_field")),
+    }
+}
+
+fn check_batch_size(ids: &[i32]) -> Result<(), Status> {
+    if ids.len() > MAX_BATCH_SIZE {
+        return Err(Status::invalid_argument(format!(
+            "at most {MAX_BATCH_SIZE} ids per request"
+        )));
+    }
+
+    Ok(())
+}
+
+/// Newest-first pages resume below the id of the last row already returned,
+/// which is their `page_token`.
+fn parse_before_id(page_token: &str) -> Result<Option<i64>, Status> {
+    if page_token.is_empty() {
+        return Ok(None);
+    }
+    page_token
+        .parse::<i64>()
+        .map(Some)
+        .map_err(|_| Status::invalid_argument("invalid page_token"))
+}
+
+/// Timestamp columns hold UTC times.
+const fn unix_timestamp(time: PrimitiveDateTime) -> i64 {
+    time.assume_utc().unix_timestamp()
+}
+
+impl From<Building> for crate::pb::common::v2::Building {
     fn from(value: Building) -> Self {
         Self {
             id: value.id,
             name: value.name,
             level: value.level,
             fortress_id: value.fortress_id,
+            version: value.version,
+            created_at: unix_timestamp(value.created_at),
+            updated_at: unix_timestamp(value.updated_at),
         }
     }
 }
 
-impl From<crate::pb::common::v1::NewBuilding> for NewBuilding {
-    fn from(value: crate::pb::common::v1::NewBuilding) -> Self {
+impl From<crate::pb::common::v2::NewBuilding> for NewBuilding {
+    fn from(value: crate::pb::common::v2::NewBuilding) -> Self {
         Self {
             name: value.name,
             level: value.level,
@@ -44,8 +167,8 @@ impl From<crate::pb::common::v1::NewBuilding> for NewBuilding {
     }
 }
 
-impl From<crate::pb::common::v1::UpdateBuilding> for UpdateBuilding {
-    fn from(value: crate::pb::common::v1::UpdateBuilding) -> Self {
+impl From<crate::pb::common::v2::UpdateBuilding> for UpdateBuilding {
+    fn from(value: crate::pb::common::v2::UpdateBuilding) -> Self {
         Self {
             name: value.name,
             level: value.level,
@@ -54,7 +177,7 @@ impl From<crate::pb::common::v1::UpdateBuilding> for UpdateBuilding {
     }
 }
 
-impl From<Fortress> for crate::pb::common::v1::Fortress {
+impl From<Fortress> for crate::pb::common::v2::Fortress {
     fn from(value: Fortress) -> Self {
         Self {
             id: value.id,
@@ -63,24 +186,96 @@ impl From<Fortress> for crate::pb::common::v1::Fortress {
             food: value.food,
             wood: value.wood,
             energy: value.energy,
+            version: value.version,
+            created_at: unix_timestamp(value.created_at),
+            updated_at: unix_timestamp(value.updated_at),
+            owner_name: value.owner_name,
         }
     }
 }
 
-impl From<crate::pb::common::v1::NewFortress> for NewFortress {
-    fn from(value: crate::pb::common::v1::NewFortress) -> Self {
+impl From<FortressEvent> for crate::pb::common::v2::FortressEvent {
+    fn from(value: FortressEvent) -> Self {
+        let kind = match EventKind::parse(&value.kind) {
+            Some(EventKind::Create) => FortressEventKind::Create,
+            Some(EventKind::Collect) => FortressEventKind::Collect,
+            Some(EventKind::Upgrade) => FortressEventKind::Upgrade,
+            Some(EventKind::Delete) => FortressEventKind::Delete,
+            Some(EventKind::Scout) => FortressEventKind::Scout,
+            Some(EventKind::Scouted) => FortressEventKind::Scouted,
+            Some(EventKind::Raid) => FortressEventKind::Raid,
+            Some(EventKind::DailyReward) => FortressEventKind::DailyReward,
+            None => FortressEventKind::Unspecified,
+        };
         Self {
-            owner_id: value.owner_id,
+            id: value.id,
+            // Only the events of live fortresses are listed.
+            fortress_id: value.fortress_id.unwrap_or_default(),
+            building_id: value.building_id,
+            kind: kind.into(),
             gold: value.gold,
             food: value.food,
             wood: value.wood,
             energy: value.energy,
+            created_at: unix_timestamp(value.created_at),
+            other_fortress_id: value.other_fortress_id,
+        }
+    }
+}
+
+impl From<ScoutReportWithBuildings> for crate::pb::common::v2::ScoutReport {
+    fn from((report, buildings): ScoutReportWithBuildings) -> Self {
+        Self {
+            id: report.id,
+            spy_fortress_id: report.spy_fortress_id,
+            target_fortress_id: report.target_fortress_id,
+            gold: report.gold,
+            food: report.food,
+            wood: report.wood,
+            energy: report.energy,
+            buildings: buildings
+                .into_iter()
+                .map(|building| crate::pb::common::v2::ScoutedBuilding {
+                    name: building.name,
+                    level: building.level,
+                })
+                .collect(),
+            created_at: unix_timestamp(report.created_at),
+        }
+    }
+}
+
+impl From<Camp> for crate::pb::common::v2::Camp {
+    fn from(value: Camp) -> Self {
+        Self {
+            id: value.id,
+            owner_id: value.owner_id,
+            slot: value.slot,
+            strength: value.strength,
+            loot: Some(crate::pb::common::v2::Costs {
+                gold: value.gold,
+                food: value.food,
+                wood: value.wood,
+                energy: value.energy,
+            }),
+            respawn_at: value.respawn_at.map(unix_timestamp),
         }
     }
 }
 
-impl From<crate::pb::common::v1::UpdateFortress> for UpdateFortress {
-    fn from(value: crate::pb::common::v1::UpdateFortress) -> Self {
+impl From<DailyReward> for crate::pb::crud::v2::DailyReward {
+    fn from(value: DailyReward) -> Self {
+        Self {
+            owner_id: value.owner_id,
+            streak: value.streak,
+            last_claimed_at: unix_timestamp(value.last_claimed_at),
+            version: value.version,
+        }
+    }
+}
+
+impl From<crate::pb::common::v2::Costs> for Costs {
+    fn from(value: crate::pb::common::v2::Costs) -> Self {
         Self {
             gold: value.gold,
             food: value.food,
@@ -90,27 +285,22 @@ impl From<crate::pb::common::v1::UpdateFortress> for UpdateFortress {
     }
 }
 
-#[derive(diesel::QueryableByName)]
-struct FortressRow {
-    #[diesel(sql_type = Integer)]
-    id: i32,
-    #[diesel(sql_type = Text)]
-    owner_id: String,
-    #[diesel(sql_type = Integer)]
-    gold: i32,
-    #[diesel(sql_type = Integer)]
-    food: i32,
-    #[diesel(sql_type = Integer)]
-    wood: i32,
-    #[diesel(sql_type = Integer)]
-    energy: i32,
+impl From<Costs> for crate::pb::common::v2::Costs {
+    fn from(value: Costs) -> Self {
+        Self {
+            gold: value.gold,
+            food: value.food,
+            wood: value.wood,
+            energy: value.energy,
+        }
+    }
 }
 
-impl From<FortressRow> for crate::pb::common::v1::Fortress {
-    fn from(value: FortressRow) -> Self {
+impl From<crate::pb::common::v2::NewFortress> for NewFortress {
+    fn from(value: crate::pb::common::v2::NewFortress) -> Self {
         Self {
-            id: value.id,
             owner_id: value.owner_id,
+            owner_name: value.owner_name,
             gold: value.gold,
             food: value.food,
             wood: value.wood,
@@ -119,30 +309,25 @@ impl From<FortressRow> for crate::pb::common::v1::Fortress {
     }
 }
 
-#[derive(Debug)]
-enum UpgradeBuildingAtomicError {
-    Diesel(diesel::result::Error),
-    BuildingNotFound,
-    FortressNotFound,
-    MaxLevel,
-    InsufficientResources,
-    ConcurrentUpdate,
-}
-
-impl From<diesel::result::Error> for UpgradeBuildingAtomicError {
-    fn from(value: diesel::result::Error) -> Self {
-        Self::Diesel(value)
+impl From<crate::pb::common::v2::UpdateFortress> for UpdateFortress {
+    fn from(value: crate::pb::common::v2::UpdateFortress) -> Self {
+        Self {
+            gold: value.gold,
+            food: value.food,
+            wood: value.wood,
+            energy: value.energy,
+        }
     }
 }
 
 pub struct MyBuildingService {
-    pool: Arc<DbPool>,
+    repository: Arc<dyn Repository>,
 }
 
 impl MyBuildingService {
     #[must_use]
-    pub const fn new(pool: Arc<DbPool>) -> Self {
-        Self { pool }
+    pub fn new(repository: Arc<dyn Repository>) -> Self {
+        Self { repository }
     }
 }
 
@@ -157,15 +342,7 @@ impl BuildingService for MyBuildingService {
             .building
             .ok_or_else(|| Status::not_found("missing building field"))?
             .into();
-        let mut conn = self
-            .pool
-            .get()
-            .map_err(|e| Status::internal(format!("{e}")))?;
-        let building = diesel::insert_into(buildings::table)
-            .values(new_building)
-            .returning(Building::as_returning())
-            .get_result(&mut conn)
-            .map_err(|e| Status::internal(format!("{e}")))?;
+        let building = self.repository.create_building(new_building).await?;
         let building = CreateBuildingResponse {
             building: Some(building.into()),
         };
@@ -178,14 +355,7 @@ impl BuildingService for MyBuildingService {
         request: Request<GetBuildingRequest>,
     ) -> Result<Response<GetBuildingResponse>, Status> {
         let building_id = request.into_inner().id;
-        let mut conn = self
-            .pool
-            .get()
-            .map_err(|e| Status::internal(format!("{e}")))?;
-        let building: Building = buildings::table
-            .filter(buildings::id.eq(building_id))
-            .get_result(&mut conn)
-            .map_err(|e| Status::not_found(format!("{e}")))?;
+        let building = self.repository.get_building(building_id).await?;
         let building = GetBuildingResponse {
             building: Some(building.into()),
         };
@@ -193,26 +363,50 @@ impl BuildingService for MyBuildingService {
         Ok(Response::new(building))
     }
 
+    async fn get_buildings(
+        &self,
+        request: Request<GetBuildingsRequest>,
+    ) -> Result<Response<GetBuildingsResponse>, Status> {
+        let ids = request.into_inner().ids;
+        check_batch_size(&ids)?;
+        let buildings = self.repository.get_buildings(ids).await?;
+        let buildings = GetBuildingsResponse {
+            buildings: buildings.into_iter().map(Into::into).collect(),
+        };
+
+        Ok(Response::new(buildings))
+    }
+
+    async fn get_building_with_fortress(
+        &self,
+        request: Request<GetBuildingWithFortressRequest>,
+    ) -> Result<Response<GetBuildingWithFortressResponse>, Status> {
+        let building_id = request.into_inner().id;
+        let (building, fortress) = self
+            .repository
+            .get_building_with_fortress(building_id)
+            .await?;
+
+        Ok(Response::new(GetBuildingWithFortressResponse {
+            building: Some(building.into()),
+            fortress: Some(fortress.into()),
+        }))
+    }
+
     async fn update_building(
         &self,
         request: Request<UpdateBuildingRequest>,
     ) -> Result<Response<UpdateBuildingResponse>, Status> {
-        let update_building = request
-            .into_inner()
+        let req = request.into_inner();
+        let update_building = req
             .building
             .ok_or_else(|| Status::not_found("missing building field"))?;
         let building_id = update_building.id;
         let update_building: UpdateBuilding = update_building.into();
-        let mut conn = self
-            .pool
-            .get()
-            .map_err(|e| Status::internal(format!("{e}")))?;
-        let building = diesel::update(buildings::table)
-            .filter(buildings::id.eq(building_id))
-            .set(update_building)
-            .returning(Building::as_returning())
-            .get_result(&mut conn)
-            .map_err(|e| Status::internal(format!("{e}")))?;
+        let building = self
+            .repository
+            .update_building(building_id, update_building, req.expected_version)
+            .await?;
         let building = UpdateBuildingResponse {
             building: Some(building.into()),
         };
@@ -225,15 +419,7 @@ impl BuildingService for MyBuildingService {
         request: Request<DeleteBuildingRequest>,
     ) -> Result<Response<DeleteBuildingResponse>, Status> {
         let building_id = request.into_inner().id;
-        let mut conn = self
-            .pool
-            .get()
-            .map_err(|e| Status::internal(format!("{e}")))?;
-        let building_delete_result = diesel::delete(buildings::table)
-            .filter(buildings::id.eq(building_id))
-            .execute(&mut conn)
-            .map_err(|e| Status::internal(format!("{e}")))?;
-        let success = building_delete_result != 0;
+        let success = self.repository.delete_building(building_id).await?;
         let success = DeleteBuildingResponse { success };
 
         Ok(Response::new(success))
@@ -241,17 +427,36 @@ impl BuildingService for MyBuildingService {
 
     async fn list_buildings(
         &self,
-        _request: Request<ListBuildingsRequest>,
+        request: Request<ListBuildingsRequest>,
     ) -> Result<Response<ListBuildingsResponse>, Status> {
-        let mut conn = self
-            .pool
-            .get()
-            .map_err(|e| Status::internal(format!("{e}")))?;
-        let buildings: Vec<Building> = buildings::table
-            .get_results(&mut conn)
-            .map_err(|e| Status::internal(format!("{e}")))?;
+        let req = request.into_inner();
+        check_batch_size(&req.fortress_ids)?;
+        let page_size = page_size(req.page_size)?;
+        let sort = building_sort(req.sort_field)?;
+        let after = parse_cursor(&req.page_token)?;
+        // Levels are 32-bit, a larger key can't come from a previous page.
+        if sort == BuildingSort::Level
+            && after.is_some_and(|after| i32::try_from(after.key).is_err())
+        {
+            return Err(Status::invalid_argument("invalid page_token"));
+        }
+        let query = BuildingQuery {
+            name: req.name,
+            min_level: req.min_level,
+            owner_id: req.owner_id,
+            fortress_ids: req.fortress_ids,
+            sort,
+            direction: sort_direction(req.sort_direction)?,
+            after,
+            limit: i64::from(page_size) + 1,
+        };
+        let mut buildings = self.repository.list_buildings(query).await?;
+        let next_page_token = truncate_page(&mut buildings, page_size)
+            .map(|last| cursor_token(sort.key(last), last.id))
+            .unwrap_or_default();
         let buildings = ListBuildingsResponse {
             buildings: buildings.into_iter().map(Into::into).collect(),
+            next_page_token,
         };
 
         Ok(Response::new(buildings))
@@ -261,15 +466,11 @@ impl BuildingService for MyBuildingService {
         &self,
         request: Request<ListBuildingsByFortressRequest>,
     ) -> Result<Response<ListBuildingsByFortressResponse>, Status> {
-        let mut conn = self
-            .pool
-            .get()
-            .map_err(|e| Status::internal(format!("{e}")))?;
         let fortress_id = request.into_inner().fortress_id;
-        let buildings: Vec<Building> = buildings::table
-            .filter(buildings::fortress_id.eq(fortress_id))
-            .get_results(&mut conn)
-            .map_err(|e| Status::internal(format!("{e}")))?;
+        let buildings = self
+            .repository
+            .list_buildings_by_fortress(fortress_id)
+            .await?;
         let buildings = ListBuildingsByFortressResponse {
             buildings: buildings.into_iter().map(Into::into).collect(),
         };
@@ -293,99 +494,44 @@ impl BuildingService for MyBuildingService {
         if costs.gold < 0 || costs.food < 0 || costs.wood < 0 || costs.energy < 0 {
             return Err(Status::invalid_argument("costs must be non-negative"));
         }
+        let costs = Costs {
+            gold: costs.gold,
+            food: costs.food,
+            wood: costs.wood,
+            energy: costs.energy,
+        };
         let expected_level = req.expected_building_level;
-        let mut conn = self
-            .pool
-            .get()
-            .map_err(|e| Status::internal(format!("{e}")))?;
-        let result: Result<(Fortress, Building), UpgradeBuildingAtomicError> =
-            conn.transaction(|conn| {
-                let building = buildings::table
-                    .filter(buildings::id.eq(building_id))
-                    .select(Building::as_select())
-                    .first(conn)
-                    .optional()?
-                    .ok_or(UpgradeBuildingAtomicError::BuildingNotFound)?;
-                if let Some(expected) = expected_level
-                    && expected != building.level
-                {
-                    return Err(UpgradeBuildingAtomicError::ConcurrentUpdate);
-                }
-                if building.level >= max_building_level {
-                    return Err(UpgradeBuildingAtomicError::MaxLevel);
-                }
-                let fortress_id = building.fortress_id;
-                let fortress = diesel::update(fortresses::table)
-                    .filter(fortresses::id.eq(fortress_id))
-                    .filter(fortresses::gold.ge(costs.gold))
-                    .filter(fortresses::food.ge(costs.food))
-                    .filter(fortresses::wood.ge(costs.wood))
-                    .filter(fortresses::energy.ge(costs.energy))
-                    .set((
-                        fortresses::gold.eq(fortresses::gold - costs.gold),
-                        fortresses::food.eq(fortresses::food - costs.food),
-                        fortresses::wood.eq(fortresses::wood - costs.wood),
-                        fortresses::energy.eq(fortresses::energy - costs.energy),
-                    ))
-                    .returning(Fortress::as_returning())
-                    .get_result(conn)
-                    .optional()?;
-                let Some(fortress) = fortress else {
-                    let exists = fortresses::table
-                        .filter(fortresses::id.eq(fortress_id))
-                        .select(Fortress::as_select())
-                        .first(conn)
-                        .optional()?;
-                    if exists.is_some() {
-                        return Err(UpgradeBuildingAtomicError::InsufficientResources);
-                    }
-                    return Err(UpgradeBuildingAtomicError::FortressNotFound);
-                };
-                let upgraded_building = diesel::update(buildings::table)
-                    .filter(buildings::id.eq(building_id))
-                    .filter(buildings::level.eq(building.level))
-                    .set(buildings::level.eq(buildings::level + 1))
-                    .returning(Building::as_returning())
-                    .get_result(conn)
-                    .optional()?
-                    .ok_or(UpgradeBuildingAtomicError::ConcurrentUpdate)?;
-
-                Ok((fortress, upgraded_building))
-            });
+        let expected_version = req.expected_building_version;
+        let (fortress, building) = self
+            .repository
+            .upgrade_building_atomic(
+                building_id,
+                costs,
+                expected_level,
+                expected_version,
+                max_building_level,
+            )
+            .await?;
 
-        match result {
-            Ok((fortress, building)) => Ok(Response::new(UpgradeBuildingAtomicResponse {
-                fortress: Some(fortress.into()),
-                building: Some(building.into()),
-            })),
-            Err(UpgradeBuildingAtomicError::BuildingNotFound) => {
-                Err(Status::not_found("building not found"))
-            }
-            Err(UpgradeBuildingAtomicError::FortressNotFound) => {
-                Err(Status::not_found("fortress not found"))
-            }
-            Err(UpgradeBuildingAtomicError::MaxLevel) => {
-                Err(Status::failed_precondition("building already at max level"))
-            }
-            Err(UpgradeBuildingAtomicError::InsufficientResources) => {
-                Err(Status::failed_precondition("insufficient resources"))
-            }
-            Err(UpgradeBuildingAtomicError::ConcurrentUpdate) => {
-                Err(Status::aborted("concurrent update; retry"))
-            }
-            Err(UpgradeBuildingAtomicError::Diesel(_e)) => Err(Status::internal("db error")),
-        }
+        Ok(Response::new(UpgradeBuildingAtomicResponse {
+            fortress: Some(fortress.into()),
+            building: Some(building.into()),
+        }))
     }
 }
 
 pub struct MyFortressService {
-    pool: Arc<DbPool>,
+    repository: Arc<dyn Repository>,
+    restore_grace_period: Duration,
 }
 
 impl MyFortressService {
     #[must_use]
-    pub const fn new(pool: Arc<DbPool>) -> Self {
-        Self { pool }
+    pub fn new(repository: Arc<dyn Repository>, restore_grace_period: Duration) -> Self {
+        Self {
+            repository,
+            restore_grace_period,
+        }
     }
 }
 
@@ -400,15 +546,7 @@ impl FortressService for MyFortressService {
             .fortress
             .ok_or_else(|| Status::not_found("missing fortress field"))?
             .into();
-        let mut conn = self
-            .pool
-            .get()
-            .map_err(|e| Status::internal(format!("{e}")))?;
-        let fortress = diesel::insert_into(fortresses::table)
-            .values(new_fortress)
-            .returning(Fortress::as_returning())
-            .get_result(&mut conn)
-            .map_err(|e| Status::internal(format!("{e}")))?;
+        let fortress = self.repository.create_fortress(new_fortress).await?;
         let fortress = CreateFortressResponse {
             fortress: Some(fortress.into()),
         };
@@ -416,19 +554,37 @@ impl FortressService for MyFortressService {
         Ok(Response::new(fortress))
     }
 
+    async fn create_fortress_with_buildings(
+        &self,
+        request: Request<CreateFortressWithBuildingsRequest>,
+    ) -> Result<Response<CreateFortressWithBuildingsResponse>, Status> {
+        let req = request.into_inner();
+        let new_fortress: NewFortress = req
+            .fortress
+            .ok_or_else(|| Status::invalid_argument("missing fortress field"))?
+            .into();
+        let max_fortresses_per_owner = req.max_fortresses_per_owner.map(i64::from);
+        let (fortress, buildings) = self
+            .repository
+            .create_fortress_with_buildings(
+                new_fortress,
+                req.building_names,
+                max_fortresses_per_owner,
+            )
+            .await?;
+
+        Ok(Response::new(CreateFortressWithBuildingsResponse {
+            fortress: Some(fortress.into()),
+            buildings: buildings.into_iter().map(Into::into).collect(),
+        }))
+    }
+
     async fn get_fortress(
         &self,
         request: Request<GetFortressRequest>,
     ) -> Result<Response<GetFortressResponse>, Status> {
         let fortress_id = request.into_inner().id;
-        let mut conn = self
-            .pool
-            .get()
-            .map_err(|e| Status::internal(format!("{e}")))?;
-        let fortress: Fortress = fortresses::table
-            .filter(fortresses::id.eq(fortress_id))
-            .get_result(&mut conn)
-            .map_err(|e| Status::not_found(format!("{e}")))?;
+        let fortress = self.repository.get_fortress(fortress_id).await?;
         let fortress = GetFortressResponse {
             fortress: Some(fortress.into()),
         };
@@ -436,26 +592,50 @@ impl FortressService for MyFortressService {
         Ok(Response::new(fortress))
     }
 
+    async fn get_fortresses(
+        &self,
+        request: Request<GetFortressesRequest>,
+    ) -> Result<Response<GetFortressesResponse>, Status> {
+        let ids = request.into_inner().ids;
+        check_batch_size(&ids)?;
+        let fortresses = self.repository.get_fortresses(ids).await?;
+        let fortresses = GetFortressesResponse {
+            fortresses: fortresses.into_iter().map(Into::into).collect(),
+        };
+
+        Ok(Response::new(fortresses))
+    }
+
+    async fn get_fortress_with_buildings(
+        &self,
+        request: Request<GetFortressWithBuildingsRequest>,
+    ) -> Result<Response<GetFortressWithBuildingsResponse>, Status> {
+        let fortress_id = request.into_inner().id;
+        let (fortress, buildings) = self
+            .repository
+            .get_fortress_with_buildings(fortress_id)
+            .await?;
+
+        Ok(Response::new(GetFortressWithBuildingsResponse {
+            fortress: Some(fortress.into()),
+            buildings: buildings.into_iter().map(Into::into).collect(),
+        }))
+    }
+
     async fn update_fortress(
         &self,
         request: Request<UpdateFortressRequest>,
     ) -> Result<Response<UpdateFortressResponse>, Status> {
-        let update_fortress = request
-            .into_inner()
+        let req = request.into_inner();
+        let update_fortress = req
             .fortress
             .ok_or_else(|| Status::not_found("missing fortress field"))?;
         let fortress_id = update_fortress.id;
         let update_fortress: UpdateFortress = update_fortress.into();
-        let mut conn = self
-            .pool
-            .get()
-            .map_err(|e| Status::internal(format!("{e}")))?;
-        let fortress = diesel::update(fortresses::table)
-            .filter(fortresses::id.eq(fortress_id))
-            .set(update_fortress)
-            .returning(Fortress::as_returning())
-            .get_result(&mut conn)
-            .map_err(|e| Status::internal(format!("{e}")))?;
+        let fortress = self
+            .repository
+            .update_fortress(fortress_id, update_fortress, req.expected_version)
+            .await?;
         let fortress = UpdateFortressResponse {
             fortress: Some(fortress.into()),
         };
@@ -468,41 +648,54 @@ impl FortressService for MyFortressService {
         request: Request<DeleteFortressRequest>,
     ) -> Result<Response<DeleteFortressResponse>, Status> {
         let fortress_id = request.into_inner().id;
-        let mut conn = self
-            .pool
-            .get()
-            .map_err(|e| Status::internal(format!("{e}")))?;
-        let _building_delete_result = diesel::delete(buildings::table)
-            .filter(buildings::fortress_id.eq(fortress_id))
-            .execute(&mut conn)
-            .map_err(|e| Status::internal(format!("{e}")))?;
-        let fortress_delete_result = diesel::delete(fortresses::table)
-            .filter(fortresses::id.eq(fortress_id))
-            .execute(&mut conn)
-            .map_err(|e| Status::internal(format!("{e}")))?;
-        let success = fortress_delete_result != 0;
+        let success = self.repository.delete_fortress(fortress_id).await?;
         let success = DeleteFortressResponse { success };
 
         Ok(Response::new(success))
     }
 
+    async fn restore_fortress(
+        &self,
+        request: Request<RestoreFortressRequest>,
+    ) -> Result<Response<RestoreFortressResponse>, Status> {
+        let req = request.into_inner();
+        let fortress = self
+            .repository
+            .restore_fortress(
+                req.id,
+                req.owner_id,
+                self.restore_grace_period,
+                req.max_fortresses_per_owner.map(i64::from),
+            )
+            .await?;
+        let fortress = RestoreFortressResponse {
+            fortress: Some(fortress.into()),
+        };
+
+        Ok(Response::new(fortress))
+    }
+
     async fn list_fortresses(
         &self,
         request: Request<ListFortressesRequest>,
     ) -> Result<Response<ListFortressesResponse>, Status> {
-        let mut conn = self
-            .pool
-            .get()
-            .map_err(|e| Status::internal(format!("{e}")))?;
-        let mut query = fortresses::table.into_boxed();
-        if let Some(owner) = request.into_inner().owner_id {
-            query = query.filter(fortresses::owner_id.eq(owner));
-        }
-        let fortresses: Vec<Fortress> = query
-            .load(&mut conn)
-            .map_err(|e| Status::internal(format!("{e}")))?;
+        let req = request.into_inner();
+        let page_size = page_size(req.page_size)?;
+        let sort = fortress_sort(req.sort_field)?;
+        let query = FortressQuery {
+            owner_id: req.owner_id,
+            sort,
+            direction: sort_direction(req.sort_direction)?,
+            after: parse_cursor(&req.page_token)?,
+            limit: i64::from(page_size) + 1,
+        };
+        let mut fortresses = self.repository.list_fortresses(query).await?;
+        let next_page_token = truncate_page(&mut fortresses, page_size)
+            .map(|last| cursor_token(sort.key(last), last.id))
+            .unwrap_or_default();
         let fortresses = ListFortressesResponse {
             fortresses: fortresses.into_iter().map(Into::into).collect(),
+            next_page_token,
         };
 
         Ok(Response::new(fortresses))
@@ -516,73 +709,1532 @@ impl FortressService for MyFortressService {
         let fortress_id = req.id;
         let bonus_building_name = req.bonus_building_name;
         let base = req.base.unwrap_or(1);
+        if base < 0 {
+            return Err(Status::invalid_argument("base must not be negative"));
+        }
         let resource = ResourceKind::try_from(req.resource)
             .map_err(|_| Status::invalid_argument("invalid resource kind"))?;
-        let sql = match resource {
-            ResourceKind::Gold => {
-                r"
-                UPDATE fortresses
-                SET gold = gold + $2
-                    + COALESCE((SELECT SUM(level)::int
-                                FROM buildings
-                                WHERE fortress_id = $1 AND name = $3), 0)
-                WHERE id = $1
-                RETURNING id, owner_id, gold, food, wood, energy
-            "
-            }
-            ResourceKind::Food => {
-                r"
-                UPDATE fortresses
-                SET food = food + $2
-                    + COALESCE((SELECT SUM(level)::int
-                                FROM buildings
-                                WHERE fortress_id = $1 AND name = $3), 0)
-                WHERE id = $1
-                RETURNING id, owner_id, gold, food, wood, energy
-            "
-            }
-            ResourceKind::Wood => {
-                r"
-                UPDATE fortresses
-                SET wood = wood + $2
-                    + COALESCE((SELECT SUM(level)::int
-                                FROM buildings
-                                WHERE fortress_id = $1 AND name = $3), 0)
-                WHERE id = $1
-                RETURNING id, owner_id, gold, food, wood, energy
-            "
-            }
-            ResourceKind::Energy => {
-                r"
-                UPDATE fortresses
-                SET energy = energy + $2
-                    + COALESCE((SELECT SUM(level)::int
-                                FROM buildings
-                                WHERE fortress_id = $1 AND name = $3), 0)
-                WHERE id = $1
-                RETURNING id, owner_id, gold, food, wood, energy
-            "
-            }
+        let resource = match resource {
+            ResourceKind::Gold => Resource::Gold,
+            ResourceKind::Food => Resource::Food,
+            ResourceKind::Wood => Resource::Wood,
+            ResourceKind::Energy => Resource::Energy,
             ResourceKind::Unspecified => {
                 return Err(Status::invalid_argument("resource kind is required"));
             }
         };
-        let mut conn = self
-            .pool
-            .get()
-            .map_err(|e| Status::internal(format!("{e}")))?;
-        let fortress_row: FortressRow = diesel::sql_query(sql)
-            .bind::<Integer, _>(fortress_id)
-            .bind::<Integer, _>(base)
-            .bind::<Text, _>(bonus_building_name)
-            .get_result(&mut conn)
-            .map_err(|e| match e {
-                diesel::result::Error::NotFound => Status::not_found("fortress not found"),
-                _ => Status::internal("db error"),
-            })?;
+        let fortress = self
+            .repository
+            .collect_fortress_resource(
+                fortress_id,
+                resource,
+                base,
+                bonus_building_name,
+                req.expected_fortress_version,
+            )
+            .await?;
 
         Ok(Response::new(CollectFortressResourceResponse {
-            fortress: Some(fortress_row.into()),
+            fortress: Some(fortress.into()),
+        }))
+    }
+
+    async fn get_fortress_history(
+        &self,
+        request: Request<GetFortressHistoryRequest>,
+    ) -> Result<Response<GetFortressHistoryResponse>, Status> {
+        let req = request.into_inner();
+        let page_size = page_size(req.page_size)?;
+        let before_id = parse_before_id(&req.page_token)?;
+        let mut events = self
+            .repository
+            .list_fortress_events(req.fortress_id, before_id, i64::from(page_size) + 1)
+            .await?;
+        let next_page_token = truncate_page(&mut events, page_size)
+            .map(|event| event.id.to_string())
+            .unwrap_or_default();
+
+        Ok(Response::new(GetFortressHistoryResponse {
+            events: events.into_iter().map(Into::into).collect(),
+            next_page_token,
         }))
     }
+
+    async fn scout_fortress(
+        &self,
+        request: Request<ScoutFortressRequest>,
+    ) -> Result<Response<ScoutFortressResponse>, Status> {
+        let req = request.into_inner();
+        if req.spy_fortress_id == req.target_fortress_id {
+            return Err(Status::invalid_argument("a fortress can't scout itself"));
+        }
+        let (spy_fortress, report) = self
+            .repository
+            .scout_fortress(
+                req.spy_fortress_id,
+                req.target_fortress_id,
+                req.energy_cost,
+                req.success,
+                req.detected,
+            )
+            .await?;
+
+        Ok(Response::new(ScoutFortressResponse {
+            spy_fortress: Some(spy_fortress.into()),
+            report: report.map(Into::into),
+        }))
+    }
+
+    async fn list_scout_reports(
+        &self,
+        request: Request<ListScoutReportsRequest>,
+    ) -> Result<Response<ListScoutReportsResponse>, Status> {
+        let req = request.into_inner();
+        let page_size = page_size(req.page_size)?;
+        let before_id = parse_before_id(&req.page_token)?;
+        let mut reports = self
+            .repository
+            .list_scout_reports(req.spy_fortress_id, before_id, i64::from(page_size) + 1)
+            .await?;
+        let next_page_token = truncate_page(&mut reports, page_size)
+            .map(|(report, _)| report.id.to_string())
+            .unwrap_or_default();
+
+        Ok(Response::new(ListScoutReportsResponse {
+            reports: reports.into_iter().map(Into::into).collect(),
+            next_page_token,
+        }))
+    }
+
+    async fn list_camps(
+        &self,
+        request: Request<ListCampsRequest>,
+    ) -> Result<Response<ListCampsResponse>, Status> {
+        let camps = self
+            .repository
+            .list_camps(request.into_inner().owner_id)
+            .await?;
+
+        Ok(Response::new(ListCampsResponse {
+            camps: camps.into_iter().map(Into::into).collect(),
+        }))
+    }
+
+    async fn spawn_camp(
+        &self,
+        request: Request<SpawnCampRequest>,
+    ) -> Result<Response<SpawnCampResponse>, Status> {
+        let req = request.into_inner();
+        let loot = req.loot.unwrap_or_default();
+        let new_camp = NewCamp {
+            owner_id: req.owner_id,
+            slot: req.slot,
+            strength: req.strength,
+            gold: loot.gold,
+            food: loot.food,
+            wood: loot.wood,
+            energy: loot.energy,
+        };
+        let camp = self.repository.spawn_camp(new_camp).await?;
+
+        Ok(Response::new(SpawnCampResponse {
+            camp: Some(camp.into()),
+        }))
+    }
+
+    async fn raid_camp(
+        &self,
+        request: Request<RaidCampRequest>,
+    ) -> Result<Response<RaidCampResponse>, Status> {
+        let req = request.into_inner();
+        let respawn_after = u64::try_from(req.respawn_after_secs)
+            .map(Duration::from_secs)
+            .map_err(|_| Status::invalid_argument("respawn_after_secs must not be negative"))?;
+        let (fortress, camp) = self
+            .repository
+            .raid_camp(
+                req.camp_id,
+                req.fortress_id,
+                req.energy_cost,
+                req.success,
+                respawn_after,
+            )
+            .await?;
+
+        Ok(Response::new(RaidCampResponse {
+            fortress: Some(fortress.into()),
+            camp: Some(camp.into()),
+        }))
+    }
+
+    async fn get_daily_reward(
+        &self,
+        request: Request<GetDailyRewardRequest>,
+    ) -> Result<Response<GetDailyRewardResponse>, Status> {
+        let daily_reward = self
+            .repository
+            .get_daily_reward(request.into_inner().owner_id)
+            .await?;
+
+        Ok(Response::new(GetDailyRewardResponse {
+            daily_reward: daily_reward.map(Into::into),
+        }))
+    }
+
+    async fn claim_daily_reward(
+        &self,
+        request: Request<ClaimDailyRewardRequest>,
+    ) -> Result<Response<ClaimDailyRewardResponse>, Status> {
+        let req = request.into_inner();
+        let rewards = req.rewards.into_iter().map(Into::into).collect();
+        let (fortress, daily_reward, reward) = self
+            .repository
+            .claim_daily_reward(req.owner_id, req.fortress_id, rewards)
+            .await?;
+
+        Ok(Response::new(ClaimDailyRewardResponse {
+            fortress: Some(fortress.into()),
+            daily_reward: Some(daily_reward.into()),
+            reward: Some(reward.into()),
+        }))
+    }
+}
+
+pub struct MyClockService {
+    clock: Arc<SkewedClock>,
+}
+
+impl MyClockService {
+    #[must_use]
+    pub const fn new(clock: Arc<SkewedClock>) -> Self {
+        Self { clock }
+    }
+}
+
+#[tonic::async_trait]
+impl ClockService for MyClockService {
+    async fn get_clock(
+        &self,
+        _request: Request<GetClockRequest>,
+    ) -> Result<Response<GetClockResponse>, Status> {
+        Ok(Response::new(GetClockResponse {
+            now: self.clock.unix_secs(),
+            skew_secs: self.clock.skew(),
+        }))
+    }
+
+    async fn set_clock_skew(
+        &self,
+        request: Request<SetClockSkewRequest>,
+    ) -> Result<Response<SetClockSkewResponse>, Status> {
+        if !cfg!(debug_assertions) {
+            return Err(Status::failed_precondition(
+                "the clock can only be skewed in debug builds",
+            ));
+        }
+        self.clock.set_skew(request.into_inner().skew_secs);
+
+        Ok(Response::new(SetClockSkewResponse {
+            now: self.clock.unix_secs(),
+            skew_secs: self.clock.skew(),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        pb::common::v2::{self, Costs as PbCosts},
+        repository::{
+            memory::MemoryRepository,
+            sql::{PoolConfig, SqlRepository, build_pool},
+        },
+    };
+    use rusty::{clock::FakeClock, connection::run_pending_migrations, error_details::error_info};
+
+    const RESTORE_GRACE_PERIOD: Duration = Duration::from_hours(1);
+
+    /// Time of the tests, which only moves when they advance it.
+    fn fake_clock() -> Arc<FakeClock> {
+        Arc::new(FakeClock::at_unix_secs(1_700_000_000))
+    }
+
+    fn sqlite_repository(clock: Arc<dyn Clock>) -> SqlRepository {
+        // Every connection to `:memory:` opens a distinct database.
+        let config = PoolConfig {
+            max_size: 1,
+            connection_timeout: Duration::from_secs(5),
+            statement_timeout: Some(Duration::from_secs(5)),
+        };
+        let Ok(pool) = build_pool("sqlite://:memory:".to_owned(), &config) else {
+            panic!("failed to open the SQLite database");
+        };
+        let migrated = pool
+            .get()
+            .map_err(|e| e.to_string())
+            .and_then(|mut conn| run_pending_migrations(&mut conn).map_err(|e| e.to_string()));
+        if let Err(e) = migrated {
+            panic!("failed to migrate the SQLite database: {e}");
+        }
+        SqlRepository::new(pool, clock)
+    }
+
+    fn repositories_at(clock: &Arc<FakeClock>) -> [Arc<dyn Repository>; 2] {
+        [
+            Arc::new(MemoryRepository::new(clock.clone())),
+            Arc::new(sqlite_repository(clock.clone())),
+        ]
+    }
+
+    fn repositories() -> [Arc<dyn Repository>; 2] {
+        repositories_at(&fake_clock())
+    }
+
+    fn services() -> Vec<(MyBuildingService, MyFortressService)> {
+        repositories()
+            .into_iter()
+            .map(|repository| {
+                (
+                    MyBuildingService::new(repository.clone()),
+                    MyFortressService::new(repository, RESTORE_GRACE_PERIOD),
+                )
+            })
+            .collect()
+    }
+
+    async fn new_fortress(fortress_service: &MyFortressService, gold: i64) -> Option<v2::Fortress> {
+        let request = CreateFortressRequest {
+            fortress: Some(v2::NewFortress {
+                owner_id: "player".to_owned(),
+                owner_name: "Player".to_owned(),
+                gold,
+                food: gold,
+                wood: gold,
+                energy: gold,
+            }),
+        };
+        fortress_service
+            .create_fortress(Request::new(request))
+            .await
+            .ok()?
+            .into_inner()
+            .fortress
+    }
+
+    async fn new_building(
+        building_service: &MyBuildingService,
+        name: &str,
+        level: i32,
+        fortress_id: i32,
+    ) -> Option<v2::Building> {
+        let request = CreateBuildingRequest {
+            building: Some(v2::NewBuilding {
+                name: name.to_owned(),
+                level,
+                fortress_id,
+            }),
+        };
+        building_service
+            .create_building(Request::new(request))
+            .await
+            .ok()?
+            .into_inner()
+            .building
+    }
+
+    fn upgrade_request(building_id: i32, cost: i64) -> Request<UpgradeBuildingAtomicRequest> {
+        Request::new(UpgradeBuildingAtomicRequest {
+            building_id,
+            costs: Some(PbCosts {
+                gold: cost,
+                food: cost,
+                wood: cost,
+                energy: cost,
+            }),
+            expected_building_level: Some(0),
+            expected_building_version: None,
+            max_building_level: 20,
+        })
+    }
+
+    #[tokio::test]
+    async fn create_building_requires_fortress() {
+        for (building_service, fortress_service) in services() {
+            let fortress = new_fortress(&fortress_service, 0).await;
+            let fortress_id = fortress.map_or(0, |fortress| fortress.id);
+
+            let building = new_building(&building_service, "bank", 0, fortress_id).await;
+            assert_eq!(
+                building.map(|building| building.fortress_id),
+                Some(fortress_id)
+            );
+            let orphan = new_building(&building_service, "bank", 0, fortress_id + 1).await;
+            assert_eq!(orphan, None);
+        }
+    }
+
+    #[tokio::test]
+    async fn create_building_rejects_duplicate_name() {
+        for (building_service, fortress_service) in services() {
+            let fortress_id = new_fortress(&fortress_service, 0)
+                .await
+                .map_or(0, |fortress| fortress.id);
+            let bank = new_building(&building_service, "bank", 0, fortress_id).await;
+            assert!(bank.is_some());
+
+            let duplicate = building_service
+                .create_building(Request::new(CreateBuildingRequest {
+                    building: Some(v2::NewBuilding {
+                        name: "bank".to_owned(),
+                        level: 0,
+                        fortress_id,
+                    }),
+                }))
+                .await;
+            let reason = duplicate
+                .as_ref()
+                .err()
+                .and_then(error_info)
+                .map(|info| info.reason);
+            assert_eq!(
+                duplicate.err().map(|e| e.code()),
+                Some(tonic::Code::AlreadyExists)
+            );
+            assert_eq!(reason.as_deref(), Some("DUPLICATE_BUILDING"));
+            let negative = building_service
+                .create_building(Request::new(CreateBuildingRequest {
+                    building: Some(v2::NewBuilding {
+                        name: "farm".to_owned(),
+                        level: -1,
+                        fortress_id,
+                    }),
+                }))
+                .await;
+            assert_eq!(
+                negative.err().map(|e| e.code()),
+                Some(tonic::Code::InvalidArgument)
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn upgrade_building_atomic_spends_costs() {
+        for (building_service, fortress_service) in services() {
+            let fortress_id = new_fortress(&fortress_service, 100)
+                .await
+                .map_or(0, |fortress| fortress.id);
+            let building_id = new_building(&building_service, "farm", 0, fortress_id)
+                .await
+                .map_or(0, |building| building.id);
+
+            let upgraded = building_service
+                .upgrade_building_atomic(upgrade_request(building_id, 30))
+                .await
+                .map(Response::into_inner);
+            let Ok(upgraded) = upgraded else {
+                panic!("upgrade failed: {upgraded:?}");
+            };
+            assert_eq!(upgraded.building.map(|building| building.level), Some(1));
+            assert_eq!(upgraded.fortress.map(|fortress| fortress.gold), Some(70));
+
+            let stale = building_service
+                .upgrade_building_atomic(upgrade_request(building_id, 30))
+                .await;
+            assert_eq!(stale.err().map(|e| e.code()), Some(tonic::Code::Aborted));
+        }
+    }
+
+    #[tokio::test]
+    async fn upgrade_building_atomic_rolls_back_on_insufficient_resources() {
+        for (building_service, fortress_service) in services() {
+            let fortress_id = new_fortress(&fortress_service, 10)
+                .await
+                .map_or(0, |fortress| fortress.id);
+            let building_id = new_building(&building_service, "farm", 0, fortress_id)
+                .await
+                .map_or(0, |building| building.id);
+
+            let result = building_service
+                .upgrade_building_atomic(upgrade_request(building_id, 30))
+                .await;
+            let Err(status) = result else {
+                panic!("upgrade succeeded without resources");
+            };
+            assert_eq!(status.code(), tonic::Code::FailedPrecondition);
+            let info = error_info(&status);
+            assert_eq!(
+                info.as_ref().map(|info| info.reason.as_str()),
+                Some("INSUFFICIENT_RESOURCES")
+            );
+            assert_eq!(
+                info.as_ref()
+                    .and_then(|info| info.metadata.get("missing_gold"))
+                    .map(String::as_str),
+                Some("20")
+            );
+            let building = building_service
+                .get_building(Request::new(GetBuildingRequest { id: building_id }))
+                .await
+                .ok()
+                .and_then(|response| response.into_inner().building);
+            assert_eq!(building.map(|building| building.level), Some(0));
+            let fortress = fortress_service
+                .get_fortress(Request::new(GetFortressRequest { id: fortress_id }))
+                .await
+                .ok()
+                .and_then(|response| response.into_inner().fortress);
+            assert_eq!(fortress.map(|fortress| fortress.gold), Some(10));
+        }
+    }
+
+    #[tokio::test]
+    async fn collect_fortress_resource_adds_bonus_levels() {
+        for (building_service, fortress_service) in services() {
+            let fortress_id = new_fortress(&fortress_service, 0)
+                .await
+                .map_or(0, |fortress| fortress.id);
+            new_building(&building_service, "bank", 3, fortress_id).await;
+            new_building(&building_service, "farm", 7, fortress_id).await;
+
+            let fortress = fortress_service
+                .collect_fortress_resource(Request::new(CollectFortressResourceRequest {
+                    id: fortress_id,
+                    resource: ResourceKind::Gold as i32,
+                    bonus_building_name: "bank".to_owned(),
+                    base: None,
+                    expected_fortress_version: None,
+                }))
+                .await
+                .ok()
+                .and_then(|response| response.into_inner().fortress);
+            assert_eq!(
+                fortress.map(|fortress| (fortress.gold, fortress.food)),
+                Some((4, 0))
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn collect_fortress_resource_saturates() {
+        for (building_service, fortress_service) in services() {
+            let fortress_id = new_fortress(&fortress_service, i64::MAX - 1)
+                .await
+                .map_or(0, |fortress| fortress.id);
+            new_building(&building_service, "bank", 5, fortress_id).await;
+
+            let fortress = fortress_service
+                .collect_fortress_resource(Request::new(CollectFortressResourceRequest {
+                    id: fortress_id,
+                    resource: ResourceKind::Gold as i32,
+                    bonus_building_name: "bank".to_owned(),
+                    base: Some(i64::MAX),
+                    expected_fortress_version: None,
+                }))
+                .await
+                .ok()
+                .and_then(|response| response.into_inner().fortress);
+            assert_eq!(fortress.map(|fortress| fortress.gold), Some(i64::MAX));
+            let collected = fortress_service
+                .get_fortress_history(Request::new(GetFortressHistoryRequest {
+                    fortress_id,
+                    page_size: 1,
+                    page_token: String::new(),
+                }))
+                .await
+                .ok()
+                .and_then(|response| response.into_inner().events.into_iter().next())
+                .map(|event| (event.kind(), event.gold));
+            assert_eq!(collected, Some((FortressEventKind::Collect, 1)));
+        }
+    }
+
+    #[tokio::test]
+    async fn update_fortress_rejects_stale_version() {
+        for (_, fortress_service) in services() {
+            let fortress = new_fortress(&fortress_service, 0).await;
+            let (fortress_id, version) =
+                fortress.map_or((0, 0), |fortress| (fortress.id, fortress.version));
+            let request = |gold| {
+                Request::new(UpdateFortressRequest {
+                    fortress: Some(v2::UpdateFortress {
+                        id: fortress_id,
+                        gold: Some(gold),
+                        food: None,
+                        wood: None,
+                        energy: None,
+                    }),
+                    expected_version: Some(version),
+                })
+            };
+
+            let updated = fortress_service
+                .update_fortress(request(5))
+                .await
+                .ok()
+                .and_then(|response| response.into_inner().fortress)
+                .map(|fortress| (fortress.gold, fortress.version));
+            assert_eq!(updated, Some((5, version + 1)));
+            let stale = fortress_service.update_fortress(request(7)).await;
+            assert_eq!(stale.err().map(|e| e.code()), Some(tonic::Code::Aborted));
+            let fortress = fortress_service
+                .get_fortress(Request::new(GetFortressRequest { id: fortress_id }))
+                .await
+                .ok()
+                .and_then(|response| response.into_inner().fortress);
+            assert_eq!(fortress.map(|fortress| fortress.gold), Some(5));
+        }
+    }
+
+    #[tokio::test]
+    async fn updates_are_stamped_with_the_game_clock() {
+        for (_, fortress_service) in services() {
+            let fortress = new_fortress(&fortress_service, 0).await;
+            let (fortress_id, created_at) =
+                fortress.map_or((0, 0), |fortress| (fortress.id, fortress.created_at));
+            let _ = fortress_service
+                .update_fortress(Request::new(UpdateFortressRequest {
+                    fortress: Some(v2::UpdateFortress {
+                        id: fortress_id,
+                        gold: Some(5),
+                        food: None,
+                        wood: None,
+                        energy: None,
+                    }),
+                    expected_version: None,
+                }))
+                .await;
+
+            // Read back, past anything the database does after the update.
+            let fortress = fortress_service
+                .get_fortress(Request::new(GetFortressRequest { id: fortress_id }))
+                .await
+                .ok()
+                .and_then(|response| response.into_inner().fortress);
+            assert_eq!(
+                fortress.map(|fortress| (fortress.gold, fortress.updated_at)),
+                Some((5, created_at))
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn update_fortress_rejects_negative_resources() {
+        for (_, fortress_service) in services() {
+            let fortress_id = new_fortress(&fortress_service, 10)
+                .await
+                .map_or(0, |fortress| fortress.id);
+
+            let result = fortress_service
+                .update_fortress(Request::new(UpdateFortressRequest {
+                    fortress: Some(v2::UpdateFortress {
+                        id: fortress_id,
+                        gold: Some(-1),
+                        food: None,
+                        wood: None,
+                        energy: None,
+                    }),
+                    expected_version: None,
+                }))
+                .await;
+            assert_eq!(
+                result.err().map(|e| e.code()),
+                Some(tonic::Code::InvalidArgument)
+            );
+            let fortress = fortress_service
+                .get_fortress(Request::new(GetFortressRequest { id: fortress_id }))
+                .await
+                .ok()
+                .and_then(|response| response.into_inner().fortress);
+            assert_eq!(fortress.map(|fortress| fortress.gold), Some(10));
+        }
+    }
+
+    #[tokio::test]
+    async fn create_fortress_with_buildings_enforces_limit() {
+        for (building_service, fortress_service) in services() {
+            let request = || {
+                Request::new(CreateFortressWithBuildingsRequest {
+                    fortress: Some(v2::NewFortress {
+                        owner_id: "player".to_owned(),
+                        owner_name: "Player".to_owned(),
+                        gold: 0,
+                        food: 0,
+                        wood: 0,
+                        energy: 0,
+                    }),
+                    building_names: vec!["bank".to_owned(), "farm".to_owned()],
+                    max_fortresses_per_owner: Some(1),
+                })
+            };
+
+            let created = fortress_service
+                .create_fortress_with_buildings(request())
+                .await
+                .map(Response::into_inner);
+            let Ok(created) = created else {
+                panic!("create failed: {created:?}");
+            };
+            let fortress_id = created.fortress.map_or(0, |fortress| fortress.id);
+            assert!(
+                created
+                    .buildings
+                    .iter()
+                    .all(|building| building.fortress_id == fortress_id && building.level == 0)
+            );
+            assert_eq!(created.buildings.len(), 2);
+
+            let over_limit = fortress_service
+                .create_fortress_with_buildings(request())
+                .await;
+            assert_eq!(
+                over_limit.err().map(|e| e.code()),
+                Some(tonic::Code::ResourceExhausted)
+            );
+            let fortresses = fortress_service
+                .list_fortresses(Request::new(ListFortressesRequest::default()))
+                .await
+                .ok()
+                .map(|response| response.into_inner().fortresses.len());
+            assert_eq!(fortresses, Some(1));
+            let buildings = building_service
+                .list_buildings(Request::new(ListBuildingsRequest::default()))
+                .await
+                .ok()
+                .map(|response| response.into_inner().buildings.len());
+            assert_eq!(buildings, Some(2));
+        }
+    }
+
+    #[tokio::test]
+    async fn delete_fortress_hides_buildings() {
+        for (building_service, fortress_service) in services() {
+            let fortress_id = new_fortress(&fortress_service, 0)
+                .await
+                .map_or(0, |fortress| fortress.id);
+            new_building(&building_service, "bank", 0, fortress_id).await;
+
+            let deleted = fortress_service
+                .delete_fortress(Request::new(DeleteFortressRequest { id: fortress_id }))
+                .await
+                .ok()
+                .map(|response| response.into_inner().success);
+            assert_eq!(deleted, Some(true));
+            let buildings = building_service
+                .list_buildings(Request::new(ListBuildingsRequest::default()))
+                .await
+                .ok()
+                .map(|response| response.into_inner().buildings);
+            assert_eq!(buildings, Some(Vec::new()));
+        }
+    }
+
+    #[tokio::test]
+    async fn restore_fortress_brings_back_deleted_fortress() {
+        for (building_service, fortress_service) in services() {
+            let fortress_id = new_fortress(&fortress_service, 0)
+                .await
+                .map_or(0, |fortress| fortress.id);
+            new_building(&building_service, "bank", 0, fortress_id).await;
+            let _ = fortress_service
+                .delete_fortress(Request::new(DeleteFortressRequest { id: fortress_id }))
+                .await;
+
+            let code = fortress_service
+                .get_fortress(Request::new(GetFortressRequest { id: fortress_id }))
+                .await
+                .err()
+                .map(|status| status.code());
+            assert_eq!(code, Some(tonic::Code::NotFound));
+            let code = fortress_service
+                .restore_fortress(Request::new(RestoreFortressRequest {
+                    id: fortress_id,
+                    owner_id: Some("someone else".to_owned()),
+                    max_fortresses_per_owner: None,
+                }))
+                .await
+                .err()
+                .map(|status| status.code());
+            assert_eq!(code, Some(tonic::Code::NotFound));
+            let restored = fortress_service
+                .restore_fortress(Request::new(RestoreFortressRequest {
+                    id: fortress_id,
+                    owner_id: Some("player".to_owned()),
+                    max_fortresses_per_owner: None,
+                }))
+                .await
+                .ok()
+                .and_then(|response| response.into_inner().fortress)
+                .map(|fortress| fortress.id);
+            assert_eq!(restored, Some(fortress_id));
+            let buildings = building_service
+                .list_buildings_by_fortress(Request::new(ListBuildingsByFortressRequest {
+                    fortress_id,
+                }))
+                .await
+                .ok()
+                .map(|response| response.into_inner().buildings.len());
+            assert_eq!(buildings, Some(1));
+            let code = fortress_service
+                .restore_fortress(Request::new(RestoreFortressRequest {
+                    id: fortress_id,
+                    owner_id: None,
+                    max_fortresses_per_owner: None,
+                }))
+                .await
+                .err()
+                .map(|status| status.code());
+            assert_eq!(code, Some(tonic::Code::FailedPrecondition));
+        }
+    }
+
+    #[tokio::test]
+    async fn restore_fortress_enforces_limit() {
+        for (_, fortress_service) in services() {
+            let fortress_id = new_fortress(&fortress_service, 0)
+                .await
+                .map_or(0, |fortress| fortress.id);
+            let _ = fortress_service
+                .delete_fortress(Request::new(DeleteFortressRequest { id: fortress_id }))
+                .await;
+            new_fortress(&fortress_service, 0).await;
+            let restore = |max_fortresses_per_owner| {
+                fortress_service.restore_fortress(Request::new(RestoreFortressRequest {
+                    id: fortress_id,
+                    owner_id: Some("player".to_owned()),
+                    max_fortresses_per_owner,
+                }))
+            };
+
+            let code = restore(Some(1)).await.err().map(|status| status.code());
+            assert_eq!(code, Some(tonic::Code::ResourceExhausted));
+            let restored = restore(Some(2))
+                .await
+                .ok()
+                .and_then(|response| response.into_inner().fortress)
+                .map(|fortress| fortress.id);
+            assert_eq!(restored, Some(fortress_id));
+        }
+    }
+
+    #[tokio::test]
+    async fn purge_deleted_fortresses_after_grace_period() {
+        let clock = fake_clock();
+        for repository in repositories_at(&clock) {
+            let building_service = MyBuildingService::new(repository.clone());
+            let fortress_service = MyFortressService::new(repository.clone(), RESTORE_GRACE_PERIOD);
+            let fortress_id = new_fortress(&fortress_service, 0)
+                .await
+                .map_or(0, |fortress| fortress.id);
+            new_building(&building_service, "bank", 0, fortress_id).await;
+            let _ = fortress_service
+                .delete_fortress(Request::new(DeleteFortressRequest { id: fortress_id }))
+                .await;
+
+            clock.advance(RESTORE_GRACE_PERIOD / 2);
+            let purged = repository
+                .purge_deleted_fortresses(RESTORE_GRACE_PERIOD)
+                .await
+                .ok();
+            assert_eq!(purged, Some(0));
+            clock.advance(RESTORE_GRACE_PERIOD);
+            let code = fortress_service
+                .restore_fortress(Request::new(RestoreFortressRequest {
+                    id: fortress_id,
+                    owner_id: None,
+                    max_fortresses_per_owner: None,
+                }))
+                .await
+                .err()
+                .map(|status| status.code());
+            assert_eq!(code, Some(tonic::Code::FailedPrecondition));
+            let purged = repository
+                .purge_deleted_fortresses(RESTORE_GRACE_PERIOD)
+                .await
+                .ok();
+            assert_eq!(purged, Some(1));
+            let code = fortress_service
+                .restore_fortress(Request::new(RestoreFortressRequest {
+                    id: fortress_id,
+                    owner_id: None,
+                    max_fortresses_per_owner: None,
+                }))
+                .await
+                .err()
+                .map(|status| status.code());
+            assert_eq!(code, Some(tonic::Code::NotFound));
+        }
+    }
+
+    #[tokio::test]
+    async fn get_fortress_with_buildings_reads_live_rows() {
+        for (building_service, fortress_service) in services() {
+            let fortress_id = new_fortress(&fortress_service, 0)
+                .await
+                .map_or(0, |fortress| fortress.id);
+            let empty_id = new_fortress(&fortress_service, 0)
+                .await
+                .map_or(0, |fortress| fortress.id);
+            let farm_id = new_building(&building_service, "farm", 2, fortress_id)
+                .await
+                .map_or(0, |building| building.id);
+            let bank_id = new_building(&building_service, "bank", 1, fortress_id)
+                .await
+                .map_or(0, |building| building.id);
+
+            let found = fortress_service
+                .get_fortress_with_buildings(Request::new(GetFortressWithBuildingsRequest {
+                    id: fortress_id,
+                }))
+                .await
+                .map(Response::into_inner);
+            let Ok(found) = found else {
+                panic!("get failed: {found:?}");
+            };
+            assert_eq!(
+                found.fortress.map(|fortress| fortress.id),
+                Some(fortress_id)
+            );
+            let buildings: Vec<_> = found
+                .buildings
+                .iter()
+                .map(|building| (building.id, building.level))
+                .collect();
+            assert_eq!(buildings, [(farm_id, 2), (bank_id, 1)]);
+            let empty = fortress_service
+                .get_fortress_with_buildings(Request::new(GetFortressWithBuildingsRequest {
+                    id: empty_id,
+                }))
+                .await
+                .ok()
+                .map(|response| response.into_inner().buildings.len());
+            assert_eq!(empty, Some(0));
+            let owner = building_service
+                .get_building_with_fortress(Request::new(GetBuildingWithFortressRequest {
+                    id: bank_id,
+                }))
+                .await
+                .ok()
+                .and_then(|response| response.into_inner().fortress)
+                .map(|fortress| fortress.owner_id);
+            assert_eq!(owner.as_deref(), Some("player"));
+
+            let _ = fortress_service
+                .delete_fortress(Request::new(DeleteFortressRequest { id: fortress_id }))
+                .await;
+            let code = fortress_service
+                .get_fortress_with_buildings(Request::new(GetFortressWithBuildingsRequest {
+                    id: fortress_id,
+                }))
+                .await
+                .err()
+                .map(|status| status.code());
+            assert_eq!(code, Some(tonic::Code::NotFound));
+            let code = building_service
+                .get_building_with_fortress(Request::new(GetBuildingWithFortressRequest {
+                    id: bank_id,
+                }))
+                .await
+                .err()
+                .map(|status| status.code());
+            assert_eq!(code, Some(tonic::Code::NotFound));
+        }
+    }
+
+    #[tokio::test]
+    async fn get_fortresses_skips_unknown_ids() {
+        for (building_service, fortress_service) in services() {
+            let first_id = new_fortress(&fortress_service, 0)
+                .await
+                .map_or(0, |fortress| fortress.id);
+            let second_id = new_fortress(&fortress_service, 0)
+                .await
+                .map_or(0, |fortress| fortress.id);
+            let building_id = new_building(&building_service, "bank", 0, first_id)
+                .await
+                .map_or(0, |building| building.id);
+
+            let fortresses = fortress_service
+                .get_fortresses(Request::new(GetFortressesRequest {
+                    ids: vec![second_id, second_id + 1, first_id],
+                }))
+                .await
+                .ok()
+                .map(|response| {
+                    let fortresses = response.into_inner().fortresses;
+                    fortresses.iter().map(|fortress| fortress.id).collect()
+                });
+            assert_eq!(fortresses, Some(vec![first_id, second_id]));
+            let buildings = building_service
+                .get_buildings(Request::new(GetBuildingsRequest {
+                    ids: vec![building_id, building_id + 1],
+                }))
+                .await
+                .ok()
+                .map(|response| response.into_inner().buildings.len());
+            assert_eq!(buildings, Some(1));
+            let code = fortress_service
+                .get_fortresses(Request::new(GetFortressesRequest {
+                    ids: vec![first_id; MAX_BATCH_SIZE + 1],
+                }))
+                .await
+                .err()
+                .map(|status| status.code());
+            assert_eq!(code, Some(tonic::Code::InvalidArgument));
+        }
+    }
+
+    #[tokio::test]
+    async fn list_buildings_pages_with_filters() {
+        for (building_service, fortress_service) in services() {
+            let mut ids = Vec::new();
+            for levels in [[1, 3, 3], [2, 0, 0]] {
+                let fortress_id = new_fortress(&fortress_service, 0)
+                    .await
+                    .map_or(0, |fortress| fortress.id);
+                for (name, level) in ["farm", "bank", "sawmill"].into_iter().zip(levels) {
+                    let building = new_building(&building_service, name, level, fortress_id).await;
+                    ids.push(building.map_or(0, |building| building.id));
+                }
+            }
+
+            let &[farm_a, bank_a, sawmill_a, farm_b, _, _] = ids.as_slice() else {
+                panic!("failed to create the buildings: {ids:?}");
+            };
+
+            let mut pages = Vec::new();
+            let mut page_token = String::new();
+            loop {
+                let page = building_service
+                    .list_buildings(Request::new(ListBuildingsRequest {
+                        page_size: 2,
+                        page_token,
+                        min_level: Some(1),
+                        sort_field: v2::BuildingSortField::Level.into(),
+                        sort_direction: v2::SortDirection::Descending.into(),
+                        ..ListBuildingsRequest::default()
+                    }))
+                    .await
+                    .map(Response::into_inner);
+                let Ok(page) = page else {
+                    panic!("list failed: {page:?}");
+                };
+                pages.push(
+                    page.buildings
+                        .iter()
+                        .map(|building| building.id)
+                        .collect::<Vec<_>>(),
+                );
+                if page.next_page_token.is_empty() {
+                    break;
+                }
+                page_token = page.next_page_token;
+            }
+            assert_eq!(pages, [[sawmill_a, bank_a], [farm_b, farm_a]]);
+            let farms = building_service
+                .list_buildings(Request::new(ListBuildingsRequest {
+                    name: Some("farm".to_owned()),
+                    ..ListBuildingsRequest::default()
+                }))
+                .await
+                .ok()
+                .map(|response| {
+                    let buildings = response.into_inner().buildings;
+                    buildings.iter().map(|building| building.id).collect()
+                });
+            assert_eq!(farms, Some(vec![farm_a, farm_b]));
+        }
+    }
+
+    #[tokio::test]
+    async fn list_buildings_filters_by_owner_and_fortress() {
+        for (building_service, fortress_service) in services() {
+            let mut fortress_ids = Vec::new();
+            let mut building_ids = Vec::new();
+            for owner_id in ["player", "player", "someone else"] {
+                let fortress = fortress_service
+                    .create_fortress(Request::new(CreateFortressRequest {
+                        fortress: Some(v2::NewFortress {
+                            owner_id: owner_id.to_owned(),
+                            ..v2::NewFortress::default()
+                        }),
+                    }))
+                    .await
+                    .ok()
+                    .and_then(|response| response.into_inner().fortress);
+                let fortress_id = fortress.map_or(0, |fortress| fortress.id);
+                let building = new_building(&building_service, "farm", 1, fortress_id).await;
+                fortress_ids.push(fortress_id);
+                building_ids.push(building.map_or(0, |building| building.id));
+            }
+
+            let &[first, second, theirs] = building_ids.as_slice() else {
+                panic!("failed to create the buildings: {building_ids:?}");
+            };
+            let &[_, second_fortress, their_fortress] = fortress_ids.as_slice() else {
+                panic!("failed to create the fortresses: {fortress_ids:?}");
+            };
+
+            let list = |owner_id: Option<&str>, fortress_ids: Vec<i32>| {
+                building_service.list_buildings(Request::new(ListBuildingsRequest {
+                    owner_id: owner_id.map(str::to_owned),
+                    fortress_ids,
+                    ..ListBuildingsRequest::default()
+                }))
+            };
+            let ids = |response: Result<Response<ListBuildingsResponse>, Status>| {
+                response.ok().map(|response| {
+                    let buildings = response.into_inner().buildings;
+                    buildings
+                        .iter()
+                        .map(|building| building.id)
+                        .collect::<Vec<_>>()
+                })
+            };
+            assert_eq!(
+                ids(list(Some("player"), vec![]).await),
+                Some(vec![first, second])
+            );
+            assert_eq!(
+                ids(list(None, vec![second_fortress, their_fortress]).await),
+                Some(vec![second, theirs])
+            );
+            assert_eq!(
+                ids(list(Some("player"), vec![their_fortress]).await),
+                Some(vec![])
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn list_fortresses_pages_by_gold() {
+        for (_, fortress_service) in services() {
+            let mut ids = Vec::new();
+            for gold in [5, 10, 5] {
+                let fortress = new_fortress(&fortress_service, gold).await;
+                ids.push(fortress.map_or(0, |fortress| fortress.id));
+            }
+
+            let &[poor, rich, poor_too] = ids.as_slice() else {
+                panic!("failed to create the fortresses: {ids:?}");
+            };
+
+            let mut listed = Vec::new();
+            let mut page_token = String::new();
+            loop {
+                let page = fortress_service
+                    .list_fortresses(Request::new(ListFortressesRequest {
+                        page_size: 1,
+                        page_token,
+                        sort_field: v2::FortressSortField::Gold.into(),
+                        ..ListFortressesRequest::default()
+                    }))
+                    .await
+                    .map(Response::into_inner);
+                let Ok(page) = page else {
+                    panic!("list failed: {page:?}");
+                };
+                assert!(page.fortresses.len() <= 1);
+                listed.extend(page.fortresses.iter().map(|fortress| fortress.id));
+                if page.next_page_token.is_empty() {
+                    break;
+                }
+                page_token = page.next_page_token;
+            }
+            assert_eq!(listed, [poor, poor_too, rich]);
+            let code = fortress_service
+                .list_fortresses(Request::new(ListFortressesRequest {
+                    page_token: "not a token".to_owned(),
+                    ..ListFortressesRequest::default()
+                }))
+                .await
+                .err()
+                .map(|status| status.code());
+            assert_eq!(code, Some(tonic::Code::InvalidArgument));
+        }
+    }
+
+    #[tokio::test]
+    async fn get_fortress_history_pages_newest_first() {
+        for (building_service, fortress_service) in services() {
+            let fortress_id = new_fortress(&fortress_service, 100)
+                .await
+                .map_or(0, |fortress| fortress.id);
+            let building_id = new_building(&building_service, "farm", 0, fortress_id)
+                .await
+                .map_or(0, |building| building.id);
+            let collected = fortress_service
+                .collect_fortress_resource(Request::new(CollectFortressResourceRequest {
+                    id: fortress_id,
+                    resource: ResourceKind::Wood as i32,
+                    bonus_building_name: "farm".to_owned(),
+                    base: Some(5),
+                    expected_fortress_version: None,
+                }))
+                .await;
+            assert!(collected.is_ok());
+            let upgraded = building_service
+                .upgrade_building_atomic(upgrade_request(building_id, 30))
+                .await;
+            assert!(upgraded.is_ok());
+
+            let mut events = Vec::new();
+            let mut page_token = String::new();
+            loop {
+                let page = fortress_service
+                    .get_fortress_history(Request::new(GetFortressHistoryRequest {
+                        fortress_id,
+                        page_size: 2,
+                        page_token,
+                    }))
+                    .await
+                    .map(Response::into_inner);
+                let Ok(page) = page else {
+                    panic!("history failed: {page:?}");
+                };
+                assert!(page.events.len() <= 2);
+                events.extend(page.events);
+                if page.next_page_token.is_empty() {
+                    break;
+                }
+                page_token = page.next_page_token;
+            }
+            let summary: Vec<_> = events
+                .iter()
+                .map(|event| (event.kind(), event.building_id, event.gold, event.wood))
+                .collect();
+            assert_eq!(
+                summary,
+                [
+                    (FortressEventKind::Upgrade, Some(building_id), -30, -30),
+                    (FortressEventKind::Collect, None, 0, 5),
+                    (FortressEventKind::Create, None, 100, 100),
+                ]
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn scout_fortress_spends_energy_and_stores_reports() {
+        for (building_service, fortress_service) in services() {
+            let spy_id = new_fortress(&fortress_service, 10)
+                .await
+                .map_or(0, |fortress| fortress.id);
+            let target_id = new_fortress(&fortress_service, 70)
+                .await
+                .map_or(0, |fortress| fortress.id);
+            let sanctuary = new_building(&building_service, "sanctuary", 3, target_id).await;
+            assert!(sanctuary.is_some());
+            let scout = |success, detected| {
+                fortress_service.scout_fortress(Request::new(ScoutFortressRequest {
+                    spy_fortress_id: spy_id,
+                    target_fortress_id: target_id,
+                    energy_cost: 4,
+                    success,
+                    detected,
+                }))
+            };
+
+            let failed = scout(false, true).await.map(Response::into_inner);
+            let Ok(failed) = failed else {
+                panic!("scout failed: {failed:?}");
+            };
+            assert_eq!(failed.spy_fortress.map(|fortress| fortress.energy), Some(6));
+            assert!(failed.report.is_none());
+
+            let succeeded = scout(true, false).await.map(Response::into_inner);
+            let Some(report) = succeeded.ok().and_then(|response| response.report) else {
+                panic!("successful scout without report");
+            };
+            assert_eq!((report.gold, report.energy), (70, 70));
+            let seen: Vec<_> = report
+                .buildings
+                .iter()
+                .map(|building| (building.name.as_str(), building.level))
+                .collect();
+            assert_eq!(seen, [("sanctuary", 3)]);
+
+            let broke = scout(true, true).await;
+            assert_eq!(
+                broke.err().map(|status| status.code()),
+                Some(tonic::Code::FailedPrecondition)
+            );
+
+            // Only the detected attempt shows up in the target history.
+            let history = fortress_service
+                .get_fortress_history(Request::new(GetFortressHistoryRequest {
+                    fortress_id: target_id,
+                    page_size: 0,
+                    page_token: String::new(),
+                }))
+                .await
+                .map(|response| {
+                    let events = response.into_inner().events;
+                    events
+                        .iter()
+                        .map(|event| (event.kind(), event.other_fortress_id))
+                        .collect::<Vec<_>>()
+                });
+            assert_eq!(
+                history.ok(),
+                Some(vec![
+                    (FortressEventKind::Scouted, Some(spy_id)),
+                    (FortressEventKind::Create, None),
+                ])
+            );
+
+            let reports = fortress_service
+                .list_scout_reports(Request::new(ListScoutReportsRequest {
+                    spy_fortress_id: spy_id,
+                    page_size: 0,
+                    page_token: String::new(),
+                }))
+                .await
+                .map(|response| {
+                    let reports = response.into_inner().reports;
+                    reports.iter().map(|report| report.id).collect::<Vec<_>>()
+                });
+            assert_eq!(reports.ok(), Some(vec![report.id]));
+        }
+    }
+
+    #[tokio::test]
+    async fn raid_camp_credits_loot_once() {
+        for (_, fortress_service) in services() {
+            let fortress_id = new_fortress(&fortress_service, 10)
+                .await
+                .map_or(0, |fortress| fortress.id);
+            let spawn = |strength, gold| {
+                fortress_service.spawn_camp(Request::new(SpawnCampRequest {
+                    owner_id: "player".to_owned(),
+                    slot: 0,
+                    strength,
+                    loot: Some(PbCosts {
+                        gold,
+                        food: 0,
+                        wood: 0,
+                        energy: 3,
+                    }),
+                }))
+            };
+            let raid = |camp_id, success| {
+                fortress_service.raid_camp(Request::new(RaidCampRequest {
+                    camp_id,
+                    fortress_id,
+                    energy_cost: 4,
+                    success,
+                    respawn_after_secs: 0,
+                }))
+            };
+
+            let spawned = spawn(5, 7).await.map(Response::into_inner);
+            let Some(camp) = spawned.ok().and_then(|response| response.camp) else {
+                panic!("camp not spawned");
+            };
+            // The slot is taken until the camp is raided.
+            let kept = spawn(50, 70).await.map(Response::into_inner);
+            let kept = kept.ok().and_then(|response| response.camp);
+            assert_eq!(
+                kept.map(|kept| (kept.id, kept.strength)),
+                Some((camp.id, 5))
+            );
+
+            let failed = raid(camp.id, false).await.map(Response::into_inner);
+            let Ok(failed) = failed else {
+                panic!("raid failed: {failed:?}");
+            };
+            let resources = failed.fortress.map(|f| (f.gold, f.energy));
+            assert_eq!(resources, Some((10, 6)));
+            assert_eq!(failed.camp.map(|camp| camp.respawn_at), Some(None));
+
+            let succeeded = raid(camp.id, true).await.map(Response::into_inner);
+            let Ok(succeeded) = succeeded else {
+                panic!("raid failed: {succeeded:?}");
+            };
+            let resources = succeeded.fortress.map(|f| (f.gold, f.energy));
+            assert_eq!(resources, Some((17, 5)));
+            assert!(succeeded.camp.is_some_and(|camp| camp.respawn_at.is_some()));
+
+            let again = raid(camp.id, true).await;
+            let reason = again
+                .as_ref()
+                .err()
+                .and_then(error_info)
+                .map(|info| info.reason);
+            assert_eq!(reason.as_deref(), Some("CAMP_NOT_AVAILABLE"));
+
+            // Past its respawn time, the slot takes the new camp.
+            let respawned = spawn(8, 9).await.map(Response::into_inner);
+            let respawned = respawned.ok().and_then(|response| response.camp);
+            assert_eq!(
+                respawned.map(|camp| (camp.id, camp.strength, camp.respawn_at)),
+                Some((camp.id, 8, None))
+            );
+
+            let list = |owner_id: &str| {
+                fortress_service.list_camps(Request::new(ListCampsRequest {
+                    owner_id: owner_id.to_owned(),
+                }))
+            };
+            let listed = list("player").await.map(|response| {
+                let camps = response.into_inner().camps;
+                camps.iter().map(|camp| camp.slot).collect::<Vec<_>>()
+            });
+            assert_eq!(listed.ok(), Some(vec![0]));
+            let others = list("other")
+                .await
+                .map(|response| response.into_inner().camps);
+            assert_eq!(others.ok(), Some(vec![]));
+
+            let history = fortress_service
+                .get_fortress_history(Request::new(GetFortressHistoryRequest {
+                    fortress_id,
+                    page_size: 0,
+                    page_token: String::new(),
+                }))
+                .await
+                .map(|response| {
+                    let events = response.into_inner().events;
+                    events
+                        .iter()
+                        .map(|event| (event.kind(), event.gold, event.energy))
+                        .collect::<Vec<_>>()
+                });
+            assert_eq!(
+                history.ok(),
+                Some(vec![
+                    (FortressEventKind::Raid, 7, -1),
+                    (FortressEventKind::Raid, 0, -4),
+                    (FortressEventKind::Create, 10, 10),
+                ])
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn raided_camp_respawns_after_its_delay() {
+        let clock = fake_clock();
+        for repository in repositories_at(&clock) {
+            let fortress_service = MyFortressService::new(repository, RESTORE_GRACE_PERIOD);
+            let fortress_id = new_fortress(&fortress_service, 10)
+                .await
+                .map_or(0, |fortress| fortress.id);
+            let spawn = |strength| {
+                fortress_service.spawn_camp(Request::new(SpawnCampRequest {
+                    owner_id: "player".to_owned(),
+                    slot: 0,
+                    strength,
+                    loot: Some(PbCosts::default()),
+                }))
+            };
+            let spawned = spawn(5).await.map(Response::into_inner);
+            let Some(camp) = spawned.ok().and_then(|response| response.camp) else {
+                panic!("camp not spawned");
+            };
+            let raided = fortress_service
+                .raid_camp(Request::new(RaidCampRequest {
+                    camp_id: camp.id,
+                    fortress_id,
+                    energy_cost: 1,
+                    success: true,
+                    respawn_after_secs: 60,
+                }))
+                .await
+                .ok()
+                .and_then(|response| response.into_inner().camp);
+            let respawn_at = raided.and_then(|camp| camp.respawn_at);
+            assert_eq!(respawn_at, Some(clock.unix_secs() + 60));
+
+            clock.advance(Duration::from_secs(59));
+            let kept = spawn(8).await.map(Response::into_inner);
+            let kept = kept.ok().and_then(|response| response.camp);
+            assert_eq!(kept.map(|camp| camp.strength), Some(5));
+            clock.advance(Duration::from_secs(1));
+            let respawned = spawn(8).await.map(Response::into_inner);
+            let respawned = respawned.ok().and_then(|response| response.camp);
+            assert_eq!(
+                respawned.map(|camp| (camp.id, camp.strength, camp.respawn_at)),
+                Some((camp.id, 8, None))
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn daily_reward_streak_follows_utc_days() {
+        // 22:13 UTC, the next day starts in less than two hours.
+        let clock = fake_clock();
+        let start = clock.now();
+        for repository in repositories_at(&clock) {
+            clock.set(start);
+            let fortress_service = MyFortressService::new(repository, RESTORE_GRACE_PERIOD);
+            let fortress_id = new_fortress(&fortress_service, 0)
+                .await
+                .map_or(0, |fortress| fortress.id);
+            let claim = |owner_id: &str| {
+                fortress_service.claim_daily_reward(Request::new(ClaimDailyRewardRequest {
+                    owner_id: owner_id.to_owned(),
+                    fortress_id,
+                    rewards: vec![
+                        PbCosts {
+                            gold: 1,
+                            ..PbCosts::default()
+                        },
+                        PbCosts {
+                            gold: 2,
+                            ..PbCosts::default()
+                        },
+                    ],
+                }))
+            };
+            let claimed = |response: Result<Response<ClaimDailyRewardResponse>, Status>| {
+                let response = response.ok()?.into_inner();
+                let streak = response.daily_reward?.streak;
+                Some((streak, response.reward?.gold, response.fortress?.gold))
+            };
+
+            let code = claim("other").await.err().map(|status| status.code());
+            assert_eq!(code, Some(tonic::Code::NotFound));
+            assert_eq!(claimed(claim("player").await), Some((1, 1, 1)));
+            let again = claim("player").await;
+            let reason = again
+                .as_ref()
+                .err()
+                .and_then(error_info)
+                .map(|info| info.reason);
+            assert_eq!(reason.as_deref(), Some("DAILY_REWARD_CLAIMED"));
+
+            clock.advance(Duration::from_hours(2));
+            assert_eq!(claimed(claim("player").await), Some((2, 2, 3)));
+            clock.advance(Duration::from_hours(24));
+            assert_eq!(claimed(claim("player").await), Some((3, 2, 5)));
+            // A missed day starts a new streak.
+            clock.advance(Duration::from_hours(48));
+            assert_eq!(claimed(claim("player").await), Some((1, 1, 6)));
+
+            let streak = fortress_service
+                .get_daily_reward(Request::new(GetDailyRewardRequest {
+                    owner_id: "player".to_owned(),
+                }))
+                .await
+                .ok()
+                .and_then(|response| response.into_inner().daily_reward)
+                .map(|daily_reward| daily_reward.streak);
+            assert_eq!(streak, Some(1));
+            let history = fortress_service
+                .get_fortress_history(Request::new(GetFortressHistoryRequest {
+                    fortress_id,
+                    page_size: 0,
+                    page_token: String::new(),
+                }))
+                .await
+                .map(|response| {
+                    response
+                        .into_inner()
+                        .events
+                        .first()
+                        .map(v2::FortressEvent::kind)
+                });
+            assert_eq!(history.ok().flatten(), Some(FortressEventKind::DailyReward));
+        }
+    }
 }
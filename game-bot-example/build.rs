@@ -2,8 +2,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("cargo:rerun-if-changed=../proto");
     tonic_prost_build::configure().compile_protos(
         &[
-            "../proto/common/v1/common.proto",
-            "../proto/game/v1/game.proto",
+            "../proto/common/v2/common.proto",
+            "../proto/game/v2/game.proto",
         ],
         &["../proto"],
     )?;
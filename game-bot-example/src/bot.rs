@@ -0,0 +1,241 @@
+use crate::{
+    pb::game::v2::{
+        CollectFortressEnergyRequest, CollectFortressFoodRequest, CollectFortressGoldRequest,
+        CollectFortressWoodRequest, GetFortressOverviewRequest, ImproveBuildingRequest,
+        building_service_client::BuildingServiceClient,
+        fortress_service_client::FortressServiceClient,
+    },
+    session::{AuthInterceptor, Session},
+    strategy::{Action, FortressState, Resource, Strategy},
+};
+use std::time::Duration;
+use tonic::{
+    Code, Status,
+    service::interceptor::InterceptedService,
+    transport::{Channel, ClientTlsConfig, Endpoint},
+};
+
+type FortressClient = FortressServiceClient<InterceptedService<Channel, AuthInterceptor>>;
+type BuildingClient = BuildingServiceClient<InterceptedService<Channel, AuthInterceptor>>;
+
+/// Delay before the first reconnection, doubled on each failure up to
+/// [`MAX_BACKOFF`].
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_mins(1);
+
+pub struct BotConfig {
+    pub server_url: String,
+    pub fortress_id: i32,
+    /// Pause between two turns.
+    pub interval: Duration,
+    /// Number of turns to play, `None` to play until stopped.
+    pub turns: Option<u64>,
+}
+
+/// Plays a strategy on a fortress, refreshing the session when the access
+/// token expires and reconnecting when the server goes away.
+pub struct Bot {
+    config: BotConfig,
+    session: Session,
+    strategy: Box<dyn Strategy>,
+}
+
+struct Backoff {
+    delay: Duration,
+}
+
+impl Backoff {
+    const fn new() -> Self {
+        Self {
+            delay: INITIAL_BACKOFF,
+        }
+    }
+
+    async fn wait(&mut self) {
+        println!("Retrying in {}s...", self.delay.as_secs());
+        tokio::time::sleep(self.delay).await;
+        self.delay = (self.delay * 2).min(MAX_BACKOFF);
+    }
+}
+
+fn endpoint(url: &str) -> Result<Endpoint, Box<dyn std::error::Error>> {
+    let mut endpoint = Channel::from_shared(url.to_owned())?;
+    if url.starts_with("https://") {
+        endpoint = endpoint.tls_config(ClientTlsConfig::new().with_native_roots())?;
+    }
+    Ok(endpoint)
+}
+
+/// Errors which end the turn: the session or the connection must be renewed
+/// before anything else can succeed.
+fn ends_turn(status: &Status) -> bool {
+    matches!(status.code(), Code::Unauthenticated | Code::Unavailable)
+}
+
+impl Bot {
+    #[must_use]
+    pub fn new(config: BotConfig, session: Session, strategy: Box<dyn Strategy>) -> Self {
+        Self {
+            config,
+            session,
+            strategy,
+        }
+    }
+
+    /// Plays until the configured number of turns is reached.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the session cannot be refreshed, or when the fortress does
+    /// not exist or belongs to someone else.
+    pub async fn run(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let endpoint = endpoint(&self.config.server_url)?;
+        if !self.session.has_access_token() {
+            println!("Initializing the session...");
+            self.session.refresh().await?;
+        }
+
+        let mut backoff = Backoff::new();
+        let mut turns = 0;
+        loop {
+            let channel = match endpoint.connect().await {
+                Ok(channel) => channel,
+                Err(e) => {
+                    println!("Cannot reach the server: {e}");
+                    backoff.wait().await;
+                    continue;
+                }
+            };
+            println!("Connected bot!");
+            let mut fortress_client = FortressServiceClient::with_interceptor(
+                channel.clone(),
+                self.session.interceptor(),
+            );
+            let mut building_client =
+                BuildingServiceClient::with_interceptor(channel, self.session.interceptor());
+
+            loop {
+                match self.turn(&mut fortress_client, &mut building_client).await {
+                    Ok(()) => {
+                        backoff = Backoff::new();
+                        turns += 1;
+                        if self.config.turns.is_some_and(|max| turns >= max) {
+                            println!("{turns} turns played!");
+                            return Ok(());
+                        }
+                        if turns % 100 == 0 {
+                            println!("{turns} turns played...");
+                        }
+                        tokio::time::sleep(self.config.interval).await;
+                    }
+                    Err(status) => match status.code() {
+                        Code::Unauthenticated => {
+                            println!("The access token has expired. Silent refresh...");
+                            self.session.refresh().await?;
+                        }
+                        // Nothing to play on, waiting will not change it.
+                        Code::NotFound | Code::PermissionDenied | Code::InvalidArgument => {
+                            return Err(status.into());
+                        }
+                        Code::Unavailable => {
+                            println!("Connection lost: {}", status.message());
+                            backoff.wait().await;
+                            break;
+                        }
+                        _ => {
+                            println!("Unexpected error: {status:?}");
+                            backoff.wait().await;
+                        }
+                    },
+                }
+            }
+        }
+    }
+
+    async fn turn(
+        &mut self,
+        fortress_client: &mut FortressClient,
+        building_client: &mut BuildingClient,
+    ) -> Result<(), Status> {
+        let id = self.config.fortress_id;
+        let state = state(id, fortress_client).await?;
+        for action in self.strategy.decide(&state) {
+            match play(id, action, fortress_client, building_client).await {
+                Ok(()) => {}
+                Err(status) if ends_turn(&status) => return Err(status),
+                Err(status) => println!("{action:?} failed: {}", status.message()),
+            }
+        }
+        Ok(())
+    }
+}
+
+async fn state(id: i32, fortress_client: &mut FortressClient) -> Result<FortressState, Status> {
+    let overview = fortress_client
+        .get_fortress_overview(GetFortressOverviewRequest { id })
+        .await?
+        .into_inner();
+    let fortress = overview.fortress.ok_or_else(|| {
+        Status::permission_denied(format!("Fortress {id} belongs to someone else"))
+    })?;
+    Ok(FortressState {
+        fortress,
+        buildings: overview.buildings,
+        production: overview.production.unwrap_or_default(),
+    })
+}
+
+async fn play(
+    id: i32,
+    action: Action,
+    fortress_client: &mut FortressClient,
+    building_client: &mut BuildingClient,
+) -> Result<(), Status> {
+    match action {
+        Action::Collect(Resource::Gold) => {
+            fortress_client
+                .collect_fortress_gold(CollectFortressGoldRequest {
+                    id,
+                    expected_version: None,
+                })
+                .await?;
+        }
+        Action::Collect(Resource::Food) => {
+            fortress_client
+                .collect_fortress_food(CollectFortressFoodRequest {
+                    id,
+                    expected_version: None,
+                })
+                .await?;
+        }
+        Action::Collect(Resource::Wood) => {
+            fortress_client
+                .collect_fortress_wood(CollectFortressWoodRequest {
+                    id,
+                    expected_version: None,
+                })
+                .await?;
+        }
+        Action::Collect(Resource::Energy) => {
+            fortress_client
+                .collect_fortress_energy(CollectFortressEnergyRequest {
+                    id,
+                    expected_version: None,
+                })
+                .await?;
+        }
+        Action::Upgrade { building_id } => {
+            let building = building_client
+                .improve_building(ImproveBuildingRequest {
+                    id: building_id,
+                    expected_version: None,
+                })
+                .await?
+                .into_inner()
+                .building
+                .unwrap_or_default();
+            println!("Upgraded {} to level {}", building.name, building.level);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,24 @@
+//! Building blocks of game bots: a [`Session`] keeps the access token fresh
+//! and a [`Bot`] plays a [`Strategy`] turn after turn on one fortress.
+
+#[allow(clippy::pedantic, clippy::nursery)]
+pub mod pb {
+    pub mod common {
+        pub mod v2 {
+            tonic::include_proto!("common.v2");
+        }
+    }
+    pub mod game {
+        pub mod v2 {
+            tonic::include_proto!("game.v2");
+        }
+    }
+}
+
+mod bot;
+mod session;
+pub mod strategy;
+
+pub use bot::{Bot, BotConfig};
+pub use session::{AuthInterceptor, Session};
+pub use strategy::{Action, FortressState, Strategy};
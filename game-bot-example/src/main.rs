@@ -1,160 +1,113 @@
-#![allow(clippy::similar_names)]
-
-#[allow(clippy::pedantic, clippy::nursery)]
-pub mod pb {
-    pub mod common {
-        pub mod v1 {
-            tonic::include_proto!("common.v1");
-        }
-    }
-    pub mod game {
-        pub mod v1 {
-            tonic::include_proto!("game.v1");
-        }
-    }
-}
-
-use pb::game::v1::{
-    CollectFortressEnergyRequest, CollectFortressFoodRequest, CollectFortressGoldRequest,
-    CollectFortressWoodRequest, fortress_service_client::FortressServiceClient,
-};
-use serde_json::{Value, json};
-use std::fs;
-use tonic::{
-    Status,
-    metadata::MetadataValue,
-    service::Interceptor,
-    transport::{Channel, ClientTlsConfig},
-};
+use clap::Parser;
+use game_bot_example::{Bot, BotConfig, Session, strategy::Builtin};
+use serde::Deserialize;
+use std::{fs, path::PathBuf, time::Duration};
 
 const SERVER_URL: &str = "https://rusty.anclarma.fr";
-const FORTRESS_ID: i32 = 42;
 const AUTH_URL: &str = "https://auth.rusty.anclarma.fr";
 const CLIENT_ID: &str = "rusty-client";
-// const SERVER_URL: &str = "http://localhost:8080";
-// const FORTRESS_ID: i32 = 1;
-// const AUTH_URL: &str = "http://localhost:8082";
-// const CLIENT_ID: &str = "rusty-client";
-
-#[derive(Clone)]
-struct AuthInterceptor {
-    token: String,
+const TOKEN_FILE: &str = ".bot_token";
+const INTERVAL_SECS: u64 = 1;
+
+/// Plays a fortress with one of the bundled strategies.
+///
+/// Every option can also be set in the config file, under the same name with
+/// `_` instead of `-`; the command line takes precedence.
+#[derive(Parser)]
+#[command(version)]
+struct Args {
+    /// TOML config file
+    #[arg(short, long)]
+    config: Option<PathBuf>,
+    /// Game server, the public one by default
+    #[arg(long)]
+    server_url: Option<String>,
+    /// Auth server, the public one by default
+    #[arg(long)]
+    auth_url: Option<String>,
+    /// OIDC client of the auth server [default: rusty-client]
+    #[arg(long)]
+    client_id: Option<String>,
+    /// Fortress to play, required
+    #[arg(short, long)]
+    fortress_id: Option<i32>,
+    /// collector, greedy-cheapest or balanced [default: collector]
+    #[arg(short, long)]
+    strategy: Option<Builtin>,
+    /// Seconds between two turns [default: 1]
+    #[arg(long)]
+    interval_secs: Option<u64>,
+    /// Turns to play before exiting [default: until stopped]
+    #[arg(long)]
+    turns: Option<u64>,
+    /// Tokens written by `game-client login`, or a bare refresh token
+    /// [default: `.bot_token`]
+    #[arg(long)]
+    token_file: Option<PathBuf>,
 }
 
-impl Interceptor for AuthInterceptor {
-    fn call(&mut self, mut req: tonic::Request<()>) -> Result<tonic::Request<()>, Status> {
-        let meta: MetadataValue<_> = format!("Bearer {}", self.token)
-            .parse()
-            .map_err(|_| Status::unauthenticated("Invalid token"))?;
-        req.metadata_mut().insert("authorization", meta);
-        Ok(req)
-    }
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct FileConfig {
+    server_url: Option<String>,
+    auth_url: Option<String>,
+    client_id: Option<String>,
+    fortress_id: Option<i32>,
+    strategy: Option<Builtin>,
+    interval_secs: Option<u64>,
+    turns: Option<u64>,
+    token_file: Option<PathBuf>,
 }
 
-async fn refresh_session(
-    auth_url: &str,
-    client_id: &str,
-    refresh_token: &str,
-) -> Result<Value, Box<dyn std::error::Error>> {
-    let client = reqwest::Client::new();
-    let res = client
-        .post(format!("{auth_url}/auth/v1/oidc/token"))
-        .header("Content-Type", "application/x-www-form-urlencoded")
-        .body(format!(
-            "grant_type=refresh_token&client_id={client_id}&refresh_token={refresh_token}"
-        ))
-        .send()
-        .await?;
-
-    if !res.status().is_success() {
-        return Err(format!("Rauthy error ({}) : {}", res.status(), res.text().await?).into());
+impl FileConfig {
+    fn load(path: Option<&PathBuf>) -> Result<Self, Box<dyn std::error::Error>> {
+        let Some(path) = path else {
+            return Ok(Self::default());
+        };
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Cannot read the config file {}: {e}", path.display()))?;
+        toml::from_str(&content)
+            .map_err(|e| format!("Invalid config file {}: {e}", path.display()).into())
     }
-
-    let new_session: Value = res.json().await?;
-    fs::write(".bot_token", serde_json::to_string_pretty(&new_session)?)?;
-    Ok(new_session)
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let content = fs::read_to_string(".bot_token").map_err(
-        |_| "The .bot_token file could not be found. Generate one using the game-client CLI.",
+    let args = Args::parse();
+    let file = FileConfig::load(args.config.as_ref())?;
+
+    let session = Session::load(
+        args.auth_url
+            .or(file.auth_url)
+            .unwrap_or_else(|| AUTH_URL.to_owned()),
+        args.client_id
+            .or(file.client_id)
+            .unwrap_or_else(|| CLIENT_ID.to_owned()),
+        args.token_file
+            .or(file.token_file)
+            .unwrap_or_else(|| PathBuf::from(TOKEN_FILE)),
     )?;
-
-    let mut session: Value = if content.trim().starts_with('{') {
-        serde_json::from_str(&content)?
-    } else {
-        json!({ "refresh_token": content.trim() })
+    let config = BotConfig {
+        server_url: args
+            .server_url
+            .or(file.server_url)
+            .unwrap_or_else(|| SERVER_URL.to_owned()),
+        fortress_id: args
+            .fortress_id
+            .or(file.fortress_id)
+            .ok_or("No fortress to play, set --fortress-id or fortress_id in the config file")?,
+        interval: Duration::from_secs(
+            args.interval_secs
+                .or(file.interval_secs)
+                .unwrap_or(INTERVAL_SECS),
+        ),
+        turns: args.turns.or(file.turns),
     };
+    let strategy = args.strategy.or(file.strategy).unwrap_or_default();
 
-    let mut endpoint = Channel::from_static(SERVER_URL);
-    if SERVER_URL.starts_with("https://") {
-        endpoint = endpoint.tls_config(ClientTlsConfig::new().with_native_roots())?;
-    }
-    let channel = endpoint.connect().await?;
-
-    println!("Connected bot!");
-
-    let mut i = 0;
-    while i < 1000 {
-        let mut access_token = session
-            .get("access_token")
-            .and_then(|v| v.as_str())
-            .unwrap_or_default()
-            .to_owned();
-        let refresh_token = session
-            .get("refresh_token")
-            .and_then(|v| v.as_str())
-            .unwrap_or_default()
-            .to_owned();
-
-        if access_token.is_empty() {
-            println!("Initializing the session...");
-            session = refresh_session(AUTH_URL, CLIENT_ID, &refresh_token).await?;
-            access_token = session
-                .get("access_token")
-                .and_then(|v| v.as_str())
-                .unwrap_or_default()
-                .to_owned();
-        }
-
-        let interceptor = AuthInterceptor {
-            token: access_token,
-        };
-        let mut fortress_client =
-            FortressServiceClient::with_interceptor(channel.clone(), interceptor);
-
-        match fortress_client
-            .collect_fortress_gold(CollectFortressGoldRequest { id: FORTRESS_ID })
-            .await
-        {
-            Ok(_) => {
-                let _ = fortress_client
-                    .collect_fortress_food(CollectFortressFoodRequest { id: FORTRESS_ID })
-                    .await;
-                let _ = fortress_client
-                    .collect_fortress_wood(CollectFortressWoodRequest { id: FORTRESS_ID })
-                    .await;
-                let _ = fortress_client
-                    .collect_fortress_energy(CollectFortressEnergyRequest { id: FORTRESS_ID })
-                    .await;
-
-                i += 1;
-                if i % 100 == 0 {
-                    println!("{i} cycles completed...");
-                }
-            }
-            Err(e) if e.code() == tonic::Code::Unauthenticated => {
-                println!("The access token has expired. Silent refresh...");
-                session = refresh_session(AUTH_URL, CLIENT_ID, &refresh_token).await?;
-            }
-            Err(e) => {
-                println!("Unexpected error: {e:?}");
-                tokio::time::sleep(std::time::Duration::from_secs(5)).await;
-            }
-        }
-    }
-
-    println!("Collection complete!");
-    Ok(())
+    println!(
+        "Playing fortress {} with the {strategy:?} strategy",
+        config.fortress_id
+    );
+    Bot::new(config, session, strategy.strategy()).run().await
 }
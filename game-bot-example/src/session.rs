@@ -0,0 +1,132 @@
+use serde_json::{Value, json};
+use std::{
+    fs,
+    path::PathBuf,
+    sync::{Arc, RwLock},
+};
+use tonic::{Status, metadata::MetadataValue, service::Interceptor};
+
+/// The tokens of the bot, saved back to their file on every refresh so that a
+/// restarted bot picks up the latest refresh token.
+pub struct Session {
+    auth_url: String,
+    client_id: String,
+    token_file: PathBuf,
+    access_token: Arc<RwLock<String>>,
+    refresh_token: String,
+}
+
+fn token(session: &Value, name: &str) -> String {
+    session
+        .get(name)
+        .and_then(|v| v.as_str())
+        .unwrap_or_default()
+        .to_owned()
+}
+
+impl Session {
+    /// Reads the tokens written by `game-client login`, or a bare refresh
+    /// token.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the file cannot be read or is not valid JSON.
+    pub fn load(
+        auth_url: String,
+        client_id: String,
+        token_file: PathBuf,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let content = fs::read_to_string(&token_file).map_err(|_| {
+            format!(
+                "The {} file could not be found. Generate one using the game-client CLI.",
+                token_file.display()
+            )
+        })?;
+        let session: Value = if content.trim().starts_with('{') {
+            serde_json::from_str(&content)?
+        } else {
+            json!({ "refresh_token": content.trim() })
+        };
+
+        Ok(Self {
+            auth_url,
+            client_id,
+            token_file,
+            access_token: Arc::new(RwLock::new(token(&session, "access_token"))),
+            refresh_token: token(&session, "refresh_token"),
+        })
+    }
+
+    #[must_use]
+    pub fn has_access_token(&self) -> bool {
+        self.access_token
+            .read()
+            .is_ok_and(|access_token| !access_token.is_empty())
+    }
+
+    /// An interceptor sending the access token, which follows the refreshes.
+    #[must_use]
+    pub fn interceptor(&self) -> AuthInterceptor {
+        AuthInterceptor {
+            token: Arc::clone(&self.access_token),
+        }
+    }
+
+    /// Exchanges the refresh token for new tokens, saved to the token file.
+    ///
+    /// # Errors
+    ///
+    /// Fails when the auth server rejects the refresh token or the tokens
+    /// cannot be saved.
+    pub async fn refresh(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let client = reqwest::Client::new();
+        let res = client
+            .post(format!("{}/auth/v1/oidc/token", self.auth_url))
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .body(format!(
+                "grant_type=refresh_token&client_id={}&refresh_token={}",
+                self.client_id, self.refresh_token
+            ))
+            .send()
+            .await?;
+
+        if !res.status().is_success() {
+            return Err(format!("Rauthy error ({}) : {}", res.status(), res.text().await?).into());
+        }
+
+        let new_session: Value = res.json().await?;
+        fs::write(
+            &self.token_file,
+            serde_json::to_string_pretty(&new_session)?,
+        )?;
+        let refresh_token = token(&new_session, "refresh_token");
+        if !refresh_token.is_empty() {
+            self.refresh_token = refresh_token;
+        }
+        if let Ok(mut access_token) = self.access_token.write() {
+            *access_token = token(&new_session, "access_token");
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone)]
+pub struct AuthInterceptor {
+    token: Arc<RwLock<String>>,
+}
+
+impl Interceptor for AuthInterceptor {
+    fn call(&mut self, mut req: tonic::Request<()>) -> Result<tonic::Request<()>, Status> {
+        let bearer = {
+            let Ok(token) = self.token.read() else {
+                return Err(Status::unauthenticated("Invalid token"));
+            };
+            format!("Bearer {token}")
+        };
+        let meta: MetadataValue<_> = bearer
+            .parse()
+            .map_err(|_| Status::unauthenticated("Invalid token"))?;
+        req.metadata_mut().insert("authorization", meta);
+        Ok(req)
+    }
+}
@@ -0,0 +1,305 @@
+//! What a bot does on each turn, and the strategies shipped with the crate.
+
+use crate::pb::{
+    common::v2::{Costs, Fortress},
+    game::v2::BuildingOverview,
+};
+use serde::Deserialize;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resource {
+    Gold,
+    Food,
+    Wood,
+    Energy,
+}
+
+impl Resource {
+    pub const ALL: [Self; 4] = [Self::Gold, Self::Food, Self::Wood, Self::Energy];
+
+    /// The building whose level raises the production of the resource.
+    #[must_use]
+    pub const fn bonus_building(self) -> &'static str {
+        match self {
+            Self::Gold => "bank",
+            Self::Food => "farm",
+            Self::Wood => "sawmill",
+            Self::Energy => "sanctuary",
+        }
+    }
+
+    const fn amount(self, costs: &Costs) -> i64 {
+        match self {
+            Self::Gold => costs.gold,
+            Self::Food => costs.food,
+            Self::Wood => costs.wood,
+            Self::Energy => costs.energy,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Collect(Resource),
+    /// Buildings all come with their fortress at level 0, so upgrading one
+    /// from level 0 is how it gets built.
+    Upgrade {
+        building_id: i32,
+    },
+}
+
+/// The fortress as the bot sees it at the start of a turn.
+pub struct FortressState {
+    pub fortress: Fortress,
+    /// Ordered by id.
+    pub buildings: Vec<BuildingOverview>,
+    /// What each collect adds to the fortress resources.
+    pub production: Costs,
+}
+
+const fn total(costs: &Costs) -> i64 {
+    costs
+        .gold
+        .saturating_add(costs.food)
+        .saturating_add(costs.wood)
+        .saturating_add(costs.energy)
+}
+
+impl FortressState {
+    #[must_use]
+    pub const fn can_afford(&self, costs: &Costs) -> bool {
+        self.fortress.gold >= costs.gold
+            && self.fortress.food >= costs.food
+            && self.fortress.wood >= costs.wood
+            && self.fortress.energy >= costs.energy
+    }
+
+    /// The buildings whose next level the fortress can pay for right now.
+    pub fn affordable(&self) -> impl Iterator<Item = (&BuildingOverview, &Costs)> {
+        self.buildings.iter().filter_map(|overview| {
+            overview
+                .upgrade_costs
+                .as_ref()
+                .filter(|costs| self.can_afford(costs))
+                .map(|costs| (overview, costs))
+        })
+    }
+}
+
+pub trait Strategy: Send {
+    /// The actions of this turn, played in order. A failed action is logged
+    /// and the next one is played.
+    fn decide(&mut self, state: &FortressState) -> Vec<Action>;
+}
+
+fn collect_all() -> Vec<Action> {
+    Resource::ALL.into_iter().map(Action::Collect).collect()
+}
+
+/// Collects every resource and never upgrades anything.
+pub struct Collector;
+
+impl Strategy for Collector {
+    fn decide(&mut self, _state: &FortressState) -> Vec<Action> {
+        collect_all()
+    }
+}
+
+/// Collects every resource, then upgrades the building whose next level is
+/// the cheapest among the affordable ones.
+pub struct GreedyCheapest;
+
+impl Strategy for GreedyCheapest {
+    fn decide(&mut self, state: &FortressState) -> Vec<Action> {
+        let mut actions = collect_all();
+        if let Some((overview, _)) = state.affordable().min_by_key(|(overview, costs)| {
+            (total(costs), overview.building.as_ref().map(|b| b.id))
+        }) {
+            actions.extend(overview.building.as_ref().map(|building| Action::Upgrade {
+                building_id: building.id,
+            }));
+        }
+        actions
+    }
+}
+
+/// Collects every resource, then upgrades the bonus building of the least
+/// produced resource, saving up for it rather than upgrading anything else.
+pub struct Balanced;
+
+impl Strategy for Balanced {
+    fn decide(&mut self, state: &FortressState) -> Vec<Action> {
+        let mut actions = collect_all();
+        let scarcest = Resource::ALL
+            .into_iter()
+            .filter_map(|resource| {
+                state
+                    .buildings
+                    .iter()
+                    .find(|overview| {
+                        overview
+                            .building
+                            .as_ref()
+                            .is_some_and(|b| b.name == resource.bonus_building())
+                    })
+                    .filter(|overview| overview.upgrade_costs.is_some())
+                    .map(|overview| (resource.amount(&state.production), overview))
+            })
+            .min_by_key(|(production, _)| *production);
+        if let Some((_, overview)) = scarcest
+            && overview
+                .upgrade_costs
+                .as_ref()
+                .is_some_and(|costs| state.can_afford(costs))
+        {
+            actions.extend(overview.building.as_ref().map(|building| Action::Upgrade {
+                building_id: building.id,
+            }));
+        }
+        actions
+    }
+}
+
+/// The strategies shipped with the crate, by the name used on the command
+/// line and in config files.
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Builtin {
+    #[default]
+    Collector,
+    GreedyCheapest,
+    Balanced,
+}
+
+impl Builtin {
+    pub const NAMES: [&str; 3] = ["collector", "greedy-cheapest", "balanced"];
+
+    #[must_use]
+    pub fn strategy(self) -> Box<dyn Strategy> {
+        match self {
+            Self::Collector => Box::new(Collector),
+            Self::GreedyCheapest => Box::new(GreedyCheapest),
+            Self::Balanced => Box::new(Balanced),
+        }
+    }
+}
+
+impl FromStr for Builtin {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "collector" => Ok(Self::Collector),
+            "greedy-cheapest" => Ok(Self::GreedyCheapest),
+            "balanced" => Ok(Self::Balanced),
+            _ => Err(format!(
+                "Unknown strategy '{s}', expected one of: {}",
+                Self::NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pb::common::v2::Building;
+
+    fn state(resources: i64, buildings: &[(&str, i32, Option<i64>)]) -> FortressState {
+        let building =
+            |(id, &(name, level, cost)): (usize, &(&str, i32, Option<i64>))| BuildingOverview {
+                building: Some(Building {
+                    id: i32::try_from(id).unwrap_or_default() + 1,
+                    name: name.to_owned(),
+                    level,
+                    ..Building::default()
+                }),
+                upgrade_costs: cost.map(|cost| Costs {
+                    gold: cost,
+                    food: cost,
+                    wood: cost,
+                    energy: cost,
+                }),
+            };
+        let production = |name: &str| {
+            buildings
+                .iter()
+                .find(|(n, ..)| *n == name)
+                .map_or(1, |(_, level, _)| i64::from(*level) + 1)
+        };
+        FortressState {
+            fortress: Fortress {
+                gold: resources,
+                food: resources,
+                wood: resources,
+                energy: resources,
+                ..Fortress::default()
+            },
+            buildings: buildings.iter().enumerate().map(building).collect(),
+            production: Costs {
+                gold: production("bank"),
+                food: production("farm"),
+                wood: production("sawmill"),
+                energy: production("sanctuary"),
+            },
+        }
+    }
+
+    fn upgrades(actions: &[Action]) -> Vec<i32> {
+        actions
+            .iter()
+            .filter_map(|action| match action {
+                Action::Upgrade { building_id } => Some(*building_id),
+                Action::Collect(_) => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn greedy_cheapest_upgrades_the_cheapest_affordable_building() {
+        let state = state(
+            50,
+            &[
+                ("bank", 3, Some(40)),
+                ("farm", 1, Some(20)),
+                ("sawmill", 20, None),
+                ("castle", 5, Some(60)),
+            ],
+        );
+
+        let actions = GreedyCheapest.decide(&state);
+
+        assert!(actions.starts_with(&collect_all()));
+        assert_eq!(upgrades(&actions), [2]);
+        assert!(
+            upgrades(&GreedyCheapest.decide(&self::state(10, &[("farm", 1, Some(20))]))).is_empty()
+        );
+    }
+
+    #[test]
+    fn balanced_saves_up_for_the_least_produced_resource() {
+        let buildings = [
+            ("bank", 3, Some(40)),
+            ("farm", 1, Some(20)),
+            ("sawmill", 2, Some(30)),
+        ];
+        assert_eq!(upgrades(&Balanced.decide(&state(100, &buildings))), [2]);
+
+        // The bank is affordable, but the farm comes first.
+        let buildings = [("bank", 3, Some(40)), ("farm", 1, Some(60))];
+        assert!(upgrades(&Balanced.decide(&state(50, &buildings))).is_empty());
+
+        // A building at the max level is out of the race.
+        let buildings = [("bank", 3, Some(40)), ("farm", 20, None)];
+        assert_eq!(upgrades(&Balanced.decide(&state(100, &buildings))), [1]);
+    }
+
+    #[test]
+    fn builtin_names_parse() {
+        for name in Builtin::NAMES {
+            assert!(name.parse::<Builtin>().is_ok(), "{name}");
+        }
+        assert!("lazy".parse::<Builtin>().is_err());
+    }
+}
@@ -4,8 +4,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         .type_attribute(".", "#[derive(serde::Serialize, serde::Deserialize)]")
         .compile_protos(
             &[
-                "../proto/common/v1/common.proto",
-                "../proto/game/v1/game.proto",
+                "../proto/common/v2/common.proto",
+                "../proto/game/v2/game.proto",
             ],
             &["../proto"],
         )?;
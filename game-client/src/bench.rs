@@ -0,0 +1,548 @@
+//! `game-client bench`, a load generator sending a weighted mix of calls and
+//! reporting their latencies.
+
+use crate::{
+    BuildingClient, FortressClient,
+    output::{self, Format},
+    pb::game::v2::{
+        CollectFortressEnergyRequest, CollectFortressFoodRequest, CollectFortressGoldRequest,
+        CollectFortressWoodRequest, CreateFortressRequest, DeleteFortressRequest,
+        GetFortressRequest, ImproveBuildingRequest,
+    },
+};
+use clap::ValueEnum;
+use rusty::{rng::Rng, rules::FORTRESSES_PER_PLAYER};
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    str::FromStr,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use tokio::{sync::Semaphore, task::JoinSet, time::Interval};
+use tonic::Code;
+
+/// Requests sent when neither a count nor a duration is given.
+const DEFAULT_REQUESTS: usize = 1000;
+/// Upper bounds of the latency histogram buckets, in milliseconds.
+const BUCKETS_MS: [u64; 12] = [1, 2, 5, 10, 20, 50, 100, 200, 500, 1000, 2000, 5000];
+
+#[derive(ValueEnum, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Op {
+    /// `GetFortress`
+    Read,
+    /// `CollectFortress*`, on a random resource
+    Collect,
+    /// `ImproveBuilding`
+    Upgrade,
+    /// `CreateFortress`, then `DeleteFortress` of the new fortress
+    Create,
+}
+
+/// An operation and its share of the calls, written `op=weight`.
+#[derive(Clone, Copy)]
+pub struct Weighted {
+    op: Op,
+    weight: u64,
+}
+
+impl FromStr for Weighted {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (op, weight) = s.split_once('=').unwrap_or((s, "1"));
+        Ok(Self {
+            op: Op::from_str(op, true)?,
+            weight: weight
+                .parse()
+                .map_err(|e| format!("invalid weight '{weight}': {e}"))?,
+        })
+    }
+}
+
+#[derive(clap::Args, Clone)]
+pub struct Options {
+    /// Requests to send, the default when no duration is given
+    requests: Option<usize>,
+    /// Send requests for this many seconds instead of a fixed count
+    #[arg(long, value_name = "SECS", conflicts_with = "requests")]
+    duration: Option<u64>,
+    /// Requests in flight at the same time
+    #[arg(short, long, default_value_t = 1)]
+    concurrency: usize,
+    /// Target of requests per second across all workers, as fast as possible
+    /// when unset
+    #[arg(long)]
+    rate: Option<f64>,
+    /// Operations and their weights
+    #[arg(
+        long,
+        value_delimiter = ',',
+        default_value = "read=4,collect=4,upgrade=1,create=1"
+    )]
+    mix: Vec<Weighted>,
+    /// Fortresses created before the run for reads, collects and upgrades
+    #[arg(long, default_value_t = 1)]
+    fortresses: usize,
+    /// Seed of the operation picks, from the clock when unset
+    #[arg(long)]
+    seed: Option<u64>,
+}
+
+struct Sample {
+    op: Op,
+    latency: Duration,
+    code: Option<Code>,
+}
+
+/// What the workers share.
+struct Plan {
+    mix: Vec<Weighted>,
+    total_weight: u64,
+    fortress_ids: Vec<i32>,
+    building_ids: Vec<i32>,
+    /// Fortresses `Create` calls may own at once, players being limited to
+    /// `FORTRESSES_PER_PLAYER`.
+    create_slots: Semaphore,
+    sent: AtomicUsize,
+    requests: Option<usize>,
+    deadline: Option<Instant>,
+    ticker: Option<tokio::sync::Mutex<Interval>>,
+}
+
+fn pick<'a, T>(rng: &mut Rng, items: &'a [T]) -> Option<&'a T> {
+    let len = u64::try_from(items.len()).ok().filter(|&len| len > 0)?;
+    items.get(usize::try_from(rng.next_u64() % len).ok()?)
+}
+
+impl Plan {
+    /// Whether another request should be sent.
+    fn next(&self) -> bool {
+        if let Some(deadline) = self.deadline {
+            return Instant::now() < deadline;
+        }
+        self.requests
+            .is_none_or(|requests| self.sent.fetch_add(1, Ordering::Relaxed) < requests)
+    }
+
+    fn pick_op(&self, rng: &mut Rng) -> Op {
+        let mut roll = rng.next_u64() % self.total_weight.max(1);
+        for weighted in &self.mix {
+            if roll < weighted.weight {
+                return weighted.op;
+            }
+            roll -= weighted.weight;
+        }
+        Op::Read
+    }
+
+    async fn send(
+        &self,
+        op: Op,
+        rng: &mut Rng,
+        fortress_client: &mut FortressClient,
+        building_client: &mut BuildingClient,
+    ) -> Result<(), Code> {
+        let fortress_id = pick(rng, &self.fortress_ids).copied().unwrap_or_default();
+        let result = match op {
+            Op::Read => fortress_client
+                .get_fortress(GetFortressRequest { id: fortress_id })
+                .await
+                .map(drop),
+            Op::Collect => {
+                let id = fortress_id;
+                match rng.next_u64() % 4 {
+                    0 => fortress_client
+                        .collect_fortress_gold(CollectFortressGoldRequest {
+                            id,
+                            expected_version: None,
+                        })
+                        .await
+                        .map(drop),
+                    1 => fortress_client
+                        .collect_fortress_food(CollectFortressFoodRequest {
+                            id,
+                            expected_version: None,
+                        })
+                        .await
+                        .map(drop),
+                    2 => fortress_client
+                        .collect_fortress_wood(CollectFortressWoodRequest {
+                            id,
+                            expected_version: None,
+                        })
+                        .await
+                        .map(drop),
+                    _ => fortress_client
+                        .collect_fortress_energy(CollectFortressEnergyRequest {
+                            id,
+                            expected_version: None,
+                        })
+                        .await
+                        .map(drop),
+                }
+            }
+            Op::Upgrade => building_client
+                .improve_building(ImproveBuildingRequest {
+                    id: pick(rng, &self.building_ids).copied().unwrap_or_default(),
+                    expected_version: None,
+                })
+                .await
+                .map(drop),
+            Op::Create => {
+                let _slot = self
+                    .create_slots
+                    .acquire()
+                    .await
+                    .map_err(|_| Code::Cancelled)?;
+                match fortress_client
+                    .create_fortress(CreateFortressRequest {})
+                    .await
+                {
+                    Ok(response) => match response.into_inner().fortress {
+                        Some(fortress) => fortress_client
+                            .delete_fortress(DeleteFortressRequest { id: fortress.id })
+                            .await
+                            .map(drop),
+                        None => Ok(()),
+                    },
+                    Err(status) => Err(status),
+                }
+            }
+        };
+        result.map_err(|status| status.code())
+    }
+}
+
+async fn worker(
+    plan: &Plan,
+    mut rng: Rng,
+    mut fortress_client: FortressClient,
+    mut building_client: BuildingClient,
+) -> Vec<Sample> {
+    let mut samples = Vec::new();
+    while plan.next() {
+        if let Some(ticker) = &plan.ticker {
+            ticker.lock().await.tick().await;
+        }
+        let op = plan.pick_op(&mut rng);
+        let start = Instant::now();
+        let result = plan
+            .send(op, &mut rng, &mut fortress_client, &mut building_client)
+            .await;
+        samples.push(Sample {
+            op,
+            latency: start.elapsed(),
+            code: result.err(),
+        });
+    }
+    samples
+}
+
+#[allow(clippy::cast_precision_loss)]
+const fn as_f64(n: usize) -> f64 {
+    n as f64
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+/// The latency under which `permille` thousandths of the sorted samples fall.
+fn percentile(sorted: &[Duration], permille: usize) -> Duration {
+    let rank = sorted.len().saturating_mul(permille).div_ceil(1000);
+    sorted
+        .get(rank.saturating_sub(1))
+        .copied()
+        .unwrap_or_default()
+}
+
+/// In milliseconds.
+#[derive(Serialize)]
+struct Latency {
+    min: f64,
+    mean: f64,
+    p50: f64,
+    p90: f64,
+    p99: f64,
+    max: f64,
+}
+
+impl Latency {
+    fn new(sorted: &[Duration]) -> Self {
+        let total: Duration = sorted.iter().sum();
+        Self {
+            min: millis(sorted.first().copied().unwrap_or_default()),
+            mean: millis(total) / as_f64(sorted.len().max(1)),
+            p50: millis(percentile(sorted, 500)),
+            p90: millis(percentile(sorted, 900)),
+            p99: millis(percentile(sorted, 990)),
+            max: millis(sorted.last().copied().unwrap_or_default()),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct OpReport {
+    requests: usize,
+    errors: usize,
+    latency: Latency,
+}
+
+#[derive(Serialize)]
+struct Bucket {
+    /// `None` for the last bucket, without upper bound.
+    le_ms: Option<u64>,
+    count: usize,
+}
+
+#[derive(Serialize)]
+struct Report {
+    requests: usize,
+    errors: usize,
+    elapsed_secs: f64,
+    /// Requests per second.
+    throughput: f64,
+    latency: Latency,
+    operations: BTreeMap<Op, OpReport>,
+    errors_by_code: BTreeMap<String, usize>,
+    histogram: Vec<Bucket>,
+}
+
+fn histogram(sorted: &[Duration]) -> Vec<Bucket> {
+    let mut below = 0;
+    let mut buckets: Vec<Bucket> = BUCKETS_MS
+        .iter()
+        .map(|&le_ms| {
+            let upto = sorted.partition_point(|&latency| latency <= Duration::from_millis(le_ms));
+            let count = upto - below;
+            below = upto;
+            Bucket {
+                le_ms: Some(le_ms),
+                count,
+            }
+        })
+        .collect();
+    buckets.push(Bucket {
+        le_ms: None,
+        count: sorted.len() - below,
+    });
+    buckets
+}
+
+fn report(samples: &[Sample], elapsed: Duration) -> Report {
+    let sorted_latencies = |op: Option<Op>| {
+        let mut latencies: Vec<_> = samples
+            .iter()
+            .filter(|sample| op.is_none_or(|op| sample.op == op))
+            .map(|sample| sample.latency)
+            .collect();
+        latencies.sort_unstable();
+        latencies
+    };
+    let all = sorted_latencies(None);
+
+    let mut operations = BTreeMap::new();
+    for op in Op::value_variants() {
+        let latencies = sorted_latencies(Some(*op));
+        if latencies.is_empty() {
+            continue;
+        }
+        let errors = samples
+            .iter()
+            .filter(|sample| sample.op == *op && sample.code.is_some())
+            .count();
+        operations.insert(
+            *op,
+            OpReport {
+                requests: latencies.len(),
+                errors,
+                latency: Latency::new(&latencies),
+            },
+        );
+    }
+    let mut errors_by_code = BTreeMap::new();
+    for code in samples.iter().filter_map(|sample| sample.code) {
+        *errors_by_code.entry(format!("{code:?}")).or_default() += 1;
+    }
+
+    Report {
+        requests: samples.len(),
+        errors: errors_by_code.values().sum(),
+        elapsed_secs: elapsed.as_secs_f64(),
+        throughput: as_f64(samples.len()) / elapsed.as_secs_f64().max(f64::EPSILON),
+        latency: Latency::new(&all),
+        operations,
+        errors_by_code,
+        histogram: histogram(&all),
+    }
+}
+
+/// The report as tables: latencies by operation, errors by code, then the
+/// histogram.
+fn print_tables(report: &Report) -> Result<(), Box<dyn std::error::Error>> {
+    let row = |op: &str, requests, errors, latency: &Latency| {
+        serde_json::json!({
+            "op": op,
+            "requests": requests,
+            "errors": errors,
+            "p50_ms": format!("{:.2}", latency.p50),
+            "p90_ms": format!("{:.2}", latency.p90),
+            "p99_ms": format!("{:.2}", latency.p99),
+            "max_ms": format!("{:.2}", latency.max),
+        })
+    };
+    let mut rows: Vec<_> = report
+        .operations
+        .iter()
+        .map(|(op, r)| {
+            let name = op
+                .to_possible_value()
+                .map(|value| value.get_name().to_owned())
+                .unwrap_or_default();
+            row(&name, r.requests, r.errors, &r.latency)
+        })
+        .collect();
+    rows.push(row("all", report.requests, report.errors, &report.latency));
+    output::print(Format::Table, &rows)?;
+    println!(
+        "\n{:.1} requests per second over {:.1}s\n",
+        report.throughput, report.elapsed_secs
+    );
+    if !report.errors_by_code.is_empty() {
+        let errors: Vec<_> = report
+            .errors_by_code
+            .iter()
+            .map(|(code, count)| serde_json::json!({"code": code, "count": count}))
+            .collect();
+        output::print(Format::Table, &errors)?;
+        println!();
+    }
+    output::print(Format::Table, &report.histogram)
+}
+
+pub async fn run(
+    fortress_client: &mut FortressClient,
+    building_client: &BuildingClient,
+    options: Options,
+    format: Format,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if options.concurrency == 0 {
+        return Err("The concurrency must be at least 1".into());
+    }
+    let ticker = match options.rate {
+        Some(rate) if rate > 0.0 => Some(tokio::sync::Mutex::new(tokio::time::interval(
+            Duration::from_secs_f64(1.0 / rate),
+        ))),
+        Some(_) => return Err("The rate must be positive".into()),
+        None => None,
+    };
+    let creates = options
+        .mix
+        .iter()
+        .any(|weighted| weighted.op == Op::Create && weighted.weight > 0);
+    let limit = usize::try_from(FORTRESSES_PER_PLAYER).unwrap_or_default();
+    let max_fortresses = limit - usize::from(creates);
+    if options.fortresses > max_fortresses {
+        return Err(format!(
+            "--fortresses must be at most {max_fortresses}: players own up to \
+             {FORTRESSES_PER_PLAYER} fortresses{}",
+            if creates {
+                ", one being left to create"
+            } else {
+                ""
+            }
+        )
+        .into());
+    }
+
+    let mut fortress_ids = Vec::new();
+    let mut building_ids = Vec::new();
+    for _ in 0..options.fortresses {
+        let response = fortress_client
+            .create_fortress(CreateFortressRequest {})
+            .await?
+            .into_inner();
+        fortress_ids.extend(response.fortress.map(|f| f.id));
+        building_ids.extend(response.buildings.iter().map(|b| b.id));
+    }
+
+    let plan = Plan {
+        total_weight: options.mix.iter().map(|w| w.weight).sum(),
+        mix: options.mix,
+        fortress_ids,
+        building_ids,
+        create_slots: Semaphore::new(limit - options.fortresses),
+        sent: AtomicUsize::new(0),
+        requests: options
+            .duration
+            .is_none()
+            .then(|| options.requests.unwrap_or(DEFAULT_REQUESTS)),
+        deadline: options
+            .duration
+            .map(|secs| Instant::now() + Duration::from_secs(secs)),
+        ticker,
+    };
+    let seed = options.seed.unwrap_or_else(|| {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs())
+    });
+
+    let plan = Arc::new(plan);
+    let start = Instant::now();
+    let mut workers = JoinSet::new();
+    for i in 0..options.concurrency {
+        let plan = Arc::clone(&plan);
+        let rng = Rng::new(seed.wrapping_add(u64::try_from(i).unwrap_or_default()));
+        let fortress_client = fortress_client.clone();
+        let building_client = building_client.clone();
+        workers.spawn(async move { worker(&plan, rng, fortress_client, building_client).await });
+    }
+    let mut samples = Vec::new();
+    while let Some(worker_samples) = workers.join_next().await {
+        samples.extend(worker_samples?);
+    }
+    let report = report(&samples, start.elapsed());
+
+    let mut cleanup_errors = 0;
+    for &id in &plan.fortress_ids {
+        if fortress_client
+            .delete_fortress(DeleteFortressRequest { id })
+            .await
+            .is_err()
+        {
+            cleanup_errors += 1;
+        }
+    }
+    if cleanup_errors > 0 {
+        eprintln!("{cleanup_errors} fortresses created by the bench could not be deleted");
+    }
+
+    match format {
+        Format::Table => print_tables(&report),
+        format => output::print(format, &report),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percentiles_and_histogram_cover_all_samples() {
+        let sorted: Vec<_> = (1..=100).map(Duration::from_millis).collect();
+        assert_eq!(percentile(&sorted, 500), Duration::from_millis(50));
+        assert_eq!(percentile(&sorted, 990), Duration::from_millis(99));
+        assert_eq!(percentile(&[], 500), Duration::ZERO);
+
+        let buckets = histogram(&sorted);
+        assert_eq!(buckets.iter().map(|b| b.count).sum::<usize>(), 100);
+        assert_eq!(buckets.first().map(|b| b.count), Some(1));
+        assert_eq!(buckets.last().map(|b| b.count), Some(0));
+    }
+}
@@ -0,0 +1,182 @@
+//! Server profiles of `game-client`, stored in `config.toml` under the XDG
+//! config directory.
+
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, env, fs, io, path::PathBuf};
+
+/// Profile used when none is given nor set as current.
+pub const DEFAULT_PROFILE: &str = "default";
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Profile {
+    pub url: String,
+    pub auth_url: String,
+    #[serde(default = "default_client_id")]
+    pub client_id: String,
+    /// PEM file of a CA trusted on top of the native roots.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ca_cert: Option<PathBuf>,
+    /// Name checked against the server certificate instead of the URL host.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls_domain: Option<String>,
+}
+
+fn default_client_id() -> String {
+    crate::CLIENT_ID.to_owned()
+}
+
+/// The public server.
+impl Default for Profile {
+    fn default() -> Self {
+        Self {
+            url: "https://rusty.anclarma.fr".to_owned(),
+            auth_url: "https://auth.rusty.anclarma.fr".to_owned(),
+            client_id: default_client_id(),
+            ca_cert: None,
+            tls_domain: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct Config {
+    /// Profile used when `--profile` is not given.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub current: Option<String>,
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+/// `$XDG_CONFIG_HOME/rusty-kingdom`, or `~/.config/rusty-kingdom`.
+pub fn config_dir() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .map(|dir| dir.join("rusty-kingdom"))
+        .ok_or_else(|| "Neither XDG_CONFIG_HOME nor HOME is set".into())
+}
+
+/// Creates `dir` and its parents, readable by the current user only.
+pub fn create_private_dir(dir: &std::path::Path) -> io::Result<()> {
+    let mut builder = fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+    builder.create(dir)
+}
+
+/// Profile names end up in file names, so they are kept to a safe charset.
+pub fn check_profile_name(name: &str) -> Result<(), Box<dyn std::error::Error>> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(format!(
+            "Invalid profile name '{name}', use letters, digits, '-' and '_' only"
+        )
+        .into());
+    }
+    Ok(())
+}
+
+impl Config {
+    fn path() -> Result<PathBuf, Box<dyn std::error::Error>> {
+        Ok(config_dir()?.join("config.toml"))
+    }
+
+    /// Reads the config file, an empty config when there is none yet.
+    pub fn load() -> Result<Self, Box<dyn std::error::Error>> {
+        let path = Self::path()?;
+        match fs::read_to_string(&path) {
+            Ok(content) => toml::from_str(&content)
+                .map_err(|e| format!("Invalid config file {}: {e}", path.display()).into()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn save(&self) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        let path = Self::path()?;
+        if let Some(dir) = path.parent() {
+            create_private_dir(dir)?;
+        }
+        fs::write(&path, toml::to_string_pretty(self)?)?;
+        Ok(path)
+    }
+
+    pub fn current_name(&self) -> &str {
+        self.current.as_deref().unwrap_or(DEFAULT_PROFILE)
+    }
+
+    /// The profile named `name`, the public server for `default` unless it
+    /// was redefined.
+    pub fn profile(&self, name: &str) -> Result<Profile, Box<dyn std::error::Error>> {
+        self.profiles
+            .get(name)
+            .cloned()
+            .or_else(|| (name == DEFAULT_PROFILE).then(Profile::default))
+            .ok_or_else(|| {
+                format!("Unknown profile '{name}', add it with 'game-client profile add'").into()
+            })
+    }
+
+    /// The configured profiles, with the public server as `default` unless it
+    /// was redefined.
+    pub fn profiles(&self) -> BTreeMap<String, Profile> {
+        let mut profiles = self.profiles.clone();
+        profiles.entry(DEFAULT_PROFILE.to_owned()).or_default();
+        profiles
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn local() -> Profile {
+        Profile {
+            url: "http://localhost:3000".to_owned(),
+            auth_url: "http://localhost:8080".to_owned(),
+            ..Profile::default()
+        }
+    }
+
+    #[test]
+    fn profile_names_are_safe_file_names() {
+        assert!(check_profile_name("local-dev_2").is_ok());
+        assert!(check_profile_name("").is_err());
+        assert!(check_profile_name("../etc").is_err());
+        assert!(check_profile_name("a b").is_err());
+    }
+
+    #[test]
+    fn default_profile_falls_back_to_the_public_server() {
+        let mut config = Config::default();
+        assert_eq!(config.current_name(), DEFAULT_PROFILE);
+        assert_eq!(
+            config
+                .profile(DEFAULT_PROFILE)
+                .ok()
+                .map(|profile| profile.url),
+            Some(Profile::default().url)
+        );
+        assert!(config.profile("local").is_err());
+        assert_eq!(
+            config.profiles().keys().collect::<Vec<_>>(),
+            [DEFAULT_PROFILE]
+        );
+
+        config.profiles.insert("local".to_owned(), local());
+        config.profiles.insert(DEFAULT_PROFILE.to_owned(), local());
+        assert_eq!(
+            config
+                .profile(DEFAULT_PROFILE)
+                .ok()
+                .map(|profile| profile.url),
+            Some(local().url)
+        );
+        assert_eq!(config.profiles().len(), 2);
+    }
+}
@@ -0,0 +1,186 @@
+//! Tokens saved by `game-client login`, one file per profile under the XDG
+//! config directory, readable by the current user only.
+
+use crate::config::{Profile, check_profile_name, config_dir, create_private_dir};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    io::{self, Write},
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// An access token is refreshed when it expires within this delay, so that it
+/// does not expire in the middle of a command.
+const REFRESH_MARGIN_SECS: u64 = 60;
+
+#[derive(Serialize, Deserialize)]
+pub struct Credentials {
+    pub access_token: String,
+    #[serde(default)]
+    pub refresh_token: Option<String>,
+    /// Unix time at which the access token expires.
+    pub expires_at: u64,
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+impl Credentials {
+    /// Reads a token endpoint response, keeping `refresh_token` when the
+    /// response does not rotate it.
+    pub fn from_token_response(
+        response: &serde_json::Value,
+        refresh_token: Option<String>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let access_token = response
+            .get("access_token")
+            .and_then(|v| v.as_str())
+            .ok_or("No access_token")?
+            .to_owned();
+        let refresh_token = response
+            .get("refresh_token")
+            .and_then(|v| v.as_str())
+            .map(ToOwned::to_owned)
+            .or(refresh_token);
+        let expires_in = response
+            .get("expires_in")
+            .and_then(serde_json::Value::as_u64)
+            .unwrap_or(0);
+
+        Ok(Self {
+            access_token,
+            refresh_token,
+            expires_at: unix_now().saturating_add(expires_in),
+        })
+    }
+
+    pub fn expires_soon(&self) -> bool {
+        unix_now().saturating_add(REFRESH_MARGIN_SECS) >= self.expires_at
+    }
+}
+
+fn credentials_path(profile: &str) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    check_profile_name(profile)?;
+    Ok(config_dir()?
+        .join("credentials")
+        .join(format!("{profile}.json")))
+}
+
+pub fn load(profile: &str) -> Result<Credentials, Box<dyn std::error::Error>> {
+    let content = fs::read_to_string(credentials_path(profile)?).map_err(|_| {
+        format!("Not logged in with profile '{profile}'. Please launch 'game-client login' first.")
+    })?;
+    Ok(serde_json::from_str(&content)?)
+}
+
+/// Writes the credentials of `profile`, creating the file with `0600` and its
+/// directory with `0700` permissions.
+pub fn save(
+    profile: &str,
+    credentials: &Credentials,
+) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let path = credentials_path(profile)?;
+    if let Some(dir) = path.parent() {
+        create_private_dir(dir)?;
+    }
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options.open(&path)?;
+    // The mode only applies to new files, tighten the ones left by hand.
+    #[cfg(unix)]
+    file.set_permissions(std::os::unix::fs::PermissionsExt::from_mode(0o600))?;
+    file.write_all(serde_json::to_string_pretty(credentials)?.as_bytes())?;
+    Ok(path)
+}
+
+/// Removes the credentials of `profile`, returning whether there were any.
+pub fn remove(profile: &str) -> Result<bool, Box<dyn std::error::Error>> {
+    match fs::remove_file(credentials_path(profile)?) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Exchanges the refresh token for new credentials.
+pub async fn refresh(
+    profile: &Profile,
+    credentials: &Credentials,
+) -> Result<Credentials, Box<dyn std::error::Error>> {
+    let (auth_url, client_id) = (&profile.auth_url, &profile.client_id);
+    let refresh_token = credentials
+        .refresh_token
+        .as_deref()
+        .ok_or("The session expired. Please launch 'game-client login' again.")?;
+    let res = reqwest::Client::new()
+        .post(format!("{auth_url}/auth/v1/oidc/token"))
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .body(format!(
+            "grant_type=refresh_token&client_id={client_id}&refresh_token={refresh_token}"
+        ))
+        .send()
+        .await?;
+
+    if !res.status().is_success() {
+        return Err(format!(
+            "The session could not be refreshed ({}), please launch 'game-client login' again.",
+            res.status()
+        )
+        .into());
+    }
+    Credentials::from_token_response(&res.json().await?, Some(refresh_token.to_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn expiring_at(expires_at: u64) -> Credentials {
+        Credentials {
+            access_token: "access".to_owned(),
+            refresh_token: None,
+            expires_at,
+        }
+    }
+
+    #[test]
+    fn expires_soon_within_the_refresh_margin() {
+        let now = unix_now();
+        assert!(expiring_at(0).expires_soon());
+        assert!(expiring_at(now + REFRESH_MARGIN_SECS).expires_soon());
+        assert!(!expiring_at(now + REFRESH_MARGIN_SECS + 3600).expires_soon());
+    }
+
+    #[test]
+    fn token_response_keeps_the_refresh_token_unless_rotated() {
+        let kept = Credentials::from_token_response(
+            &json!({"access_token": "new", "expires_in": 1800}),
+            Some("old".to_owned()),
+        );
+        let Ok(kept) = kept else {
+            panic!("valid response rejected");
+        };
+        assert_eq!(kept.access_token, "new");
+        assert_eq!(kept.refresh_token.as_deref(), Some("old"));
+        assert!(!kept.expires_soon());
+
+        let rotated = Credentials::from_token_response(
+            &json!({"access_token": "new", "refresh_token": "rotated"}),
+            Some("old".to_owned()),
+        )
+        .ok()
+        .and_then(|credentials| credentials.refresh_token);
+        assert_eq!(rotated.as_deref(), Some("rotated"));
+
+        let missing = Credentials::from_token_response(&json!({"expires_in": 1800}), None);
+        assert!(missing.is_err());
+    }
+}
@@ -1,36 +1,66 @@
 #[allow(clippy::pedantic, clippy::nursery)]
 pub mod pb {
     pub mod common {
-        pub mod v1 {
-            tonic::include_proto!("common.v1");
+        pub mod v2 {
+            tonic::include_proto!("common.v2");
         }
     }
     pub mod game {
-        pub mod v1 {
-            tonic::include_proto!("game.v1");
+        pub mod v2 {
+            tonic::include_proto!("game.v2");
         }
     }
 }
 
-use clap::{CommandFactory, Parser, Subcommand};
+mod bench;
+mod config;
+mod credentials;
+mod output;
+mod tui;
+
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
 use clap_complete::{Shell, generate};
-use pb::game::v1::{
-    CollectFortressEnergyRequest, CollectFortressFoodRequest, CollectFortressGoldRequest,
-    CollectFortressWoodRequest, CreateFortressRequest, DeleteFortressRequest, GetBuildingRequest,
-    GetFortressEnergyRequest, GetFortressFoodRequest, GetFortressGoldRequest, GetFortressRequest,
-    GetFortressWoodRequest, GetImproveBuildingCostsRequest, ImproveBuildingRequest,
-    ListBuildingsByFortressRequest, ListBuildingsRequest, ListFortressesRequest,
-    building_service_client::BuildingServiceClient, fortress_service_client::FortressServiceClient,
+use config::{Config, Profile, check_profile_name};
+use credentials::Credentials;
+use output::Format;
+use pb::common::v2::{BuildingSortField, FortressSortField, SortDirection};
+use pb::game::v2::{
+    ClaimDailyRewardRequest, CollectFortressEnergyRequest, CollectFortressFoodRequest,
+    CollectFortressGoldRequest, CollectFortressWoodRequest, CreateFortressRequest,
+    DeleteFortressRequest, GetBuildingRequest, GetClockRequest, GetDailyRewardRequest,
+    GetFortressEnergyRequest, GetFortressFoodRequest, GetFortressGoldRequest,
+    GetFortressHistoryRequest, GetFortressRequest, GetFortressWoodRequest,
+    GetImproveBuildingCostsRequest, ImproveBuildingRequest, ListBuildingsByFortressRequest,
+    ListBuildingsRequest, ListCampsRequest, ListFortressesRequest, ListScoutReportsRequest,
+    RaidCampRequest, RestoreFortressRequest, ScoutFortressRequest, SetClockSkewRequest,
+    building_service_client::BuildingServiceClient, clock_service_client::ClockServiceClient,
+    fortress_service_client::FortressServiceClient,
 };
 use serde_json::json;
-use std::{fs, io, time::Duration};
+use std::{
+    fs, io,
+    path::PathBuf,
+    process::ExitCode,
+    sync::{
+        Arc, RwLock,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
 use tonic::{
-    Status,
+    Code, Response, Status,
     metadata::MetadataValue,
     service::{Interceptor, interceptor::InterceptedService},
-    transport::{Channel, ClientTlsConfig},
+    transport::{Certificate, Channel, ClientTlsConfig},
 };
 
+const CLIENT_ID: &str = "rusty-client";
+
+type FortressClient = FortressServiceClient<InterceptedService<Channel, AuthInterceptor>>;
+type BuildingClient = BuildingServiceClient<InterceptedService<Channel, AuthInterceptor>>;
+type ClockClient = ClockServiceClient<InterceptedService<Channel, AuthInterceptor>>;
+
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 struct Args {
@@ -41,24 +71,41 @@ struct Args {
         short,
         long,
         value_name = "SERVER_URL",
-        default_value = "https://rusty.anclarma.fr"
+        help = "Override the server URL of the profile"
     )]
-    url: String,
+    url: Option<String>,
 
     #[arg(
         long,
         value_name = "AUTH_URL",
-        default_value = "https://auth.rusty.anclarma.fr"
+        help = "Override the auth URL of the profile"
     )]
-    auth_url: String,
+    auth_url: Option<String>,
 
     #[arg(long, help = "Disable authentication (Guest Mode)")]
     no_auth: bool,
+
+    #[arg(short, long, value_enum, default_value_t, global = true)]
+    output: Format,
+
+    #[arg(
+        short,
+        long,
+        env = "RUSTY_PROFILE",
+        help = "Profile to use instead of the current one"
+    )]
+    profile: Option<String>,
 }
 
 #[derive(Subcommand, Clone)]
 enum Commands {
     Login,
+    /// Forget the credentials of the profile
+    Logout,
+    /// Show the profile and the claims of its access token
+    Whoami,
+    /// Dashboard of your fortresses, driven from the keyboard
+    Tui,
     Fortress {
         #[command(subcommand)]
         cmd: FortressCommands,
@@ -67,20 +114,121 @@ enum Commands {
         #[command(subcommand)]
         cmd: BuildingCommands,
     },
+    /// Show the game clock, or move it on debug servers (admins only)
+    Clock {
+        #[arg(
+            long,
+            allow_hyphen_values = true,
+            help = "Seconds to put the game ahead of the real time, 0 to reset"
+        )]
+        skew: Option<i64>,
+    },
+    /// Load test the server with a mix of calls and report their latencies
     Bench {
-        size: usize,
+        #[command(flatten)]
+        options: bench::Options,
     },
     Completions {
         shell: Shell,
     },
+    /// Manage the server profiles
+    Profile {
+        #[command(subcommand)]
+        cmd: ProfileCommands,
+    },
+}
+
+#[derive(Subcommand, Clone)]
+enum ProfileCommands {
+    /// Add a profile, or replace the one with the same name
+    Add {
+        name: String,
+        #[arg(long, value_name = "SERVER_URL")]
+        url: String,
+        #[arg(long, value_name = "AUTH_URL")]
+        auth_url: String,
+        #[arg(long, default_value = CLIENT_ID)]
+        client_id: String,
+        #[arg(long, help = "PEM file of a CA to trust on top of the native roots")]
+        ca_cert: Option<PathBuf>,
+        #[arg(long, help = "Name checked against the server certificate")]
+        tls_domain: Option<String>,
+    },
+    List,
+    /// Make a profile the current one
+    Use {
+        name: String,
+    },
+}
+
+#[derive(ValueEnum, Clone, Copy, Default)]
+enum BuildingSort {
+    #[default]
+    Id,
+    Level,
+}
+
+impl From<BuildingSort> for BuildingSortField {
+    fn from(value: BuildingSort) -> Self {
+        match value {
+            BuildingSort::Id => Self::Id,
+            BuildingSort::Level => Self::Level,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Default)]
+enum FortressSort {
+    #[default]
+    Id,
+    Gold,
+}
+
+impl From<FortressSort> for FortressSortField {
+    fn from(value: FortressSort) -> Self {
+        match value {
+            FortressSort::Id => Self::Id,
+            FortressSort::Gold => Self::Gold,
+        }
+    }
+}
+
+const fn sort_direction(descending: bool) -> SortDirection {
+    if descending {
+        SortDirection::Descending
+    } else {
+        SortDirection::Ascending
+    }
 }
 
 #[derive(Subcommand, Clone)]
 enum BuildingCommands {
-    GetAll,
-    Get { building_id: i32 },
-    Improve { building_id: i32 },
-    GetImproveCosts { building_id: i32 },
+    GetAll {
+        #[arg(long, help = "Only buildings with this name")]
+        name: Option<String>,
+        #[arg(long, help = "Only buildings at this level or above")]
+        min_level: Option<i32>,
+        #[arg(long, value_enum, default_value_t)]
+        sort: BuildingSort,
+        #[arg(long, help = "Sort in descending order")]
+        desc: bool,
+        #[arg(
+            long,
+            help = "Number of buildings per request (default 50, at most 200)"
+        )]
+        page_size: Option<i32>,
+    },
+    Get {
+        building_id: i32,
+    },
+    Improve {
+        building_id: i32,
+        #[arg(long, help = "Fail if the building changed since this version")]
+        expected_version: Option<i64>,
+    },
+    GetImproveCosts {
+        building_id: i32,
+    },
 }
 
 #[derive(Subcommand, Clone)]
@@ -88,6 +236,20 @@ enum FortressCommands {
     GetAll {
         #[arg(long, help = "Only show my fortresses")]
         mine: bool,
+        #[arg(long, value_enum, default_value_t)]
+        sort: FortressSort,
+        #[arg(long, help = "Sort in descending order")]
+        desc: bool,
+        #[arg(
+            long,
+            help = "Number of fortresses per request (default 50, at most 200)"
+        )]
+        page_size: Option<i32>,
+        #[arg(
+            long,
+            help = "Print the public summaries, including fortresses of other players"
+        )]
+        summaries: bool,
     },
     New,
     Get {
@@ -96,46 +258,115 @@ enum FortressCommands {
     Delete {
         fortress_id: i32,
     },
+    Restore {
+        fortress_id: i32,
+    },
+    History {
+        fortress_id: i32,
+        #[arg(long, help = "Number of events per page (default 50, at most 200)")]
+        page_size: Option<i32>,
+        #[arg(long, help = "Token printed with the previous page")]
+        page_token: Option<String>,
+    },
+    /// Spend energy of a fortress to try and see everything of another one
+    Scout {
+        spy_from: i32,
+        target: i32,
+    },
+    ScoutReports {
+        fortress_id: i32,
+        #[arg(long, help = "Number of reports per page (default 50, at most 200)")]
+        page_size: Option<i32>,
+        #[arg(long, help = "Token printed with the previous page")]
+        page_token: Option<String>,
+    },
+    /// List my barbarian camps
+    Camps,
+    /// Spend energy of a fortress to attack one of my barbarian camps
+    RaidCamp {
+        camp_id: i32,
+        fortress_id: i32,
+    },
+    /// Show my daily reward streak and the bundle of the next claim
+    DailyReward,
+    /// Credit today's daily reward to one of my fortresses
+    ClaimDailyReward {
+        fortress_id: i32,
+    },
     GetGold {
         fortress_id: i32,
     },
     CollectGold {
         fortress_id: i32,
+        #[arg(long, help = "Fail if the fortress changed since this version")]
+        expected_version: Option<i64>,
     },
     GetFood {
         fortress_id: i32,
     },
     CollectFood {
         fortress_id: i32,
+        #[arg(long, help = "Fail if the fortress changed since this version")]
+        expected_version: Option<i64>,
     },
     GetWood {
         fortress_id: i32,
     },
     CollectWood {
         fortress_id: i32,
+        #[arg(long, help = "Fail if the fortress changed since this version")]
+        expected_version: Option<i64>,
     },
     GetEnergy {
         fortress_id: i32,
     },
     CollectEnergy {
         fortress_id: i32,
+        #[arg(long, help = "Fail if the fortress changed since this version")]
+        expected_version: Option<i64>,
     },
     GetAllBuildings {
         fortress_id: i32,
     },
 }
 
+/// Sends the access token, shared between the clients so that a refresh
+/// reaches all of them. Also counts the calls they sent.
 #[derive(Clone)]
 struct AuthInterceptor {
-    token: String,
+    token: Arc<RwLock<String>>,
+    sent: Arc<AtomicUsize>,
+}
+
+impl AuthInterceptor {
+    fn new(token: String) -> Self {
+        Self {
+            token: Arc::new(RwLock::new(token)),
+            sent: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    fn sent(&self) -> usize {
+        self.sent.load(Ordering::Relaxed)
+    }
+
+    fn set_token(&self, token: String) {
+        if let Ok(mut current) = self.token.write() {
+            *current = token;
+        }
+    }
 }
 
 impl Interceptor for AuthInterceptor {
     fn call(&mut self, mut request: tonic::Request<()>) -> Result<tonic::Request<()>, Status> {
-        if self.token.is_empty() {
+        self.sent.fetch_add(1, Ordering::Relaxed);
+        let Ok(token) = self.token.read() else {
+            return Err(Status::unauthenticated("Invalid access token"));
+        };
+        if token.is_empty() {
             return Ok(request);
         }
-        let bearer = format!("Bearer {}", self.token);
+        let bearer = format!("Bearer {token}");
         MetadataValue::try_from(&bearer).map_or_else(
             |_| Err(Status::unauthenticated("Invalid access token")),
             |meta| {
@@ -146,177 +377,399 @@ impl Interceptor for AuthInterceptor {
     }
 }
 
+/// On a version conflict, prints the fortress as it is now so the caller can
+/// retry with its current version.
+async fn refetch_fortress_on_conflict<T>(
+    fortress_client: &mut FortressClient,
+    fortress_id: i32,
+    response: Result<Response<T>, Status>,
+    format: Format,
+) -> Result<T, Box<dyn std::error::Error>> {
+    match response {
+        Ok(response) => Ok(response.into_inner()),
+        Err(status) if status.code() == Code::Aborted => {
+            let fortress = fortress_client
+                .get_fortress(GetFortressRequest { id: fortress_id })
+                .await?
+                .into_inner()
+                .fortress;
+            eprintln!("The fortress was modified concurrently, here is its latest version:");
+            output::print(format, &fortress)?;
+            Err(status.into())
+        }
+        Err(status) => Err(status.into()),
+    }
+}
+
+/// On a version conflict, prints the building as it is now so the caller can
+/// retry with its current version.
+async fn refetch_building_on_conflict<T>(
+    building_client: &mut BuildingClient,
+    building_id: i32,
+    response: Result<Response<T>, Status>,
+    format: Format,
+) -> Result<T, Box<dyn std::error::Error>> {
+    match response {
+        Ok(response) => Ok(response.into_inner()),
+        Err(status) if status.code() == Code::Aborted => {
+            let building = building_client
+                .get_building(GetBuildingRequest { id: building_id })
+                .await?
+                .into_inner()
+                .building;
+            eprintln!("The building was modified concurrently, here is its latest version:");
+            output::print(format, &building)?;
+            Err(status.into())
+        }
+        Err(status) => Err(status.into()),
+    }
+}
+
+#[allow(clippy::too_many_lines)]
 async fn handle_fortress(
-    fortress_client: &mut FortressServiceClient<InterceptedService<Channel, AuthInterceptor>>,
-    building_client: &mut BuildingServiceClient<InterceptedService<Channel, AuthInterceptor>>,
+    fortress_client: &mut FortressClient,
+    building_client: &mut BuildingClient,
     cmd: FortressCommands,
+    format: Format,
 ) -> Result<(), Box<dyn std::error::Error>> {
     match cmd {
-        FortressCommands::GetAll { mine } => {
-            let response = fortress_client
-                .list_fortresses(ListFortressesRequest { only_mine: mine })
-                .await?
-                .into_inner();
-            println!("{}", json!(response.fortresses));
+        FortressCommands::GetAll {
+            mine,
+            sort,
+            desc,
+            page_size,
+            summaries,
+        } => {
+            let mut fortresses = Vec::new();
+            let mut fortress_summaries = Vec::new();
+            let mut page_token = String::new();
+            loop {
+                let response = fortress_client
+                    .list_fortresses(ListFortressesRequest {
+                        only_mine: mine,
+                        page_size: page_size.unwrap_or_default(),
+                        page_token,
+                        sort_field: FortressSortField::from(sort).into(),
+                        sort_direction: sort_direction(desc).into(),
+                    })
+                    .await?
+                    .into_inner();
+                fortresses.extend(response.fortresses);
+                fortress_summaries.extend(response.summaries);
+                if response.next_page_token.is_empty() {
+                    break;
+                }
+                page_token = response.next_page_token;
+            }
+            if summaries {
+                output::print(format, &fortress_summaries)?;
+            } else {
+                output::print(format, &fortresses)?;
+            }
         }
         FortressCommands::New => {
             let response = fortress_client
                 .create_fortress(CreateFortressRequest {})
                 .await?
                 .into_inner();
-            println!(
-                "{}",
-                json!({"fortress": response.fortress, "buildings": response.buildings})
-            );
+            output::print(
+                format,
+                &json!({"fortress": response.fortress, "buildings": response.buildings}),
+            )?;
         }
         FortressCommands::Get { fortress_id } => {
             let response = fortress_client
                 .get_fortress(GetFortressRequest { id: fortress_id })
                 .await?
                 .into_inner();
-            let fortress = response.fortress.ok_or("fortress not found")?;
-            println!("{}", json!(fortress));
+            // Other players' fortresses only come with their summary.
+            match (response.fortress, response.summary) {
+                (Some(fortress), _) => output::print(format, &fortress)?,
+                (None, Some(summary)) => output::print(format, &summary)?,
+                (None, None) => return Err("fortress not found".into()),
+            }
         }
         FortressCommands::Delete { fortress_id } => {
             let response = fortress_client
                 .delete_fortress(DeleteFortressRequest { id: fortress_id })
                 .await?
                 .into_inner();
-            println!("{}", json!(response.success));
+            output::print(format, &response.success)?;
         }
-        FortressCommands::GetGold { fortress_id } => {
+        FortressCommands::Restore { fortress_id } => {
             let response = fortress_client
-                .get_fortress_gold(GetFortressGoldRequest { id: fortress_id })
+                .restore_fortress(RestoreFortressRequest { id: fortress_id })
                 .await?
                 .into_inner();
-            println!("{}", json!(response.gold));
+            output::print(format, &response.fortress)?;
         }
-        FortressCommands::CollectGold { fortress_id } => {
+        FortressCommands::History {
+            fortress_id,
+            page_size,
+            page_token,
+        } => {
             let response = fortress_client
-                .collect_fortress_gold(CollectFortressGoldRequest { id: fortress_id })
+                .get_fortress_history(GetFortressHistoryRequest {
+                    fortress_id,
+                    page_size: page_size.unwrap_or_default(),
+                    page_token: page_token.unwrap_or_default(),
+                })
                 .await?
                 .into_inner();
-            println!("{}", json!(response.fortress));
+            output::print(
+                format,
+                &json!({"events": response.events, "next_page_token": response.next_page_token}),
+            )?;
         }
-        FortressCommands::GetFood { fortress_id } => {
+        FortressCommands::Scout { spy_from, target } => {
             let response = fortress_client
-                .get_fortress_food(GetFortressFoodRequest { id: fortress_id })
+                .scout_fortress(ScoutFortressRequest { spy_from, target })
                 .await?
                 .into_inner();
-            println!("{}", json!(response.food));
+            output::print(
+                format,
+                &json!({
+                    "success": response.success,
+                    "detected": response.detected,
+                    "report": response.report,
+                    "fortress": response.fortress,
+                }),
+            )?;
         }
-        FortressCommands::CollectFood { fortress_id } => {
+        FortressCommands::ScoutReports {
+            fortress_id,
+            page_size,
+            page_token,
+        } => {
             let response = fortress_client
-                .collect_fortress_food(CollectFortressFoodRequest { id: fortress_id })
+                .list_scout_reports(ListScoutReportsRequest {
+                    fortress_id,
+                    page_size: page_size.unwrap_or_default(),
+                    page_token: page_token.unwrap_or_default(),
+                })
                 .await?
                 .into_inner();
-            println!("{}", json!(response.fortress));
+            output::print(
+                format,
+                &json!({"reports": response.reports, "next_page_token": response.next_page_token}),
+            )?;
         }
-        FortressCommands::GetWood { fortress_id } => {
+        FortressCommands::Camps => {
             let response = fortress_client
-                .get_fortress_wood(GetFortressWoodRequest { id: fortress_id })
+                .list_camps(ListCampsRequest {})
                 .await?
                 .into_inner();
-            println!("{}", json!(response.wood));
+            output::print(format, &response.camps)?;
         }
-        FortressCommands::CollectWood { fortress_id } => {
+        FortressCommands::RaidCamp {
+            camp_id,
+            fortress_id,
+        } => {
             let response = fortress_client
-                .collect_fortress_wood(CollectFortressWoodRequest { id: fortress_id })
+                .raid_camp(RaidCampRequest {
+                    camp_id,
+                    fortress_id,
+                })
                 .await?
                 .into_inner();
-            println!("{}", json!(response.fortress));
+            output::print(
+                format,
+                &json!({
+                    "success": response.success,
+                    "camp": response.camp,
+                    "fortress": response.fortress,
+                }),
+            )?;
         }
-        FortressCommands::GetEnergy { fortress_id } => {
+        FortressCommands::DailyReward => {
             let response = fortress_client
-                .get_fortress_energy(GetFortressEnergyRequest { id: fortress_id })
+                .get_daily_reward(GetDailyRewardRequest {})
+                .await?
+                .into_inner();
+            output::print(format, &response)?;
+        }
+        FortressCommands::ClaimDailyReward { fortress_id } => {
+            let response = fortress_client
+                .claim_daily_reward(ClaimDailyRewardRequest { fortress_id })
                 .await?
                 .into_inner();
-            println!("{}", json!(response.energy));
+            output::print(format, &response)?;
         }
-        FortressCommands::CollectEnergy { fortress_id } => {
+        FortressCommands::GetGold { fortress_id } => {
             let response = fortress_client
-                .collect_fortress_energy(CollectFortressEnergyRequest { id: fortress_id })
+                .get_fortress_gold(GetFortressGoldRequest { id: fortress_id })
                 .await?
                 .into_inner();
-            println!("{}", json!(response.fortress));
+            output::print(format, &response.gold)?;
+        }
+        FortressCommands::CollectGold {
+            fortress_id,
+            expected_version,
+        } => {
+            let response = fortress_client
+                .collect_fortress_gold(CollectFortressGoldRequest {
+                    id: fortress_id,
+                    expected_version,
+                })
+                .await;
+            let response =
+                refetch_fortress_on_conflict(fortress_client, fortress_id, response, format)
+                    .await?;
+            output::print(format, &response.fortress)?;
+        }
+        FortressCommands::GetFood { fortress_id } => {
+            let response = fortress_client
+                .get_fortress_food(GetFortressFoodRequest { id: fortress_id })
+                .await?
+                .into_inner();
+            output::print(format, &response.food)?;
+        }
+        FortressCommands::CollectFood {
+            fortress_id,
+            expected_version,
+        } => {
+            let response = fortress_client
+                .collect_fortress_food(CollectFortressFoodRequest {
+                    id: fortress_id,
+                    expected_version,
+                })
+                .await;
+            let response =
+                refetch_fortress_on_conflict(fortress_client, fortress_id, response, format)
+                    .await?;
+            output::print(format, &response.fortress)?;
+        }
+        FortressCommands::GetWood { fortress_id } => {
+            let response = fortress_client
+                .get_fortress_wood(GetFortressWoodRequest { id: fortress_id })
+                .await?
+                .into_inner();
+            output::print(format, &response.wood)?;
+        }
+        FortressCommands::CollectWood {
+            fortress_id,
+            expected_version,
+        } => {
+            let response = fortress_client
+                .collect_fortress_wood(CollectFortressWoodRequest {
+                    id: fortress_id,
+                    expected_version,
+                })
+                .await;
+            let response =
+                refetch_fortress_on_conflict(fortress_client, fortress_id, response, format)
+                    .await?;
+            output::print(format, &response.fortress)?;
+        }
+        FortressCommands::GetEnergy { fortress_id } => {
+            let response = fortress_client
+                .get_fortress_energy(GetFortressEnergyRequest { id: fortress_id })
+                .await?
+                .into_inner();
+            output::print(format, &response.energy)?;
+        }
+        FortressCommands::CollectEnergy {
+            fortress_id,
+            expected_version,
+        } => {
+            let response = fortress_client
+                .collect_fortress_energy(CollectFortressEnergyRequest {
+                    id: fortress_id,
+                    expected_version,
+                })
+                .await;
+            let response =
+                refetch_fortress_on_conflict(fortress_client, fortress_id, response, format)
+                    .await?;
+            output::print(format, &response.fortress)?;
         }
         FortressCommands::GetAllBuildings { fortress_id } => {
             let response = building_client
                 .list_buildings_by_fortress(ListBuildingsByFortressRequest { fortress_id })
                 .await?
                 .into_inner();
-            println!("{}", json!(response.buildings));
+            output::print(format, &response.buildings)?;
         }
     }
     Ok(())
 }
 
 async fn handle_building(
-    building_client: &mut BuildingServiceClient<InterceptedService<Channel, AuthInterceptor>>,
+    building_client: &mut BuildingClient,
     cmd: BuildingCommands,
+    format: Format,
 ) -> Result<(), Box<dyn std::error::Error>> {
     match cmd {
-        BuildingCommands::GetAll => {
-            let response = building_client
-                .list_buildings(ListBuildingsRequest {})
-                .await?
-                .into_inner();
-            println!("{}", json!(response.buildings));
+        BuildingCommands::GetAll {
+            name,
+            min_level,
+            sort,
+            desc,
+            page_size,
+        } => {
+            let mut buildings = Vec::new();
+            let mut page_token = String::new();
+            loop {
+                let response = building_client
+                    .list_buildings(ListBuildingsRequest {
+                        page_size: page_size.unwrap_or_default(),
+                        page_token,
+                        name: name.clone(),
+                        min_level,
+                        sort_field: BuildingSortField::from(sort).into(),
+                        sort_direction: sort_direction(desc).into(),
+                    })
+                    .await?
+                    .into_inner();
+                buildings.extend(response.buildings);
+                if response.next_page_token.is_empty() {
+                    break;
+                }
+                page_token = response.next_page_token;
+            }
+            output::print(format, &buildings)?;
         }
         BuildingCommands::Get { building_id } => {
             let response = building_client
                 .get_building(GetBuildingRequest { id: building_id })
                 .await?
                 .into_inner();
-            println!("{}", json!(response.building));
+            output::print(format, &response.building)?;
         }
-        BuildingCommands::Improve { building_id } => {
+        BuildingCommands::Improve {
+            building_id,
+            expected_version,
+        } => {
             let response = building_client
-                .improve_building(ImproveBuildingRequest { id: building_id })
-                .await?
-                .into_inner();
-            println!(
-                "{}",
-                json!({"fortress": response.fortress, "building": response.building})
-            );
+                .improve_building(ImproveBuildingRequest {
+                    id: building_id,
+                    expected_version,
+                })
+                .await;
+            let response =
+                refetch_building_on_conflict(building_client, building_id, response, format)
+                    .await?;
+            output::print(
+                format,
+                &json!({"fortress": response.fortress, "building": response.building}),
+            )?;
         }
         BuildingCommands::GetImproveCosts { building_id } => {
             let response = building_client
                 .get_improve_building_costs(GetImproveBuildingCostsRequest { id: building_id })
                 .await?
                 .into_inner();
-            println!("{}", json!(response.costs));
+            output::print(format, &response.costs)?;
         }
     }
     Ok(())
 }
 
-async fn handle_bench(
-    fortress_client: &mut FortressServiceClient<InterceptedService<Channel, AuthInterceptor>>,
-    size: usize,
+async fn handle_login(
+    auth_url: &str,
+    client_id: &str,
+    profile: &str,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let fortress = fortress_client
-        .create_fortress(CreateFortressRequest {})
-        .await?
-        .into_inner()
-        .fortress
-        .ok_or("fortress not found")?;
-    let request = CollectFortressGoldRequest { id: fortress.id };
-    for _ in 0..size {
-        fortress_client.collect_fortress_gold(request).await?;
-    }
-    let response = fortress_client
-        .get_fortress_gold(GetFortressGoldRequest { id: fortress.id })
-        .await?
-        .into_inner();
-    println!("gold: {}", response.gold);
-    fortress_client
-        .delete_fortress(DeleteFortressRequest { id: fortress.id })
-        .await?;
-    Ok(())
-}
-
-async fn handle_login(auth_url: &str, client_id: &str) -> Result<(), Box<dyn std::error::Error>> {
     let client = reqwest::Client::new();
     let device_auth_url = format!("{auth_url}/auth/v1/oidc/device");
     let token_url = format!("{auth_url}/auth/v1/oidc/token");
@@ -385,16 +838,16 @@ async fn handle_login(auth_url: &str, client_id: &str) -> Result<(), Box<dyn std
         if token_res.status().is_success() {
             let token_json: serde_json::Value = token_res.json().await?;
 
-            fs::write(
-                ".rusty_token",
-                token_json
-                    .get("access_token")
-                    .and_then(|v| v.as_str())
-                    .ok_or("No access_token")?,
+            let path = credentials::save(
+                profile,
+                &Credentials::from_token_response(&token_json, None)?,
             )?;
             fs::write(".bot_token", serde_json::to_string_pretty(&token_json)?)?;
 
-            println!("Connection successful! CLI and Bot tokens saved locally.");
+            println!(
+                "Connection successful! CLI credentials saved to {}, Bot token saved locally.",
+                path.display()
+            );
             break;
         }
         let err = token_res.json::<serde_json::Value>().await?;
@@ -407,21 +860,207 @@ async fn handle_login(auth_url: &str, client_id: &str) -> Result<(), Box<dyn std
     Ok(())
 }
 
-// TODO: Should we use `keyring` instead?
-fn get_local_token() -> Result<String, Box<dyn std::error::Error>> {
-    fs::read_to_string(".rusty_token")
-        .map_err(|_| "Not logged in. Please launch 'game-client login' first.".into())
+fn handle_logout(profile: &str) -> Result<(), Box<dyn std::error::Error>> {
+    if credentials::remove(profile)? {
+        println!("Logged out of profile '{profile}'.");
+    } else {
+        println!("Profile '{profile}' was not logged in.");
+    }
+    Ok(())
 }
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let args = Args::parse();
+/// Prints the claims of the access token as the client sees them, without
+/// checking its signature.
+fn handle_whoami(
+    profile: &str,
+    credentials: &Credentials,
+    format: Format,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let payload = credentials
+        .access_token
+        .split('.')
+        .nth(1)
+        .ok_or("The access token is not a JWT")?;
+    let claims: serde_json::Value = serde_json::from_slice(&URL_SAFE_NO_PAD.decode(payload)?)?;
+    output::print(
+        format,
+        &json!({
+            "profile": profile,
+            "expires_at": credentials.expires_at,
+            "claims": claims,
+        }),
+    )?;
+    Ok(())
+}
 
-    match args.cmd {
-        Commands::Login => {
-            handle_login(&args.auth_url, "rusty-client").await?;
+fn handle_profile(
+    config: &mut Config,
+    cmd: ProfileCommands,
+    format: Format,
+) -> Result<(), Box<dyn std::error::Error>> {
+    match cmd {
+        ProfileCommands::Add {
+            name,
+            url,
+            auth_url,
+            client_id,
+            ca_cert,
+            tls_domain,
+        } => {
+            check_profile_name(&name)?;
+            let profile = Profile {
+                url,
+                auth_url,
+                client_id,
+                ca_cert,
+                tls_domain,
+            };
+            config.profiles.insert(name.clone(), profile);
+            let path = config.save()?;
+            println!("Profile '{name}' saved to {}.", path.display());
+        }
+        ProfileCommands::List => {
+            let current = config.current_name();
+            let profiles: Vec<_> = config
+                .profiles()
+                .into_iter()
+                .map(|(name, profile)| {
+                    json!({
+                        "name": name,
+                        "current": name == current,
+                        "url": profile.url,
+                        "auth_url": profile.auth_url,
+                        "client_id": profile.client_id,
+                        "ca_cert": profile.ca_cert,
+                        "tls_domain": profile.tls_domain,
+                    })
+                })
+                .collect();
+            output::print(format, &profiles)?;
+        }
+        ProfileCommands::Use { name } => {
+            config.profile(&name)?;
+            config.current = Some(name.clone());
+            config.save()?;
+            println!("Now using profile '{name}'.");
+        }
+    }
+    Ok(())
+}
+
+/// The profile in use and its credentials, none in Guest Mode.
+struct Session {
+    profile_name: String,
+    profile: Profile,
+    credentials: Option<Credentials>,
+    interceptor: AuthInterceptor,
+}
+
+impl Session {
+    fn can_refresh(&self) -> bool {
+        self.credentials
+            .as_ref()
+            .is_some_and(|credentials| credentials.refresh_token.is_some())
+    }
+
+    /// Refreshes the credentials, saves the new ones and hands the new access
+    /// token to the clients.
+    async fn refresh(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let Some(current) = &self.credentials else {
             return Ok(());
+        };
+        let refreshed = credentials::refresh(&self.profile, current).await?;
+        credentials::save(&self.profile_name, &refreshed)?;
+        self.interceptor.set_token(refreshed.access_token.clone());
+        self.credentials = Some(refreshed);
+        Ok(())
+    }
+
+    /// Refreshes the credentials when the access token is about to expire.
+    async fn keep_alive(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.can_refresh()
+            && self
+                .credentials
+                .as_ref()
+                .is_some_and(Credentials::expires_soon)
+        {
+            self.refresh().await?;
+        }
+        Ok(())
+    }
+}
+
+async fn connect(profile: &Profile) -> Result<Channel, Box<dyn std::error::Error>> {
+    let mut endpoint = Channel::from_shared(profile.url.clone())?;
+
+    if profile.url.starts_with("https://") {
+        let mut tls = ClientTlsConfig::new().with_native_roots();
+        if let Some(ca_cert) = &profile.ca_cert {
+            tls = tls.ca_certificate(Certificate::from_pem(fs::read(ca_cert)?));
         }
+        if let Some(domain) = &profile.tls_domain {
+            tls = tls.domain_name(domain);
+        }
+        endpoint = endpoint.tls_config(tls)?;
+    }
+
+    Ok(endpoint.connect().await?)
+}
+
+async fn handle_clock(
+    client: &mut ClockClient,
+    skew: Option<i64>,
+    format: Format,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(skew_secs) = skew {
+        let response = client
+            .set_clock_skew(SetClockSkewRequest { skew_secs })
+            .await?;
+        output::print(format, &response.into_inner())?;
+    } else {
+        let response = client.get_clock(GetClockRequest {}).await?;
+        output::print(format, &response.into_inner())?;
+    }
+    Ok(())
+}
+
+async fn run(
+    fortress_client: &mut FortressClient,
+    building_client: &mut BuildingClient,
+    clock_client: &mut ClockClient,
+    cmd: Commands,
+    format: Format,
+    session: &mut Session,
+) -> Result<(), Box<dyn std::error::Error>> {
+    match cmd {
+        Commands::Fortress { cmd } => {
+            handle_fortress(fortress_client, building_client, cmd, format).await
+        }
+        Commands::Building { cmd } => handle_building(building_client, cmd, format).await,
+        Commands::Clock { skew } => handle_clock(clock_client, skew, format).await,
+        Commands::Bench { options } => {
+            bench::run(fortress_client, building_client, options, format).await
+        }
+        Commands::Tui => tui::run(fortress_client, building_client, session).await,
+        _ => Ok(()),
+    }
+}
+
+fn is_unauthenticated(result: &Result<(), Box<dyn std::error::Error>>) -> bool {
+    matches!(result, Err(e) if e.downcast_ref::<Status>().is_some_and(|status| status.code() == Code::Unauthenticated))
+}
+
+async fn run_cli(args: Args) -> Result<(), Box<dyn std::error::Error>> {
+    let format = args.output;
+    let mut config = Config::load()?;
+    let profile_name = args
+        .profile
+        .clone()
+        .unwrap_or_else(|| config.current_name().to_owned());
+
+    match args.cmd {
+        Commands::Profile { cmd } => return handle_profile(&mut config, cmd, format),
+        Commands::Logout => return handle_logout(&profile_name),
         Commands::Completions { shell } => {
             let mut cmd = Args::command();
             generate(shell, &mut cmd, "game-client", &mut io::stdout());
@@ -430,36 +1069,86 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         _ => {}
     }
 
-    let token = if args.no_auth {
-        String::new()
+    let mut profile = config.profile(&profile_name)?;
+    if let Some(url) = &args.url {
+        profile.url.clone_from(url);
+    }
+    if let Some(auth_url) = &args.auth_url {
+        profile.auth_url.clone_from(auth_url);
+    }
+    if matches!(args.cmd, Commands::Login) {
+        return handle_login(&profile.auth_url, &profile.client_id, &profile_name).await;
+    }
+
+    let credentials = if args.no_auth {
+        None
     } else {
-        get_local_token()?
+        Some(credentials::load(&profile_name)?)
     };
-    let interceptor = AuthInterceptor { token };
-    let mut endpoint = Channel::from_shared(args.url.clone())?;
-
-    if args.url.starts_with("https://") {
-        let tls = ClientTlsConfig::new().with_native_roots();
-        endpoint = endpoint.tls_config(tls)?;
+    let interceptor = AuthInterceptor::new(
+        credentials
+            .as_ref()
+            .map(|credentials| credentials.access_token.clone())
+            .unwrap_or_default(),
+    );
+    let mut session = Session {
+        profile_name,
+        profile,
+        credentials,
+        interceptor: interceptor.clone(),
+    };
+    session.keep_alive().await?;
+    if matches!(args.cmd, Commands::Whoami) {
+        let credentials = session
+            .credentials
+            .as_ref()
+            .ok_or("Guest Mode has no identity")?;
+        return handle_whoami(&session.profile_name, credentials, format);
     }
-
-    let channel = endpoint.connect().await?;
+    let channel = connect(&session.profile).await?;
 
     let mut game_building_client =
         BuildingServiceClient::with_interceptor(channel.clone(), interceptor.clone());
-    let mut game_fortress_client = FortressServiceClient::with_interceptor(channel, interceptor);
+    let mut game_fortress_client =
+        FortressServiceClient::with_interceptor(channel.clone(), interceptor.clone());
+    let mut game_clock_client = ClockServiceClient::with_interceptor(channel, interceptor.clone());
 
-    match args.cmd {
-        Commands::Fortress { cmd } => {
-            handle_fortress(&mut game_fortress_client, &mut game_building_client, cmd).await?;
-        }
-        Commands::Building { cmd } => {
-            handle_building(&mut game_building_client, cmd).await?;
-        }
-        Commands::Bench { size } => {
-            handle_bench(&mut game_fortress_client, size).await?;
+    // The token may have been revoked or have expired early, retry once with
+    // a fresh one. Only when the rejected call was the first one, so that
+    // calls that went through, by `tui`, `bench` or listing pages, aren't
+    // sent twice.
+    {
+        let result = run(
+            &mut game_fortress_client,
+            &mut game_building_client,
+            &mut game_clock_client,
+            args.cmd.clone(),
+            format,
+            &mut session,
+        )
+        .await;
+        if !(session.can_refresh() && is_unauthenticated(&result) && interceptor.sent() == 1) {
+            return result;
         }
-        _ => {}
     }
-    Ok(())
+    session.refresh().await?;
+    run(
+        &mut game_fortress_client,
+        &mut game_building_client,
+        &mut game_clock_client,
+        args.cmd,
+        format,
+        &mut session,
+    )
+    .await
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let args = Args::parse();
+    let format = args.output;
+    match run_cli(args).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => output::fail(format, e.as_ref()),
+    }
 }
@@ -0,0 +1,239 @@
+//! Rendering of responses and errors in the format picked with `--output`.
+
+use clap::ValueEnum;
+use rusty::error_details::error_info;
+use serde::Serialize;
+use serde_json::{Value, json};
+use std::process::ExitCode;
+use tonic::{Code, Status};
+
+#[derive(ValueEnum, Clone, Copy, Default)]
+pub enum Format {
+    /// Aligned columns, one row per item
+    Table,
+    #[default]
+    Json,
+    Yaml,
+    /// One JSON document per line, one line per item of a list
+    Ndjson,
+}
+
+/// Exit codes of a failed command, `2` being left to clap for usage errors.
+const EXIT_FAILURE: u8 = 1;
+const EXIT_NOT_FOUND: u8 = 3;
+const EXIT_PERMISSION_DENIED: u8 = 4;
+const EXIT_RESOURCE_EXHAUSTED: u8 = 5;
+const EXIT_TRANSPORT: u8 = 6;
+
+/// Prints a response on stdout.
+pub fn print<T: Serialize>(format: Format, value: &T) -> Result<(), Box<dyn std::error::Error>> {
+    println!("{}", render(format, &serde_json::to_value(value)?)?);
+    Ok(())
+}
+
+fn render(format: Format, value: &Value) -> Result<String, Box<dyn std::error::Error>> {
+    Ok(match format {
+        Format::Table => table(value),
+        Format::Json => value.to_string(),
+        Format::Yaml => serde_yaml::to_string(value)?.trim_end().to_owned(),
+        Format::Ndjson => match value {
+            Value::Array(items) => items
+                .iter()
+                .map(Value::to_string)
+                .collect::<Vec<_>>()
+                .join("\n"),
+            value => value.to_string(),
+        },
+    })
+}
+
+fn cell(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        value => value.to_string(),
+    }
+}
+
+/// A list of objects as columns named after their fields, an object as one
+/// field per line, nested values staying in JSON.
+fn table(value: &Value) -> String {
+    let rows: Vec<Vec<String>> = match value {
+        Value::Array(items) => {
+            let mut columns: Vec<&String> = Vec::new();
+            for key in items
+                .iter()
+                .filter_map(Value::as_object)
+                .flat_map(|o| o.keys())
+            {
+                if !columns.contains(&key) {
+                    columns.push(key);
+                }
+            }
+            if columns.is_empty() {
+                return items.iter().map(cell).collect::<Vec<_>>().join("\n");
+            }
+            let header = columns.iter().map(|c| c.to_uppercase()).collect();
+            std::iter::once(header)
+                .chain(items.iter().map(|item| {
+                    columns
+                        .iter()
+                        .map(|c| item.get(c.as_str()).map(cell).unwrap_or_default())
+                        .collect()
+                }))
+                .collect()
+        }
+        Value::Object(fields) => fields
+            .iter()
+            .map(|(key, value)| vec![key.to_uppercase(), cell(value)])
+            .collect(),
+        value => return cell(value),
+    };
+
+    let mut widths: Vec<usize> = Vec::new();
+    for row in &rows {
+        for (i, cell) in row.iter().enumerate() {
+            let width = cell.chars().count();
+            match widths.get_mut(i) {
+                Some(max) => *max = (*max).max(width),
+                None => widths.push(width),
+            }
+        }
+    }
+    rows.iter()
+        .map(|row| {
+            row.iter()
+                .zip(&widths)
+                .map(|(cell, width)| format!("{cell:<width$}"))
+                .collect::<Vec<_>>()
+                .join("  ")
+                .trim_end()
+                .to_owned()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// A failed call with the reason and metadata of its `google.rpc.ErrorInfo`
+/// detail when the server sent one.
+pub fn status_json(status: &Status) -> Value {
+    let info = error_info(status);
+    json!({
+        "code": format!("{:?}", status.code()),
+        "message": status.message(),
+        "reason": info.as_ref().map(|info| &info.reason),
+        "metadata": info.as_ref().map(|info| &info.metadata),
+    })
+}
+
+/// The error followed by its causes, transport errors saying little on their
+/// own.
+fn message(error: &(dyn std::error::Error + 'static)) -> String {
+    std::iter::successors(Some(error), |error| error.source())
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(": ")
+}
+
+fn is_transport(error: &(dyn std::error::Error + 'static)) -> bool {
+    error.is::<tonic::transport::Error>() || error.is::<reqwest::Error>()
+}
+
+const fn exit_code(code: Code) -> u8 {
+    match code {
+        Code::NotFound => EXIT_NOT_FOUND,
+        Code::PermissionDenied | Code::Unauthenticated => EXIT_PERMISSION_DENIED,
+        Code::ResourceExhausted => EXIT_RESOURCE_EXHAUSTED,
+        Code::Unavailable => EXIT_TRANSPORT,
+        _ => EXIT_FAILURE,
+    }
+}
+
+/// The rendering of an error and the exit code matching it.
+fn report(error: &(dyn std::error::Error + 'static)) -> (Value, u8) {
+    error.downcast_ref::<Status>().map_or_else(
+        || {
+            if is_transport(error) {
+                (
+                    json!({"code": "Transport", "message": message(error)}),
+                    EXIT_TRANSPORT,
+                )
+            } else {
+                (
+                    json!({"code": "Unknown", "message": message(error)}),
+                    EXIT_FAILURE,
+                )
+            }
+        },
+        |status| (status_json(status), exit_code(status.code())),
+    )
+}
+
+/// Prints an error on stderr and returns the exit code matching it.
+pub fn fail(format: Format, error: &(dyn std::error::Error + 'static)) -> ExitCode {
+    let (value, code) = report(error);
+    // Nothing more can be reported when the error itself cannot be rendered.
+    let rendered = render(format, &value).unwrap_or_else(|_| value.to_string());
+    eprintln!("{rendered}");
+    ExitCode::from(code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exit_codes_follow_status_codes() {
+        assert_eq!(exit_code(Code::NotFound), EXIT_NOT_FOUND);
+        assert_eq!(exit_code(Code::PermissionDenied), EXIT_PERMISSION_DENIED);
+        assert_eq!(exit_code(Code::Unauthenticated), EXIT_PERMISSION_DENIED);
+        assert_eq!(exit_code(Code::ResourceExhausted), EXIT_RESOURCE_EXHAUSTED);
+        assert_eq!(exit_code(Code::Unavailable), EXIT_TRANSPORT);
+        assert_eq!(exit_code(Code::InvalidArgument), EXIT_FAILURE);
+    }
+
+    #[test]
+    fn errors_are_told_apart_by_kind() {
+        let status = Status::not_found("fortress not found");
+        let (value, code) = report(&status);
+        assert_eq!(
+            value,
+            json!({
+                "code": "NotFound",
+                "message": "fortress not found",
+                "reason": null,
+                "metadata": null,
+            })
+        );
+        assert_eq!(code, EXIT_NOT_FOUND);
+
+        let Err(transport) = tonic::transport::Endpoint::from_shared("not a uri") else {
+            panic!("invalid uri accepted");
+        };
+        let (value, code) = report(&transport);
+        assert_eq!(value.get("code"), Some(&json!("Transport")));
+        assert_eq!(code, EXIT_TRANSPORT);
+
+        let io = std::io::Error::other("disk full");
+        let (value, code) = report(&io);
+        assert_eq!(value, json!({"code": "Unknown", "message": "disk full"}));
+        assert_eq!(code, EXIT_FAILURE);
+    }
+
+    #[test]
+    fn lists_render_one_item_per_line() {
+        let list = json!([
+            {"id": 1, "name": "Keep"},
+            {"id": 12, "name": null, "gold": 5},
+        ]);
+
+        assert_eq!(
+            render(Format::Ndjson, &list).ok().as_deref(),
+            Some("{\"id\":1,\"name\":\"Keep\"}\n{\"id\":12,\"name\":null,\"gold\":5}")
+        );
+        assert_eq!(
+            render(Format::Table, &list).ok().as_deref(),
+            Some("ID  NAME  GOLD\n1   Keep\n12        5")
+        );
+    }
+}
@@ -0,0 +1,476 @@
+//! `game-client tui`, a dashboard of the player's fortresses driven from the
+//! keyboard.
+
+use crate::{
+    BuildingClient, FortressClient, Session,
+    pb::common::v2::{Building, Costs, Fortress},
+    pb::game::v2::{
+        CollectFortressEnergyRequest, CollectFortressFoodRequest, CollectFortressGoldRequest,
+        CollectFortressWoodRequest, CreateFortressRequest, DeleteFortressRequest,
+        GetImproveBuildingCostsRequest, ImproveBuildingRequest, ListBuildingsByFortressRequest,
+        ListFortressesRequest,
+    },
+};
+use ratatui::{
+    DefaultTerminal, Frame,
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    layout::{Constraint, Layout},
+    style::{Color, Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, Paragraph, Row, Table, TableState},
+};
+use std::time::{Duration, Instant};
+use tonic::Status;
+
+/// How long to wait for a key before checking whether to refresh.
+const TICK: Duration = Duration::from_millis(250);
+/// Resource counters are reloaded this often.
+const REFRESH_INTERVAL: Duration = Duration::from_secs(2);
+
+const HELP: &str =
+    "q quit  tab switch  ↑↓ select  g/f/w/e collect  u upgrade  n new  d delete  r refresh";
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Focus {
+    Fortresses,
+    Buildings,
+}
+
+#[derive(Clone, Copy)]
+enum Resource {
+    Gold,
+    Food,
+    Wood,
+    Energy,
+}
+
+/// What the player reads of a failed call.
+fn describe(error: &(dyn std::error::Error + 'static)) -> String {
+    error
+        .downcast_ref::<Status>()
+        .map_or_else(|| error.to_string(), |status| status.message().to_owned())
+}
+
+/// Moves a selection by `delta` rows within `len` rows.
+fn step(state: &mut TableState, len: usize, delta: isize) {
+    let selected = state
+        .selected()
+        .map_or(0, |i| i.saturating_add_signed(delta))
+        .min(len.saturating_sub(1));
+    state.select((len > 0).then_some(selected));
+}
+
+/// Selects the row with `id`, or the nearest one when it is gone.
+fn reselect(state: &mut TableState, ids: impl Iterator<Item = i32>, id: Option<i32>) {
+    let ids: Vec<_> = ids.collect();
+    let index = id
+        .and_then(|id| ids.iter().position(|&other| other == id))
+        .or_else(|| state.selected().map(|i| i.min(ids.len().saturating_sub(1))))
+        .or(Some(0));
+    state.select(index.filter(|_| !ids.is_empty()));
+}
+
+struct App<'a> {
+    fortress_client: &'a mut FortressClient,
+    building_client: &'a mut BuildingClient,
+    session: &'a mut Session,
+    fortresses: Vec<Fortress>,
+    fortress_state: TableState,
+    buildings: Vec<Building>,
+    building_state: TableState,
+    /// Costs of the next level of the selected building.
+    costs: Option<Costs>,
+    focus: Focus,
+    status: String,
+    /// Fortress to delete once `d` is pressed again.
+    pending_delete: Option<i32>,
+}
+
+impl App<'_> {
+    fn selected_fortress(&self) -> Option<&Fortress> {
+        self.fortresses.get(self.fortress_state.selected()?)
+    }
+
+    fn selected_building(&self) -> Option<&Building> {
+        self.buildings.get(self.building_state.selected()?)
+    }
+
+    async fn refresh(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.session.keep_alive().await?;
+        let selected = self.selected_fortress().map(|f| f.id);
+        let mut fortresses = Vec::new();
+        let mut page_token = String::new();
+        loop {
+            let response = self
+                .fortress_client
+                .list_fortresses(ListFortressesRequest {
+                    only_mine: true,
+                    page_token,
+                    ..Default::default()
+                })
+                .await?
+                .into_inner();
+            fortresses.extend(response.fortresses);
+            if response.next_page_token.is_empty() {
+                break;
+            }
+            page_token = response.next_page_token;
+        }
+        self.fortresses = fortresses;
+        reselect(
+            &mut self.fortress_state,
+            self.fortresses.iter().map(|f| f.id),
+            selected,
+        );
+        if self.selected_fortress().map(|f| f.id) != selected {
+            self.load_buildings().await?;
+        }
+        Ok(())
+    }
+
+    async fn load_buildings(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let selected = self.selected_building().map(|b| b.id);
+        self.buildings = match self.selected_fortress().map(|f| f.id) {
+            Some(fortress_id) => {
+                self.building_client
+                    .list_buildings_by_fortress(ListBuildingsByFortressRequest { fortress_id })
+                    .await?
+                    .into_inner()
+                    .buildings
+            }
+            None => Vec::new(),
+        };
+        reselect(
+            &mut self.building_state,
+            self.buildings.iter().map(|b| b.id),
+            selected,
+        );
+        self.load_costs().await
+    }
+
+    async fn load_costs(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.costs = match self.selected_building().map(|b| b.id) {
+            Some(id) => {
+                self.building_client
+                    .get_improve_building_costs(GetImproveBuildingCostsRequest { id })
+                    .await?
+                    .into_inner()
+                    .costs
+            }
+            None => None,
+        };
+        Ok(())
+    }
+
+    fn replace_fortress(&mut self, fortress: Option<Fortress>) {
+        if let Some(fortress) = fortress
+            && let Some(current) = self.fortresses.iter_mut().find(|f| f.id == fortress.id)
+        {
+            *current = fortress;
+        }
+    }
+
+    async fn collect(&mut self, resource: Resource) -> Result<String, Box<dyn std::error::Error>> {
+        let id = self.selected_fortress().ok_or("No fortress selected")?.id;
+        let client = &mut *self.fortress_client;
+        let (fortress, name) = match resource {
+            Resource::Gold => (
+                client
+                    .collect_fortress_gold(CollectFortressGoldRequest {
+                        id,
+                        expected_version: None,
+                    })
+                    .await?
+                    .into_inner()
+                    .fortress,
+                "gold",
+            ),
+            Resource::Food => (
+                client
+                    .collect_fortress_food(CollectFortressFoodRequest {
+                        id,
+                        expected_version: None,
+                    })
+                    .await?
+                    .into_inner()
+                    .fortress,
+                "food",
+            ),
+            Resource::Wood => (
+                client
+                    .collect_fortress_wood(CollectFortressWoodRequest {
+                        id,
+                        expected_version: None,
+                    })
+                    .await?
+                    .into_inner()
+                    .fortress,
+                "wood",
+            ),
+            Resource::Energy => (
+                client
+                    .collect_fortress_energy(CollectFortressEnergyRequest {
+                        id,
+                        expected_version: None,
+                    })
+                    .await?
+                    .into_inner()
+                    .fortress,
+                "energy",
+            ),
+        };
+        self.replace_fortress(fortress);
+        Ok(format!("Collected {name} in fortress #{id}"))
+    }
+
+    async fn upgrade(&mut self) -> Result<String, Box<dyn std::error::Error>> {
+        let id = self.selected_building().ok_or("No building selected")?.id;
+        let response = self
+            .building_client
+            .improve_building(ImproveBuildingRequest {
+                id,
+                expected_version: None,
+            })
+            .await?
+            .into_inner();
+        self.replace_fortress(response.fortress);
+        let building = response.building.ok_or("building not found")?;
+        let message = format!("{} upgraded to level {}", building.name, building.level);
+        if let Some(current) = self.buildings.iter_mut().find(|b| b.id == building.id) {
+            *current = building;
+        }
+        self.load_costs().await?;
+        Ok(message)
+    }
+
+    async fn create(&mut self) -> Result<String, Box<dyn std::error::Error>> {
+        let fortress = self
+            .fortress_client
+            .create_fortress(CreateFortressRequest {})
+            .await?
+            .into_inner()
+            .fortress
+            .ok_or("fortress not found")?;
+        let id = fortress.id;
+        self.fortresses.push(fortress);
+        self.fortress_state
+            .select(Some(self.fortresses.len().saturating_sub(1)));
+        self.load_buildings().await?;
+        Ok(format!("Fortress #{id} created"))
+    }
+
+    async fn delete(&mut self, id: i32) -> Result<String, Box<dyn std::error::Error>> {
+        self.fortress_client
+            .delete_fortress(DeleteFortressRequest { id })
+            .await?;
+        self.fortresses.retain(|f| f.id != id);
+        reselect(
+            &mut self.fortress_state,
+            self.fortresses.iter().map(|f| f.id),
+            None,
+        );
+        self.load_buildings().await?;
+        Ok(format!(
+            "Fortress #{id} deleted, it can be restored with 'game-client fortress restore {id}'"
+        ))
+    }
+
+    async fn select(&mut self, delta: isize) -> Result<String, Box<dyn std::error::Error>> {
+        match self.focus {
+            Focus::Fortresses => {
+                step(&mut self.fortress_state, self.fortresses.len(), delta);
+                self.load_buildings().await?;
+            }
+            Focus::Buildings => {
+                step(&mut self.building_state, self.buildings.len(), delta);
+                self.load_costs().await?;
+            }
+        }
+        Ok(String::new())
+    }
+
+    /// Handles a key, returning whether to keep running.
+    async fn on_key(&mut self, code: KeyCode) -> bool {
+        let pending_delete = self.pending_delete.take();
+        let result = match code {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Tab => {
+                self.focus = match self.focus {
+                    Focus::Fortresses => Focus::Buildings,
+                    Focus::Buildings => Focus::Fortresses,
+                };
+                Ok(String::new())
+            }
+            KeyCode::Up | KeyCode::Char('k') => self.select(-1).await,
+            KeyCode::Down | KeyCode::Char('j') => self.select(1).await,
+            KeyCode::Char('g') => self.collect(Resource::Gold).await,
+            KeyCode::Char('f') => self.collect(Resource::Food).await,
+            KeyCode::Char('w') => self.collect(Resource::Wood).await,
+            KeyCode::Char('e') => self.collect(Resource::Energy).await,
+            KeyCode::Char('u') | KeyCode::Enter => self.upgrade().await,
+            KeyCode::Char('n') => self.create().await,
+            KeyCode::Char('d') => match (pending_delete, self.selected_fortress().map(|f| f.id)) {
+                (Some(id), _) => self.delete(id).await,
+                (None, Some(id)) => {
+                    self.pending_delete = Some(id);
+                    Ok(format!(
+                        "Press d again to delete fortress #{id}, any other key to cancel"
+                    ))
+                }
+                (None, None) => Ok(String::new()),
+            },
+            KeyCode::Char('r') => self.refresh().await.map(|()| "Refreshed".to_owned()),
+            _ => Ok(String::new()),
+        };
+        self.status = result.unwrap_or_else(|e| describe(e.as_ref()));
+        true
+    }
+
+    fn cost_line(&self) -> Line<'_> {
+        let (Some(costs), Some(fortress)) = (&self.costs, self.selected_fortress()) else {
+            return Line::from("Select a building to see what its next level costs");
+        };
+        let mut spans = Vec::new();
+        for (name, cost, stock) in [
+            ("gold", costs.gold, fortress.gold),
+            ("food", costs.food, fortress.food),
+            ("wood", costs.wood, fortress.wood),
+            ("energy", costs.energy, fortress.energy),
+        ] {
+            let span = if stock >= cost {
+                Span::styled(format!("{name} {cost}"), Style::new().fg(Color::Green))
+            } else {
+                Span::styled(
+                    format!("{name} {cost} (missing {})", cost - stock),
+                    Style::new().fg(Color::Red),
+                )
+            };
+            spans.extend([span, Span::raw("  ")]);
+        }
+        Line::from(spans)
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [main, costs, status, help] = Layout::vertical([
+            Constraint::Min(5),
+            Constraint::Length(3),
+            Constraint::Length(1),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        let [left, right] =
+            Layout::horizontal([Constraint::Percentage(60), Constraint::Percentage(40)])
+                .areas(main);
+        let focused = |focus| {
+            if self.focus == focus {
+                Style::new().bold()
+            } else {
+                Style::new()
+            }
+        };
+
+        let fortresses = Table::new(
+            self.fortresses.iter().map(|f| {
+                Row::new([
+                    format!("#{}", f.id),
+                    f.gold.to_string(),
+                    f.food.to_string(),
+                    f.wood.to_string(),
+                    f.energy.to_string(),
+                ])
+            }),
+            [
+                Constraint::Length(8),
+                Constraint::Fill(1),
+                Constraint::Fill(1),
+                Constraint::Fill(1),
+                Constraint::Fill(1),
+            ],
+        )
+        .header(Row::new(["ID", "GOLD", "FOOD", "WOOD", "ENERGY"]).underlined())
+        .block(
+            Block::bordered()
+                .title("Fortresses")
+                .border_style(focused(Focus::Fortresses)),
+        )
+        .row_highlight_style(Style::new().reversed());
+        frame.render_stateful_widget(fortresses, left, &mut self.fortress_state);
+
+        let buildings = Table::new(
+            self.buildings
+                .iter()
+                .map(|b| Row::new([b.name.clone(), b.level.to_string()])),
+            [Constraint::Fill(1), Constraint::Length(6)],
+        )
+        .header(Row::new(["NAME", "LEVEL"]).underlined())
+        .block(
+            Block::bordered()
+                .title("Buildings")
+                .border_style(focused(Focus::Buildings)),
+        )
+        .row_highlight_style(Style::new().reversed());
+        frame.render_stateful_widget(buildings, right, &mut self.building_state);
+
+        let title = self.selected_building().map_or_else(
+            || "Upgrade".to_owned(),
+            |b| format!("Upgrade {} to level {}", b.name, b.level.saturating_add(1)),
+        );
+        frame.render_widget(
+            Paragraph::new(self.cost_line()).block(Block::bordered().title(title)),
+            costs,
+        );
+        frame.render_widget(Paragraph::new(self.status.as_str()), status);
+        frame.render_widget(Paragraph::new(HELP).dim(), help);
+    }
+
+    async fn event_loop(
+        &mut self,
+        terminal: &mut DefaultTerminal,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut last_refresh = Instant::now();
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            if event::poll(TICK)?
+                && let Event::Key(key) = event::read()?
+                && key.kind == KeyEventKind::Press
+                && !self.on_key(key.code).await
+            {
+                return Ok(());
+            }
+            if last_refresh.elapsed() >= REFRESH_INTERVAL {
+                if let Err(e) = self.refresh().await {
+                    self.status = describe(e.as_ref());
+                }
+                last_refresh = Instant::now();
+            }
+        }
+    }
+}
+
+pub async fn run(
+    fortress_client: &mut FortressClient,
+    building_client: &mut BuildingClient,
+    session: &mut Session,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut app = App {
+        fortress_client,
+        building_client,
+        session,
+        fortresses: Vec::new(),
+        fortress_state: TableState::default(),
+        buildings: Vec::new(),
+        building_state: TableState::default(),
+        costs: None,
+        focus: Focus::Fortresses,
+        status: String::new(),
+        pending_delete: None,
+    };
+    // Failing here means the server cannot be used at all, better report it
+    // before taking over the terminal.
+    app.refresh().await?;
+
+    let mut terminal = ratatui::init();
+    let result = app.event_loop(&mut terminal).await;
+    ratatui::restore();
+    result
+}
@@ -11,8 +11,8 @@ fn prost_build() -> Result<(), Box<dyn std::error::Error>> {
         .build_transport(false)
         .compile_protos(
             &[
-                "../proto/common/v1/common.proto",
-                "../proto/game/v1/game.proto",
+                "../proto/common/v2/common.proto",
+                "../proto/game/v2/game.proto",
             ],
             &["../proto"],
         )?;
@@ -1,11 +1,14 @@
 use crate::{
-    i18n::I18nContextProvider,
-    pb::game::v1::{
-        building_service_client::BuildingServiceClient,
-        fortress_service_client::FortressServiceClient,
+    i18n::{I18nContextProvider, t, use_i18n},
+    pb::{
+        common::v2::{FortressSummary, WealthBand},
+        game::v2::{
+            building_service_client::BuildingServiceClient,
+            fortress_service_client::FortressServiceClient,
+        },
     },
     views::{
-        building_detail::BuildingDetail, building_list::BuildingList,
+        building_detail::BuildingDetail, building_list::BuildingList, camp_list::CampList,
         fortress_building_list::FortressBuildingList, fortress_detail::FortressDetail,
         fortress_list::FortressList, home::Home, nav_menu::NavMenu, not_found::NotFound,
     },
@@ -13,7 +16,7 @@ use crate::{
 use leptos::prelude::*;
 use leptos_oidc::{Auth, AuthErrorContext, AuthLoading, AuthParameters, AuthSignal, Challenge};
 use leptos_router::{
-    components::{Route, Router, Routes},
+    components::{A, Route, Router, Routes},
     hooks::use_params_map,
     path,
 };
@@ -139,6 +142,7 @@ pub fn App() -> impl IntoView {
                             />
                             <Route path=path!("/buildings") view=BuildingList />
                             <Route path=path!("/buildings/:id") view=BuildingDetail />
+                            <Route path=path!("/camps") view=CampList />
                         </Routes>
                     </main>
                 </div>
@@ -147,6 +151,51 @@ pub fn App() -> impl IntoView {
     }
 }
 
+/// Links to the first and the next page of a paginated list, the next one is
+/// only shown when `next_href` is set.
+#[component]
+pub fn PageLinks(first_href: String, next_href: Option<String>) -> impl IntoView {
+    let i18n = use_i18n();
+
+    view! {
+        <div>
+            <A href=first_href>{t!(i18n, first_page)}</A>
+            {next_href
+                .map(|href| {
+                    view! {
+                        " "
+                        <A href=href>{t!(i18n, next_page)}</A>
+                    }
+                })}
+        </div>
+    }
+}
+
+/// What other players see of a fortress: its owner, level tier and wealth.
+#[component]
+pub fn FortressSummaryView(summary: FortressSummary) -> impl IntoView {
+    let i18n = use_i18n();
+    let owner = if summary.owner_name.is_empty() {
+        t!(i18n, unknown_owner).into_view().into_any()
+    } else {
+        summary.owner_name.into_view().into_any()
+    };
+    let wealth = match summary.wealth_band() {
+        WealthBand::Unspecified => t!(i18n, no_data).into_view().into_any(),
+        WealthBand::Poor => t!(i18n, wealth_poor).into_view().into_any(),
+        WealthBand::Modest => t!(i18n, wealth_modest).into_view().into_any(),
+        WealthBand::Wealthy => t!(i18n, wealth_wealthy).into_view().into_any(),
+        WealthBand::Opulent => t!(i18n, wealth_opulent).into_view().into_any(),
+    };
+
+    view! {
+        <span>
+            {t!(i18n, owner)}": " {owner} ", " {t!(i18n, level_tier)}": " {summary.level_tier}
+            ", " {t!(i18n, wealth)}": " {wealth}
+        </span>
+    }
+}
+
 #[component]
 pub fn ResourceView<T, F, IV>(resource: LocalResource<Result<T, String>>, view: F) -> impl IntoView
 where
@@ -3,13 +3,13 @@
 #[allow(clippy::pedantic, clippy::nursery)]
 pub mod pb {
     pub mod common {
-        pub mod v1 {
-            tonic::include_proto!("common.v1");
+        pub mod v2 {
+            tonic::include_proto!("common.v2");
         }
     }
     pub mod game {
-        pub mod v1 {
-            tonic::include_proto!("game.v1");
+        pub mod v2 {
+            tonic::include_proto!("game.v2");
         }
     }
 }
@@ -1,13 +1,11 @@
 use crate::{
     app::{ResourceView, get_building_client, get_token, use_id_param},
     i18n::{t, use_i18n},
-    pb::{
-        common::v1::Costs,
-        game::v1::{GetBuildingRequest, GetImproveBuildingCostsRequest, ImproveBuildingRequest},
-    },
+    pb::game::v2::{GetBuildingRequest, ImproveBuildingRequest},
 };
 use leptos::prelude::*;
 use leptos_router::components::A;
+use rusty::{Costs, rules::Economy};
 
 #[component]
 pub fn BuildingDetail() -> impl IntoView {
@@ -20,7 +18,7 @@ pub fn BuildingDetail() -> impl IntoView {
         let token = get_token();
         async move {
             let Some(id) = id else { return Ok(None) };
-            let mut building_client = get_building_client(token.clone());
+            let mut building_client = get_building_client(token);
             let building_req = tonic::Request::new(GetBuildingRequest { id });
             let building = match building_client.get_building(building_req).await {
                 Ok(resp) => match resp.into_inner().building {
@@ -36,25 +34,29 @@ pub fn BuildingDetail() -> impl IntoView {
                     return Err(status.to_string());
                 }
             };
-            let mut cost_client = get_building_client(token);
-            let cost_req = tonic::Request::new(GetImproveBuildingCostsRequest { id });
-            let costs = cost_client
-                .get_improve_building_costs(cost_req)
-                .await
-                .map(|resp| resp.into_inner().costs)
-                .map_err(|e| e.to_string())?;
+            // Same rules as the server, no need to ask it.
+            let costs = Economy::LIVE.next_upgrade_costs(building.level);
 
             Ok(Some((building, costs)))
         }
     });
-    let improve_action = Action::new_local(move |id: &i32| {
-        let id = *id;
+    let improve_action = Action::new_local(move |&(id, version): &(i32, i64)| {
         let token = get_token();
         async move {
             let mut client = get_building_client(token);
-            let request = tonic::Request::new(ImproveBuildingRequest { id });
-            if (client.improve_building(request).await).is_ok() {
-                set_refresh_trigger.update(|n| *n += 1);
+            let request = tonic::Request::new(ImproveBuildingRequest {
+                id,
+                expected_version: Some(version),
+            });
+            match client.improve_building(request).await {
+                Ok(_) => set_refresh_trigger.update(|n| *n += 1),
+                // Someone else changed the building, show its current state
+                // and costs before the player tries again.
+                Err(status) if status.code() == tonic::Code::Aborted => {
+                    leptos::logging::warn!("Improve conflict: {}", status.message());
+                    set_refresh_trigger.update(|n| *n += 1);
+                }
+                Err(status) => leptos::logging::error!("Improve failed: {}", status),
             }
         }
     });
@@ -74,7 +76,12 @@ pub fn BuildingDetail() -> impl IntoView {
                                     <li>{t!(i18n, level)} ": " {b.level}</li>
                                     <li>{t!(i18n, fortress_id)} ": " {b.fortress_id}</li>
                                 </ul>
-                                <UpgradeSection id=b.id costs=costs action=improve_action />
+                                <UpgradeSection
+                                    id=b.id
+                                    version=b.version
+                                    costs=costs
+                                    action=improve_action
+                                />
                                 <div>
                                     <A href=format!(
                                         "/fortresses/{}",
@@ -95,7 +102,12 @@ pub fn BuildingDetail() -> impl IntoView {
 }
 
 #[component]
-fn UpgradeSection(id: i32, costs: Option<Costs>, action: Action<i32, ()>) -> impl IntoView {
+fn UpgradeSection(
+    id: i32,
+    version: i64,
+    costs: Option<Costs>,
+    action: Action<(i32, i64), ()>,
+) -> impl IntoView {
     let i18n = use_i18n();
 
     view! {
@@ -103,7 +115,7 @@ fn UpgradeSection(id: i32, costs: Option<Costs>, action: Action<i32, ()>) -> imp
         <div>
             {costs
                 .map_or_else(
-                    || view! { <p>{t!(i18n, costs_not_found)}</p> }.into_any(),
+                    || view! { <p>{t!(i18n, max_level_reached)}</p> }.into_any(),
                     |c| {
                         view! {
                             <p>
@@ -117,9 +129,9 @@ fn UpgradeSection(id: i32, costs: Option<Costs>, action: Action<i32, ()>) -> imp
                 )}
             <button
                 on:click=move |_| {
-                    action.dispatch(id);
+                    action.dispatch((id, version));
                 }
-                disabled=move || action.pending().get()
+                disabled=move || costs.is_none() || action.pending().get()
             >
                 {move || {
                     if action.pending().get() {
@@ -1,23 +1,32 @@
 use crate::{
-    app::{ResourceView, get_building_client, get_token},
+    app::{PageLinks, ResourceView, get_building_client, get_token},
     i18n::{t, use_i18n},
-    pb::game::v1::ListBuildingsRequest,
+    pb::game::v2::ListBuildingsRequest,
 };
 use leptos::prelude::*;
-use leptos_router::components::A;
+use leptos_router::{components::A, hooks::use_query_map};
 
 #[component]
 pub fn BuildingList() -> impl IntoView {
     let i18n = use_i18n();
-    let buildings_resource = LocalResource::new(move || async move {
+    let query = use_query_map();
+    let buildings_resource = LocalResource::new(move || {
         let token = get_token();
-        let mut client = get_building_client(token);
-        let request = tonic::Request::new(ListBuildingsRequest {});
-        client
-            .list_buildings(request)
-            .await
-            .map(tonic::Response::into_inner)
-            .map_err(|e| e.to_string())
+        let page_token = query.get().get("page_token").unwrap_or_default();
+
+        async move {
+            let mut client = get_building_client(token);
+            let request = tonic::Request::new(ListBuildingsRequest {
+                page_size: 0,
+                page_token,
+                ..ListBuildingsRequest::default()
+            });
+            client
+                .list_buildings(request)
+                .await
+                .map(tonic::Response::into_inner)
+                .map_err(|e| e.to_string())
+        }
     });
 
     view! {
@@ -25,7 +34,9 @@ pub fn BuildingList() -> impl IntoView {
             <h2>{t!(i18n, building_list)}</h2>
             <ResourceView
                 resource=buildings_resource
-                view=|resp| {
+                view=move |resp| {
+                    let next_page_token = resp.next_page_token;
+
                     view! {
                         <ul>
                             <For
@@ -43,6 +54,11 @@ pub fn BuildingList() -> impl IntoView {
                                 }
                             />
                         </ul>
+                        <PageLinks
+                            first_href="/buildings".to_owned()
+                            next_href=(!next_page_token.is_empty())
+                                .then(|| format!("/buildings?page_token={next_page_token}"))
+                        />
                     }
                 }
             />
@@ -0,0 +1,149 @@
+use crate::{
+    app::{ResourceView, get_fortress_client, get_token},
+    i18n::{t, use_i18n},
+    pb::game::v2::{ListCampsRequest, ListFortressesRequest, RaidCampRequest},
+};
+use leptos::prelude::*;
+
+#[component]
+pub fn CampList() -> impl IntoView {
+    let i18n = use_i18n();
+    let (refresh_trigger, set_refresh_trigger) = signal(0);
+    // The fortress picked to raid from, the first one until another is picked.
+    let (raider, set_raider) = signal(None::<i32>);
+    let (outcome, set_outcome) = signal(None::<Result<bool, String>>);
+    let camps_resource = LocalResource::new(move || {
+        refresh_trigger.get();
+        let token = get_token();
+
+        async move {
+            let mut client = get_fortress_client(token);
+            let camps = client
+                .list_camps(tonic::Request::new(ListCampsRequest {}))
+                .await
+                .map_err(|e| e.message().to_owned())?
+                .into_inner()
+                .camps;
+            let fortresses = client
+                .list_fortresses(tonic::Request::new(ListFortressesRequest {
+                    only_mine: true,
+                    page_size: 0,
+                    page_token: String::new(),
+                    ..ListFortressesRequest::default()
+                }))
+                .await
+                .map_err(|e| e.message().to_owned())?
+                .into_inner()
+                .fortresses;
+            Ok((camps, fortresses))
+        }
+    });
+    let raid_action = Action::new_local(move |&(camp_id, fortress_id): &(i32, i32)| {
+        let token = get_token();
+
+        async move {
+            let mut client = get_fortress_client(token);
+            let request = tonic::Request::new(RaidCampRequest {
+                camp_id,
+                fortress_id,
+            });
+            match client.raid_camp(request).await {
+                Ok(resp) => set_outcome.set(Some(Ok(resp.into_inner().success))),
+                Err(e) => set_outcome.set(Some(Err(e.message().to_owned()))),
+            }
+            set_refresh_trigger.update(|n| *n += 1);
+        }
+    });
+
+    view! {
+        <div>
+            <h2>{t!(i18n, camps)}</h2>
+            {move || {
+                outcome
+                    .get()
+                    .map(|outcome| match outcome {
+                        Ok(true) => view! { <p>{t!(i18n, raid_success)}</p> }.into_any(),
+                        Ok(false) => view! { <p>{t!(i18n, raid_failure)}</p> }.into_any(),
+                        Err(e) => view! { <p>"Error: " {e}</p> }.into_any(),
+                    })
+            }}
+            <ResourceView
+                resource=camps_resource
+                view=move |(camps, fortresses)| {
+                    let Some(first) = fortresses.first().map(|f| f.id) else {
+                        return view! { <p>{t!(i18n, no_fortress_to_raid)}</p> }.into_any();
+                    };
+
+                    view! {
+                        <p>
+                            {t!(i18n, raid_from)}": "
+                            <select on:change=move |ev| {
+                                set_raider.set(event_target_value(&ev).parse().ok());
+                            }>
+                                <For
+                                    each=move || fortresses.clone()
+                                    key=|f| f.id
+                                    children=move |f| {
+                                        view! {
+                                            <option
+                                                value=f.id.to_string()
+                                                selected=move || raider.get() == Some(f.id)
+                                            >
+                                                {t!(i18n, fortress)}" #"{f.id}" ("{t!(i18n, energy)}": "
+                                                {f.energy}")"
+                                            </option>
+                                        }
+                                    }
+                                />
+                            </select>
+                        </p>
+                        <ul>
+                            <For
+                                each=move || camps.clone()
+                                key=|c| (c.id, c.respawn_at)
+                                children=move |c| {
+                                    let camp_id = c.id;
+                                    let standing = c.respawn_at.is_none();
+                                    let loot = c.loot.unwrap_or_default();
+                                    view! {
+                                        <li>
+                                            {t!(i18n, camp)}" " {c.slot + 1} ", "
+                                            {t!(i18n, strength)}": " {c.strength} ", "
+                                            {t!(i18n, loot)}": " {loot.gold} " "
+                                            {t!(i18n, gold)} ", " {loot.food} " " {t!(i18n, food)}
+                                            ", " {loot.wood} " " {t!(i18n, wood)} ", "
+                                            {loot.energy} " " {t!(i18n, energy)} " "
+                                            <Show
+                                                when=move || standing
+                                                fallback=move || t!(i18n, camp_raided)
+                                            >
+                                                <button
+                                                    on:click=move |_| {
+                                                        let fortress_id = raider
+                                                            .get_untracked()
+                                                            .unwrap_or(first);
+                                                        raid_action.dispatch((camp_id, fortress_id));
+                                                    }
+                                                    disabled=move || raid_action.pending().get()
+                                                >
+                                                    {move || {
+                                                        if raid_action.pending().get() {
+                                                            t!(i18n, raiding).into_view().into_any()
+                                                        } else {
+                                                            t!(i18n, raid).into_view().into_any()
+                                                        }
+                                                    }}
+                                                </button>
+                                            </Show>
+                                        </li>
+                                    }
+                                }
+                            />
+                        </ul>
+                    }
+                        .into_any()
+                }
+            />
+        </div>
+    }
+}
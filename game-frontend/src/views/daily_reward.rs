@@ -0,0 +1,133 @@
+use crate::{
+    app::{ResourceView, get_fortress_client, get_token},
+    i18n::{t, use_i18n},
+    pb::game::v2::{ClaimDailyRewardRequest, GetDailyRewardRequest, ListFortressesRequest},
+};
+use leptos::prelude::*;
+
+#[component]
+pub fn DailyReward() -> impl IntoView {
+    let i18n = use_i18n();
+    let (refresh_trigger, set_refresh_trigger) = signal(0);
+    // The fortress credited with the reward, the first one until another is picked.
+    let (target, set_target) = signal(None::<i32>);
+    let (error, set_error) = signal(None::<String>);
+    let reward_resource = LocalResource::new(move || {
+        refresh_trigger.get();
+        let token = get_token();
+
+        async move {
+            let mut client = get_fortress_client(token);
+            let reward = client
+                .get_daily_reward(tonic::Request::new(GetDailyRewardRequest {}))
+                .await
+                .map_err(|e| e.message().to_owned())?
+                .into_inner();
+            let fortresses = client
+                .list_fortresses(tonic::Request::new(ListFortressesRequest {
+                    only_mine: true,
+                    page_size: 0,
+                    page_token: String::new(),
+                    ..ListFortressesRequest::default()
+                }))
+                .await
+                .map_err(|e| e.message().to_owned())?
+                .into_inner()
+                .fortresses;
+            Ok((reward, fortresses))
+        }
+    });
+    let claim_action = Action::new_local(move |&fortress_id: &i32| {
+        let token = get_token();
+
+        async move {
+            let mut client = get_fortress_client(token);
+            let request = tonic::Request::new(ClaimDailyRewardRequest { fortress_id });
+            match client.claim_daily_reward(request).await {
+                Ok(_) => set_error.set(None),
+                Err(e) => set_error.set(Some(e.message().to_owned())),
+            }
+            set_refresh_trigger.update(|n| *n += 1);
+        }
+    });
+
+    view! {
+        <div>
+            <h3>{t!(i18n, daily_reward)}</h3>
+            {move || error.get().map(|e| view! { <p>"Error: " {e}</p> })}
+            <ResourceView
+                resource=reward_resource
+                view=move |(reward, fortresses)| {
+                    let next = reward.next_reward.unwrap_or_default();
+                    let claimable = reward.claimable;
+                    let first = fortresses.first().map(|f| f.id);
+
+                    view! {
+                        <p>
+                            {t!(i18n, streak)}": " {reward.streak} " " {t!(i18n, days)}
+                        </p>
+                        <p>
+                            <Show
+                                when=move || claimable
+                                fallback=move || t!(i18n, next_daily_reward)
+                            >
+                                {t!(i18n, todays_reward)}
+                            </Show>
+                            ": " {next.gold} " " {t!(i18n, gold)} ", " {next.food} " "
+                            {t!(i18n, food)} ", " {next.wood} " " {t!(i18n, wood)} ", "
+                            {next.energy} " " {t!(i18n, energy)}
+                        </p>
+                        {match first {
+                            None => view! { <p>{t!(i18n, no_fortress_to_claim)}</p> }.into_any(),
+                            Some(first) if claimable => {
+                                view! {
+                                    <p>
+                                        <select on:change=move |ev| {
+                                            set_target.set(event_target_value(&ev).parse().ok());
+                                        }>
+                                            <For
+                                                each=move || fortresses.clone()
+                                                key=|f| f.id
+                                                children=move |f| {
+                                                    view! {
+                                                        <option
+                                                            value=f.id.to_string()
+                                                            selected=move || target.get() == Some(f.id)
+                                                        >
+                                                            {t!(i18n, fortress)}" #"{f.id}
+                                                        </option>
+                                                    }
+                                                }
+                                            />
+                                        </select>
+                                        " "
+                                        <button
+                                            on:click=move |_| {
+                                                let fortress_id = target
+                                                    .get_untracked()
+                                                    .unwrap_or(first);
+                                                claim_action.dispatch(fortress_id);
+                                            }
+                                            disabled=move || claim_action.pending().get()
+                                        >
+                                            {move || {
+                                                if claim_action.pending().get() {
+                                                    t!(i18n, claiming).into_view().into_any()
+                                                } else {
+                                                    t!(i18n, claim).into_view().into_any()
+                                                }
+                                            }}
+                                        </button>
+                                    </p>
+                                }
+                                    .into_any()
+                            }
+                            Some(_) => view! { <p>{t!(i18n, daily_reward_claimed)}</p> }.into_any(),
+                        }}
+                    }
+                        .into_any()
+                }
+            />
+        </div>
+    }
+}
@@ -1,7 +1,7 @@
 use crate::{
     app::{ResourceView, get_building_client, get_token, use_id_param},
     i18n::{t, use_i18n},
-    pb::game::v1::ListBuildingsByFortressRequest,
+    pb::game::v2::ListBuildingsByFortressRequest,
 };
 use leptos::prelude::*;
 use leptos_router::components::A;
@@ -1,13 +1,17 @@
 use crate::{
-    app::{ResourceView, get_fortress_client, get_token, use_id_param},
+    app::{FortressSummaryView, ResourceView, get_fortress_client, get_token, use_id_param},
     i18n::{t, use_i18n},
-    pb::game::v1::{
-        CollectFortressEnergyRequest, CollectFortressFoodRequest, CollectFortressGoldRequest,
-        CollectFortressWoodRequest, GetFortressRequest,
+    pb::{
+        common::v2::{Building, Costs, Fortress, FortressSummary},
+        game::v2::{
+            CollectFortressEnergyRequest, CollectFortressFoodRequest, CollectFortressGoldRequest,
+            CollectFortressWoodRequest, GetFortressOverviewRequest,
+        },
     },
 };
 use leptos::prelude::*;
 use leptos_router::components::A;
+use rusty::rules::{Economy, can_afford};
 
 macro_rules! make_collect_action {
     ($req_type:ident, $method:ident, $trigger:expr) => {
@@ -18,7 +22,10 @@ macro_rules! make_collect_action {
 
             async move {
                 let mut client = get_fortress_client(token);
-                let request = tonic::Request::new($req_type { id });
+                let request = tonic::Request::new($req_type {
+                    id,
+                    expected_version: None,
+                });
 
                 match client.$method(request).await {
                     Ok(_) => trigger.update(|n| *n += 1),
@@ -29,13 +36,20 @@ macro_rules! make_collect_action {
     };
 }
 
+/// Owners see the whole fortress, other players only its summary.
+#[derive(Clone)]
+enum Overview {
+    Full(Fortress, Costs, Vec<Building>),
+    Summary(FortressSummary),
+}
+
 #[allow(clippy::similar_names)]
 #[component]
 pub fn FortressDetail() -> impl IntoView {
     let i18n = use_i18n();
     let id_signal = use_id_param();
     let (refresh_trigger, set_refresh_trigger) = signal(0);
-    let fortress_resource = LocalResource::new(move || {
+    let overview_resource = LocalResource::new(move || {
         let id = id_signal();
         refresh_trigger.get();
         let token = get_token();
@@ -44,10 +58,25 @@ pub fn FortressDetail() -> impl IntoView {
             let Some(id) = id else { return Ok(None) };
 
             let mut client = get_fortress_client(token);
-            let request = tonic::Request::new(GetFortressRequest { id });
+            let request = tonic::Request::new(GetFortressOverviewRequest { id });
 
-            match client.get_fortress(request).await {
-                Ok(resp) => resp.into_inner().fortress.map_or(Ok(None), |f| Ok(Some(f))),
+            match client.get_fortress_overview(request).await {
+                Ok(resp) => {
+                    let overview = resp.into_inner();
+                    let summary = overview.summary;
+                    Ok(overview.fortress.map_or_else(
+                        || summary.map(Overview::Summary),
+                        |f| {
+                            let production = overview.production.unwrap_or_default();
+                            let buildings = overview
+                                .buildings
+                                .into_iter()
+                                .filter_map(|b| b.building)
+                                .collect::<Vec<_>>();
+                            Some(Overview::Full(f, production, buildings))
+                        },
+                    ))
+                }
                 Err(status) => {
                     if status.code() == tonic::Code::NotFound
                         || status.message().contains("not found")
@@ -84,40 +113,85 @@ pub fn FortressDetail() -> impl IntoView {
         <div>
             <h2>{t!(i18n, fortress_detail)}</h2>
             <ResourceView
-                resource=fortress_resource
-                view=move |fortress_opt| {
-                    fortress_opt
+                resource=overview_resource
+                view=move |overview_opt| {
+                    overview_opt
                         .map_or_else(
                             || t!(i18n, no_data).into_view().into_any(),
-                            |f| {
+                            |overview| {
+                                let (f, production, buildings) = match overview {
+                                    Overview::Full(f, production, buildings) => {
+                                        (f, production, buildings)
+                                    }
+                                    Overview::Summary(summary) => {
+                                        return view! {
+                                            <p>
+                                                {t!(i18n, id)}": " {summary.id} " "
+                                                <FortressSummaryView summary=summary />
+                                            </p>
+                                        }
+                                            .into_any();
+                                    }
+                                };
+                                let resources = rusty::Costs {
+                                    gold: f.gold,
+                                    food: f.food,
+                                    wood: f.wood,
+                                    energy: f.energy,
+                                };
                                 view! {
                                     <ul>
                                         <li>{t!(i18n, id)}": " {f.id}</li>
                                         <ResourceRow
                                             label=t!(i18n, gold).into_view().into_any()
                                             value=f.gold
+                                            production=production.gold
                                             id=f.id
                                             action=collect_gold_action
                                         />
                                         <ResourceRow
                                             label=t!(i18n, food).into_view().into_any()
                                             value=f.food
+                                            production=production.food
                                             id=f.id
                                             action=collect_food_action
                                         />
                                         <ResourceRow
                                             label=t!(i18n, wood).into_view().into_any()
                                             value=f.wood
+                                            production=production.wood
                                             id=f.id
                                             action=collect_wood_action
                                         />
                                         <ResourceRow
                                             label=t!(i18n, energy).into_view().into_any()
                                             value=f.energy
+                                            production=production.energy
                                             id=f.id
                                             action=collect_energy_action
                                         />
                                     </ul>
+                                    <h3>{t!(i18n, buildings)}</h3>
+                                    <ul>
+                                        <For
+                                            each=move || buildings.clone()
+                                            key=|b| b.id
+                                            children=move |b| {
+                                                view! {
+                                                    <li>
+                                                        <A href=format!(
+                                                            "/buildings/{}",
+                                                            b.id,
+                                                        )>{format!("{} (Lvl {})", b.name, b.level)}</A>
+                                                        <UpgradePreview
+                                                            level=b.level
+                                                            resources=resources
+                                                        />
+                                                    </li>
+                                                }
+                                            }
+                                        />
+                                    </ul>
                                     <div>
                                         <A href=format!(
                                             "/fortresses/{}/buildings",
@@ -137,12 +211,18 @@ pub fn FortressDetail() -> impl IntoView {
 }
 
 #[component]
-fn ResourceRow(label: AnyView, value: i32, id: i32, action: Action<i32, ()>) -> impl IntoView {
+fn ResourceRow(
+    label: AnyView,
+    value: i64,
+    production: i64,
+    id: i32,
+    action: Action<i32, ()>,
+) -> impl IntoView {
     let i18n = use_i18n();
 
     view! {
         <li>
-            {label} ": " {value} " "
+            {label} ": " {value} " (+" {production} " " {t!(i18n, per_collect)} ") "
             <button
                 on:click=move |_| {
                     action.dispatch(id);
@@ -154,3 +234,27 @@ fn ResourceRow(label: AnyView, value: i32, id: i32, action: Action<i32, ()>) ->
         </li>
     }
 }
+
+/// The next level of a building as computed by the shared game rules, and
+/// whether the fortress can pay for it.
+#[component]
+fn UpgradePreview(level: i32, resources: rusty::Costs) -> impl IntoView {
+    let i18n = use_i18n();
+
+    Economy::LIVE.next_upgrade_costs(level).map_or_else(
+        || view! { " - " {t!(i18n, max_level_reached)} }.into_any(),
+        |c| {
+            let status = if can_afford(&resources, &c) {
+                t!(i18n, affordable).into_view().into_any()
+            } else {
+                t!(i18n, not_affordable).into_view().into_any()
+            };
+            view! {
+                " - " {t!(i18n, next_level)} ": " {t!(i18n, gold)} " " {c.gold} ", "
+                {t!(i18n, food)} " " {c.food} ", " {t!(i18n, wood)} " " {c.wood} ", "
+                {t!(i18n, energy)} " " {c.energy} " (" {status} ")"
+            }
+            .into_any()
+        },
+    )
+}
@@ -1,7 +1,7 @@
 use crate::{
-    app::{ResourceView, get_fortress_client, get_token},
+    app::{FortressSummaryView, PageLinks, ResourceView, get_fortress_client, get_token},
     i18n::{t, use_i18n},
-    pb::game::v1::{CreateFortressRequest, DeleteFortressRequest, ListFortressesRequest},
+    pb::game::v2::{CreateFortressRequest, DeleteFortressRequest, ListFortressesRequest},
 };
 use leptos::prelude::*;
 use leptos_router::{components::A, hooks::use_query_map};
@@ -15,10 +15,16 @@ pub fn FortressList() -> impl IntoView {
         refresh_trigger.get();
         let token = get_token();
         let is_mine = query.get().get("mine").is_some_and(|v| v == "true");
+        let page_token = query.get().get("page_token").unwrap_or_default();
 
         async move {
             let mut fortress_client = get_fortress_client(token);
-            let request = tonic::Request::new(ListFortressesRequest { only_mine: is_mine });
+            let request = tonic::Request::new(ListFortressesRequest {
+                only_mine: is_mine,
+                page_size: 0,
+                page_token,
+                ..ListFortressesRequest::default()
+            });
             let response = fortress_client.list_fortresses(request).await;
             response
                 .map(tonic::Response::into_inner)
@@ -75,32 +81,51 @@ pub fn FortressList() -> impl IntoView {
             <ResourceView
                 resource=fortresses_resource
                 view=move |resp| {
+                    let is_mine = query.get().get("mine").is_some_and(|v| v == "true");
+                    let next_page_token = resp.next_page_token;
+                    let owned: Vec<i32> = resp.fortresses.iter().map(|f| f.id).collect();
+
                     view! {
                         <ul>
                             <For
-                                each=move || resp.fortresses.clone()
-                                key=|f| f.id
-                                children=move |f| {
+                                each=move || resp.summaries.clone()
+                                key=|s| s.id
+                                children=move |s| {
+                                    let id = s.id;
+                                    let deletable = owned.contains(&id);
                                     view! {
                                         <li>
                                             <A href=format!(
                                                 "/fortresses/{}",
-                                                f.id,
-                                            )>{t!(i18n, fortress)}" #"{f.id}</A>
+                                                id,
+                                            )>{t!(i18n, fortress)}" #"{id}</A>
+                                            " "
+                                            <FortressSummaryView summary=s />
                                             " "
-                                            <button
-                                                on:click=move |_| {
-                                                    delete_action.dispatch(f.id);
-                                                }
-                                                disabled=move || delete_action.pending().get()
-                                            >
-                                                {t!(i18n, delete)}
-                                            </button>
+                                            <Show when=move || deletable>
+                                                <button
+                                                    on:click=move |_| {
+                                                        delete_action.dispatch(id);
+                                                    }
+                                                    disabled=move || delete_action.pending().get()
+                                                >
+                                                    {t!(i18n, delete)}
+                                                </button>
+                                            </Show>
                                         </li>
                                     }
                                 }
                             />
                         </ul>
+                        <PageLinks
+                            first_href=format!("/fortresses?mine={is_mine}")
+                            next_href=(!next_page_token.is_empty())
+                                .then(|| {
+                                    format!(
+                                        "/fortresses?mine={is_mine}&page_token={next_page_token}",
+                                    )
+                                })
+                        />
                     }
                 }
             />
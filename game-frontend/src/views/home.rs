@@ -1,5 +1,9 @@
-use crate::i18n::{t, use_i18n};
+use crate::{
+    i18n::{t, use_i18n},
+    views::daily_reward::DailyReward,
+};
 use leptos::prelude::*;
+use leptos_oidc::Authenticated;
 
 #[component]
 pub fn Home() -> impl IntoView {
@@ -14,6 +18,11 @@ pub fn Home() -> impl IntoView {
                 {t!(i18n, project_intro_note)}
             </p>
 
+            <Authenticated>
+                <hr />
+                <DailyReward />
+            </Authenticated>
+
             <hr />
 
             <h3>{t!(i18n, architecture_presentation)}</h3>
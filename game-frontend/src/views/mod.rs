@@ -1,5 +1,7 @@
 pub mod building_detail;
 pub mod building_list;
+pub mod camp_list;
+pub mod daily_reward;
 pub mod fortress_building_list;
 pub mod fortress_detail;
 pub mod fortress_list;
@@ -32,6 +32,8 @@ pub fn NavMenu() -> impl IntoView {
             }>
                 <A href="/fortresses?mine=true">{t!(i18n, my_fortresses)}</A>
                 " | "
+                <A href="/camps">{t!(i18n, camps)}</A>
+                " | "
                 <span>{t!(i18n, connected)}</span>
                 " | "
                 <LogoutLink class="auth-link">{t!(i18n, logout)}</LogoutLink>
@@ -3,8 +3,10 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     tonic_prost_build::configure().compile_protos(
         &[
             "../proto/common/v1/common.proto",
-            "../proto/crud/v1/crud.proto",
+            "../proto/common/v2/common.proto",
+            "../proto/crud/v2/crud.proto",
             "../proto/game/v1/game.proto",
+            "../proto/game/v2/game.proto",
         ],
         &["../proto"],
     )?;
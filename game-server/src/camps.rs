@@ -0,0 +1,121 @@
+//! Barbarian camps, for players without rivals to fight.
+//!
+//! Every player has [`CAMPS_PER_PLAYER`] camps, one per slot, generated from
+//! the building levels of all their fortresses. A successful raid tears a camp
+//! down, and listing the camps replaces it with a new one after
+//! [`CAMP_RESPAWN_SECS`].
+
+use crate::pb::common::v2::{Building, Camp, Costs};
+use rusty::rng::Rng;
+
+pub const CAMPS_PER_PLAYER: i32 = 3;
+pub const CAMP_RESPAWN_SECS: i64 = 3600;
+/// Energy spent by each raid, successful or not.
+pub const RAID_ENERGY_COST: i64 = 10;
+/// Strength of the first camp of a player without any building level.
+const CAMP_BASE_STRENGTH: f64 = 5.0;
+const CAMP_STRENGTH_PER_LEVEL: f64 = 0.5;
+/// Random variation of the camp strength and loot, as a fraction.
+const STRENGTH_SPREAD: f64 = 0.2;
+const LOOT_SPREAD: f64 = 0.5;
+/// Loot per point of strength, before the random variation.
+const LOOT_TABLE: Costs = Costs {
+    gold: 5,
+    food: 3,
+    wood: 3,
+    energy: 1,
+};
+/// Attack of a fortress without any building level.
+const RAID_BASE_ATTACK: i64 = 5;
+const RAID_MIN_CHANCE: f64 = 0.05;
+const RAID_MAX_CHANCE: f64 = 0.95;
+
+/// Whether the slot of `camp` takes a new camp at `now`.
+pub fn needs_respawn(camp: &Camp, now: i64) -> bool {
+    camp.respawn_at.is_some_and(|respawn_at| respawn_at <= now)
+}
+
+pub fn total_levels(buildings: &[Building]) -> i64 {
+    buildings.iter().fold(0, |sum, building| {
+        sum.saturating_add(i64::from(building.level))
+    })
+}
+
+/// Uniform factor in `[1 - spread, 1 + spread)`.
+fn vary(rng: &mut Rng, spread: f64) -> f64 {
+    spread.mul_add(2.0_f64.mul_add(rng.next_f64(), -1.0), 1.0)
+}
+
+/// Strength and loot of a camp in `slot`, for a player whose buildings add up
+/// to `total_levels`. Each slot is stronger than the previous one, and the
+/// loot grows with the strength.
+///
+/// Float to integer casts saturate, so huge levels can't wrap around.
+#[allow(clippy::cast_possible_truncation, clippy::cast_precision_loss)]
+pub fn generate_camp(rng: &mut Rng, slot: i32, total_levels: i64) -> (i32, Costs) {
+    let strength = CAMP_STRENGTH_PER_LEVEL.mul_add(total_levels as f64, CAMP_BASE_STRENGTH)
+        * f64::from(slot.saturating_add(1))
+        * vary(rng, STRENGTH_SPREAD);
+    let mut loot =
+        |per_strength: i64| (strength * per_strength as f64 * vary(rng, LOOT_SPREAD)) as i64;
+    let loot = Costs {
+        gold: loot(LOOT_TABLE.gold),
+        food: loot(LOOT_TABLE.food),
+        wood: loot(LOOT_TABLE.wood),
+        energy: loot(LOOT_TABLE.energy),
+    };
+
+    (strength as i32, loot)
+}
+
+/// Attack against strength, where the attack grows with the building levels
+/// of the raiding fortress.
+#[allow(clippy::cast_precision_loss)]
+pub fn raid_success_chance(attack_levels: i64, strength: i32) -> f64 {
+    let attack = RAID_BASE_ATTACK.saturating_add(attack_levels.max(0)) as f64;
+    (attack / (attack + f64::from(strength.max(0)))).clamp(RAID_MIN_CHANCE, RAID_MAX_CHANCE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn camps_scale_with_slot_and_levels() {
+        let mut rng = Rng::new(7);
+        let (weakest, weakest_loot) = generate_camp(&mut rng, 0, 0);
+        assert!((4..=6).contains(&weakest));
+        assert!(weakest_loot.gold >= 0 && weakest_loot.gold <= 45);
+
+        // (5 + 0.5 * 100) * 3, give or take 20%.
+        let (strongest, strongest_loot) = generate_camp(&mut rng, 2, 100);
+        assert!((132..=198).contains(&strongest));
+        assert!(strongest_loot.gold > weakest_loot.gold);
+
+        let replayed = generate_camp(&mut Rng::new(7), 0, 0);
+        assert_eq!(replayed, (weakest, weakest_loot));
+
+        let (saturated, _) = generate_camp(&mut rng, i32::MAX, i64::MAX);
+        assert_eq!(saturated, i32::MAX);
+    }
+
+    #[test]
+    fn raid_chance_grows_with_levels() {
+        assert!((raid_success_chance(0, 5) - 0.5).abs() < f64::EPSILON);
+        assert!(raid_success_chance(20, 5) > raid_success_chance(10, 5));
+        assert!((raid_success_chance(i64::MAX, 0) - RAID_MAX_CHANCE).abs() < f64::EPSILON);
+        assert!((raid_success_chance(0, i32::MAX) - RAID_MIN_CHANCE).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn only_raided_camps_past_their_time_respawn() {
+        let standing = Camp::default();
+        let raided = Camp {
+            respawn_at: Some(100),
+            ..Camp::default()
+        };
+        assert!(!needs_respawn(&standing, 100));
+        assert!(!needs_respawn(&raided, 99));
+        assert!(needs_respawn(&raided, 100));
+    }
+}
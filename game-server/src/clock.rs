@@ -0,0 +1,100 @@
+//! Time of the game. Debug builds let admins skew it so QA can skip waiting
+//! for respawns and grace periods; crud-server is skewed first so that both
+//! servers keep agreeing on the time.
+
+use crate::{
+    pb::{
+        crud::v2::clock_service_client::ClockServiceClient,
+        game::v2::{
+            GetClockRequest, GetClockResponse, SetClockSkewRequest, SetClockSkewResponse,
+            clock_service_server::ClockService,
+        },
+    },
+    service::get_user,
+};
+use rusty::{
+    clock::{Clock, SkewedClock},
+    error_details::with_error_info,
+};
+use std::{collections::HashMap, sync::Arc};
+use tonic::{Code, Request, Response, Status};
+
+fn admin_required() -> Status {
+    with_error_info(
+        Code::PermissionDenied,
+        "Only admins can change the game clock.".to_owned(),
+        "ADMIN_REQUIRED",
+        HashMap::new(),
+    )
+}
+
+fn clock_locked() -> Status {
+    with_error_info(
+        Code::FailedPrecondition,
+        "The game clock can only be changed in debug builds that enable it.".to_owned(),
+        "CLOCK_LOCKED",
+        HashMap::new(),
+    )
+}
+
+pub struct MyClockService {
+    crud_clock_client: ClockServiceClient<tonic::transport::Channel>,
+    clock: Arc<SkewedClock>,
+}
+
+impl MyClockService {
+    pub const fn new(
+        crud_clock_client: ClockServiceClient<tonic::transport::Channel>,
+        clock: Arc<SkewedClock>,
+    ) -> Self {
+        Self {
+            crud_clock_client,
+            clock,
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl ClockService for MyClockService {
+    async fn get_clock(
+        &self,
+        _request: Request<GetClockRequest>,
+    ) -> Result<Response<GetClockResponse>, Status> {
+        Ok(Response::new(GetClockResponse {
+            now: self.clock.unix_secs(),
+            skew_secs: self.clock.skew(),
+        }))
+    }
+
+    async fn set_clock_skew(
+        &self,
+        request: Request<SetClockSkewRequest>,
+    ) -> Result<Response<SetClockSkewResponse>, Status> {
+        let user = get_user(&request)?;
+        if !user.is_admin() {
+            return Err(admin_required());
+        }
+        if !cfg!(debug_assertions) {
+            return Err(clock_locked());
+        }
+        let skew_secs = request.into_inner().skew_secs;
+        self.crud_clock_client
+            .clone()
+            .set_clock_skew(crate::pb::crud::v2::SetClockSkewRequest { skew_secs })
+            .await
+            // crud-server only serves its clock when told to.
+            .map_err(|status| {
+                if status.code() == Code::Unimplemented {
+                    clock_locked()
+                } else {
+                    status
+                }
+            })?;
+        self.clock.set_skew(skew_secs);
+
+        Ok(Response::new(SetClockSkewResponse {
+            now: self.clock.unix_secs(),
+            skew_secs: self.clock.skew(),
+        }))
+    }
+}
@@ -0,0 +1,364 @@
+//! `game.v1` API, kept for clients that predate 64-bit resources.
+//!
+//! Every call is forwarded to the `game.v2` services, resources that don't
+//! fit in an `int32` are reported as `i32::MAX`. The API is frozen, new RPCs
+//! only go to `game.v2`.
+
+use crate::{
+    pb::{
+        common::{v1 as common_v1, v2 as common_v2},
+        game::{
+            v1::{
+                self, building_service_server::BuildingService,
+                fortress_service_server::FortressService,
+            },
+            v2::{
+                self, building_service_server::BuildingService as _,
+                fortress_service_server::FortressService as _,
+            },
+        },
+    },
+    service::{MyBuildingService, MyFortressService},
+};
+use tonic::{Request, Response, Status};
+
+fn saturate(value: i64) -> i32 {
+    i32::try_from(value).unwrap_or(if value < 0 { i32::MIN } else { i32::MAX })
+}
+
+impl From<common_v2::Building> for common_v1::Building {
+    fn from(value: common_v2::Building) -> Self {
+        Self {
+            id: value.id,
+            name: value.name,
+            level: value.level,
+            fortress_id: value.fortress_id,
+            version: value.version,
+            created_at: value.created_at,
+            updated_at: value.updated_at,
+        }
+    }
+}
+
+impl From<common_v2::Fortress> for common_v1::Fortress {
+    fn from(value: common_v2::Fortress) -> Self {
+        Self {
+            id: value.id,
+            owner_id: value.owner_id,
+            gold: saturate(value.gold),
+            food: saturate(value.food),
+            wood: saturate(value.wood),
+            energy: saturate(value.energy),
+            version: value.version,
+            created_at: value.created_at,
+            updated_at: value.updated_at,
+        }
+    }
+}
+
+impl From<common_v2::FortressSummary> for common_v1::FortressSummary {
+    fn from(value: common_v2::FortressSummary) -> Self {
+        Self {
+            id: value.id,
+            owner_name: value.owner_name,
+            level_tier: value.level_tier,
+            // Both versions number the bands the same way.
+            wealth_band: value.wealth_band,
+        }
+    }
+}
+
+impl From<common_v2::Costs> for common_v1::Costs {
+    fn from(value: common_v2::Costs) -> Self {
+        Self {
+            gold: saturate(value.gold),
+            food: saturate(value.food),
+            wood: saturate(value.wood),
+            energy: saturate(value.energy),
+        }
+    }
+}
+
+pub struct LegacyBuildingService {
+    inner: MyBuildingService,
+}
+
+impl LegacyBuildingService {
+    pub const fn new(inner: MyBuildingService) -> Self {
+        Self { inner }
+    }
+}
+
+#[tonic::async_trait]
+impl BuildingService for LegacyBuildingService {
+    async fn get_building(
+        &self,
+        request: Request<v1::GetBuildingRequest>,
+    ) -> Result<Response<v1::GetBuildingResponse>, Status> {
+        let request = request.map(|r| v2::GetBuildingRequest { id: r.id });
+        let response = self.inner.get_building(request).await?;
+
+        Ok(response.map(|r| v1::GetBuildingResponse {
+            building: r.building.map(Into::into),
+        }))
+    }
+
+    async fn list_buildings(
+        &self,
+        request: Request<v1::ListBuildingsRequest>,
+    ) -> Result<Response<v1::ListBuildingsResponse>, Status> {
+        let request = request.map(|r| v2::ListBuildingsRequest {
+            page_size: r.page_size,
+            page_token: r.page_token,
+            ..v2::ListBuildingsRequest::default()
+        });
+        let response = self.inner.list_buildings(request).await?;
+
+        Ok(response.map(|r| v1::ListBuildingsResponse {
+            buildings: r.buildings.into_iter().map(Into::into).collect(),
+            next_page_token: r.next_page_token,
+        }))
+    }
+
+    async fn list_buildings_by_fortress(
+        &self,
+        request: Request<v1::ListBuildingsByFortressRequest>,
+    ) -> Result<Response<v1::ListBuildingsByFortressResponse>, Status> {
+        let request = request.map(|r| v2::ListBuildingsByFortressRequest {
+            fortress_id: r.fortress_id,
+        });
+        let response = self.inner.list_buildings_by_fortress(request).await?;
+
+        Ok(response.map(|r| v1::ListBuildingsByFortressResponse {
+            buildings: r.buildings.into_iter().map(Into::into).collect(),
+        }))
+    }
+
+    async fn improve_building(
+        &self,
+        request: Request<v1::ImproveBuildingRequest>,
+    ) -> Result<Response<v1::ImproveBuildingResponse>, Status> {
+        let request = request.map(|r| v2::ImproveBuildingRequest {
+            id: r.id,
+            expected_version: r.expected_version,
+        });
+        let response = self.inner.improve_building(request).await?;
+
+        Ok(response.map(|r| v1::ImproveBuildingResponse {
+            fortress: r.fortress.map(Into::into),
+            building: r.building.map(Into::into),
+        }))
+    }
+
+    async fn get_improve_building_costs(
+        &self,
+        request: Request<v1::GetImproveBuildingCostsRequest>,
+    ) -> Result<Response<v1::GetImproveBuildingCostsResponse>, Status> {
+        let request = request.map(|r| v2::GetImproveBuildingCostsRequest { id: r.id });
+        let response = self.inner.get_improve_building_costs(request).await?;
+
+        Ok(response.map(|r| v1::GetImproveBuildingCostsResponse {
+            costs: r.costs.map(Into::into),
+        }))
+    }
+}
+
+pub struct LegacyFortressService {
+    inner: MyFortressService,
+}
+
+impl LegacyFortressService {
+    pub const fn new(inner: MyFortressService) -> Self {
+        Self { inner }
+    }
+}
+
+#[tonic::async_trait]
+impl FortressService for LegacyFortressService {
+    async fn create_fortress(
+        &self,
+        request: Request<v1::CreateFortressRequest>,
+    ) -> Result<Response<v1::CreateFortressResponse>, Status> {
+        let request = request.map(|v1::CreateFortressRequest {}| v2::CreateFortressRequest {});
+        let response = self.inner.create_fortress(request).await?;
+
+        Ok(response.map(|r| v1::CreateFortressResponse {
+            fortress: r.fortress.map(Into::into),
+            buildings: r.buildings.into_iter().map(Into::into).collect(),
+        }))
+    }
+
+    async fn get_fortress(
+        &self,
+        request: Request<v1::GetFortressRequest>,
+    ) -> Result<Response<v1::GetFortressResponse>, Status> {
+        let request = request.map(|r| v2::GetFortressRequest { id: r.id });
+        let response = self.inner.get_fortress(request).await?;
+
+        Ok(response.map(|r| v1::GetFortressResponse {
+            fortress: r.fortress.map(Into::into),
+            summary: r.summary.map(Into::into),
+        }))
+    }
+
+    async fn delete_fortress(
+        &self,
+        request: Request<v1::DeleteFortressRequest>,
+    ) -> Result<Response<v1::DeleteFortressResponse>, Status> {
+        let request = request.map(|r| v2::DeleteFortressRequest { id: r.id });
+        let response = self.inner.delete_fortress(request).await?;
+
+        Ok(response.map(|r| v1::DeleteFortressResponse { success: r.success }))
+    }
+
+    async fn restore_fortress(
+        &self,
+        request: Request<v1::RestoreFortressRequest>,
+    ) -> Result<Response<v1::RestoreFortressResponse>, Status> {
+        let request = request.map(|r| v2::RestoreFortressRequest { id: r.id });
+        let response = self.inner.restore_fortress(request).await?;
+
+        Ok(response.map(|r| v1::RestoreFortressResponse {
+            fortress: r.fortress.map(Into::into),
+        }))
+    }
+
+    async fn list_fortresses(
+        &self,
+        request: Request<v1::ListFortressesRequest>,
+    ) -> Result<Response<v1::ListFortressesResponse>, Status> {
+        let request = request.map(|r| v2::ListFortressesRequest {
+            only_mine: r.only_mine,
+            page_size: r.page_size,
+            page_token: r.page_token,
+            ..v2::ListFortressesRequest::default()
+        });
+        let response = self.inner.list_fortresses(request).await?;
+
+        Ok(response.map(|r| v1::ListFortressesResponse {
+            fortresses: r.fortresses.into_iter().map(Into::into).collect(),
+            next_page_token: r.next_page_token,
+            summaries: r.summaries.into_iter().map(Into::into).collect(),
+        }))
+    }
+
+    async fn get_fortress_gold(
+        &self,
+        request: Request<v1::GetFortressGoldRequest>,
+    ) -> Result<Response<v1::GetFortressGoldResponse>, Status> {
+        let request = request.map(|r| v2::GetFortressGoldRequest { id: r.id });
+        let response = self.inner.get_fortress_gold(request).await?;
+
+        Ok(response.map(|r| v1::GetFortressGoldResponse {
+            gold: saturate(r.gold),
+        }))
+    }
+
+    async fn collect_fortress_gold(
+        &self,
+        request: Request<v1::CollectFortressGoldRequest>,
+    ) -> Result<Response<v1::CollectFortressGoldResponse>, Status> {
+        let request = request.map(|r| v2::CollectFortressGoldRequest {
+            id: r.id,
+            expected_version: r.expected_version,
+        });
+        let response = self.inner.collect_fortress_gold(request).await?;
+
+        Ok(response.map(|r| v1::CollectFortressGoldResponse {
+            fortress: r.fortress.map(Into::into),
+        }))
+    }
+
+    async fn get_fortress_food(
+        &self,
+        request: Request<v1::GetFortressFoodRequest>,
+    ) -> Result<Response<v1::GetFortressFoodResponse>, Status> {
+        let request = request.map(|r| v2::GetFortressFoodRequest { id: r.id });
+        let response = self.inner.get_fortress_food(request).await?;
+
+        Ok(response.map(|r| v1::GetFortressFoodResponse {
+            food: saturate(r.food),
+        }))
+    }
+
+    async fn collect_fortress_food(
+        &self,
+        request: Request<v1::CollectFortressFoodRequest>,
+    ) -> Result<Response<v1::CollectFortressFoodResponse>, Status> {
+        let request = request.map(|r| v2::CollectFortressFoodRequest {
+            id: r.id,
+            expected_version: r.expected_version,
+        });
+        let response = self.inner.collect_fortress_food(request).await?;
+
+        Ok(response.map(|r| v1::CollectFortressFoodResponse {
+            fortress: r.fortress.map(Into::into),
+        }))
+    }
+
+    async fn get_fortress_wood(
+        &self,
+        request: Request<v1::GetFortressWoodRequest>,
+    ) -> Result<Response<v1::GetFortressWoodResponse>, Status> {
+        let request = request.map(|r| v2::GetFortressWoodRequest { id: r.id });
+        let response = self.inner.get_fortress_wood(request).await?;
+
+        Ok(response.map(|r| v1::GetFortressWoodResponse {
+            wood: saturate(r.wood),
+        }))
+    }
+
+    async fn collect_fortress_wood(
+        &self,
+        request: Request<v1::CollectFortressWoodRequest>,
+    ) -> Result<Response<v1::CollectFortressWoodResponse>, Status> {
+        let request = request.map(|r| v2::CollectFortressWoodRequest {
+            id: r.id,
+            expected_version: r.expected_version,
+        });
+        let response = self.inner.collect_fortress_wood(request).await?;
+
+        Ok(response.map(|r| v1::CollectFortressWoodResponse {
+            fortress: r.fortress.map(Into::into),
+        }))
+    }
+
+    async fn get_fortress_energy(
+        &self,
+        request: Request<v1::GetFortressEnergyRequest>,
+    ) -> Result<Response<v1::GetFortressEnergyResponse>, Status> {
+        let request = request.map(|r| v2::GetFortressEnergyRequest { id: r.id });
+        let response = self.inner.get_fortress_energy(request).await?;
+
+        Ok(response.map(|r| v1::GetFortressEnergyResponse {
+            energy: saturate(r.energy),
+        }))
+    }
+
+    async fn collect_fortress_energy(
+        &self,
+        request: Request<v1::CollectFortressEnergyRequest>,
+    ) -> Result<Response<v1::CollectFortressEnergyResponse>, Status> {
+        let request = request.map(|r| v2::CollectFortressEnergyRequest {
+            id: r.id,
+            expected_version: r.expected_version,
+        });
+        let response = self.inner.collect_fortress_energy(request).await?;
+
+        Ok(response.map(|r| v1::CollectFortressEnergyResponse {
+            fortress: r.fortress.map(Into::into),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saturate_clamps_to_i32() {
+        assert_eq!(saturate(42), 42);
+        assert_eq!(saturate(i64::MAX), i32::MAX);
+        assert_eq!(saturate(i64::MIN), i32::MIN);
+    }
+}
@@ -1,22 +1,33 @@
 pub mod auth;
+mod camps;
+pub mod clock;
+pub mod legacy;
 pub mod service;
+mod visibility;
 
 use crate::{
     auth::AuthInterceptor,
+    clock::MyClockService,
+    legacy::{LegacyBuildingService, LegacyFortressService},
     pb::{
-        crud::v1::{
+        crud::v2::{
             building_service_client::BuildingServiceClient,
+            clock_service_client::ClockServiceClient,
             fortress_service_client::FortressServiceClient,
         },
-        game::v1::{
-            building_service_server::BuildingServiceServer,
-            fortress_service_server::FortressServiceServer,
-        },
+        game::{v1, v2},
     },
     service::{MyBuildingService, MyFortressService},
 };
 use jsonwebtoken::jwk::JwkSet;
-use std::sync::Arc;
+use rusty::{
+    clock::{SkewedClock, SystemClock},
+    rng::Rng,
+};
+use std::{
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
 use tokio::signal::unix::{SignalKind, signal};
 use tonic::transport::Server;
 use tonic_web::GrpcWebLayer;
@@ -29,16 +40,22 @@ pub mod pb {
         pub mod v1 {
             tonic::include_proto!("common.v1");
         }
+        pub mod v2 {
+            tonic::include_proto!("common.v2");
+        }
     }
     pub mod crud {
-        pub mod v1 {
-            tonic::include_proto!("crud.v1");
+        pub mod v2 {
+            tonic::include_proto!("crud.v2");
         }
     }
     pub mod game {
         pub mod v1 {
             tonic::include_proto!("game.v1");
         }
+        pub mod v2 {
+            tonic::include_proto!("game.v2");
+        }
     }
 }
 
@@ -85,11 +102,38 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         issuer: format!("{issuer_url}/auth/v1/"),
     };
 
+    // A fixed seed replays the same scouting outcomes.
+    let rng_seed = match std::env::var("RNG_SEED") {
+        Ok(seed) => seed.parse().map_err(|e| format!("RNG_SEED {e}"))?,
+        Err(_) => SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs() ^ u64::from(since.subsec_nanos())),
+    };
+    let rng = Arc::new(Mutex::new(Rng::new(rng_seed)));
+    let clock = Arc::new(SkewedClock::new(SystemClock));
+
     let crud_building_client = BuildingServiceClient::connect(crud_server_url.clone()).await?;
-    let crud_fortress_client = FortressServiceClient::connect(crud_server_url).await?;
+    let crud_fortress_client = FortressServiceClient::connect(crud_server_url.clone()).await?;
+    let crud_clock_client = ClockServiceClient::connect(crud_server_url).await?;
     let building_service =
         MyBuildingService::new(crud_building_client.clone(), crud_fortress_client.clone());
-    let fortress_service = MyFortressService::new(crud_building_client, crud_fortress_client);
+    let fortress_service = MyFortressService::new(
+        crud_fortress_client.clone(),
+        crud_building_client.clone(),
+        Arc::clone(&rng),
+        clock.clone(),
+    );
+    let legacy_building_service = LegacyBuildingService::new(MyBuildingService::new(
+        crud_building_client.clone(),
+        crud_fortress_client.clone(),
+    ));
+    let legacy_fortress_service = LegacyFortressService::new(MyFortressService::new(
+        crud_fortress_client,
+        crud_building_client,
+        rng,
+        clock.clone(),
+    ));
+    let clock_service = MyClockService::new(crud_clock_client, clock);
 
     info!("Listening on {addr}");
 
@@ -97,14 +141,36 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .accept_http1(true)
         .layer(CorsLayer::permissive())
         .layer(GrpcWebLayer::new())
-        .add_service(BuildingServiceServer::with_interceptor(
-            building_service,
-            auth_interceptor.clone(),
-        ))
-        .add_service(FortressServiceServer::with_interceptor(
-            fortress_service,
-            auth_interceptor,
-        ))
+        .add_service(
+            v2::building_service_server::BuildingServiceServer::with_interceptor(
+                building_service,
+                auth_interceptor.clone(),
+            ),
+        )
+        .add_service(
+            v2::fortress_service_server::FortressServiceServer::with_interceptor(
+                fortress_service,
+                auth_interceptor.clone(),
+            ),
+        )
+        .add_service(
+            v2::clock_service_server::ClockServiceServer::with_interceptor(
+                clock_service,
+                auth_interceptor.clone(),
+            ),
+        )
+        .add_service(
+            v1::building_service_server::BuildingServiceServer::with_interceptor(
+                legacy_building_service,
+                auth_interceptor.clone(),
+            ),
+        )
+        .add_service(
+            v1::fortress_service_server::FortressServiceServer::with_interceptor(
+                legacy_fortress_service,
+                auth_interceptor,
+            ),
+        )
         .serve_with_shutdown(addr, shutdown_signal())
         .await?;
 
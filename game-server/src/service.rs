@@ -1,67 +1,235 @@
 use crate::{
     auth::Claims,
+    camps::{
+        CAMP_RESPAWN_SECS, CAMPS_PER_PLAYER, RAID_ENERGY_COST, generate_camp, needs_respawn,
+        raid_success_chance, total_levels,
+    },
     pb::{
-        common::v1::{Costs, NewBuilding, NewFortress},
-        crud::v1::{
-            CollectFortressResourceRequest, ResourceKind, UpgradeBuildingAtomicRequest,
-            building_service_client::BuildingServiceClient,
+        common::v2::{Building, Costs, Fortress, FortressSortField, FortressSummary, NewFortress},
+        crud::v2::{
+            ClaimDailyRewardRequest as CrudClaimDailyRewardRequest, CollectFortressResourceRequest,
+            CreateFortressWithBuildingsRequest, DailyReward, ResourceKind, SpawnCampRequest,
+            UpgradeBuildingAtomicRequest, building_service_client::BuildingServiceClient,
             fortress_service_client::FortressServiceClient,
         },
-        game::v1::{
+        game::v2::{
+            BuildingOverview, ClaimDailyRewardRequest, ClaimDailyRewardResponse,
             CollectFortressEnergyRequest, CollectFortressEnergyResponse,
             CollectFortressFoodRequest, CollectFortressFoodResponse, CollectFortressGoldRequest,
             CollectFortressGoldResponse, CollectFortressWoodRequest, CollectFortressWoodResponse,
             CreateFortressRequest, CreateFortressResponse, DeleteFortressRequest,
-            DeleteFortressResponse, GetBuildingRequest, GetBuildingResponse,
+            DeleteFortressResponse, GetBuildingRequest, GetBuildingResponse, GetBuildingsRequest,
+            GetBuildingsResponse, GetDailyRewardRequest, GetDailyRewardResponse,
             GetFortressEnergyRequest, GetFortressEnergyResponse, GetFortressFoodRequest,
             GetFortressFoodResponse, GetFortressGoldRequest, GetFortressGoldResponse,
-            GetFortressRequest, GetFortressResponse, GetFortressWoodRequest,
-            GetFortressWoodResponse, GetImproveBuildingCostsRequest,
-            GetImproveBuildingCostsResponse, ImproveBuildingRequest, ImproveBuildingResponse,
-            ListBuildingsByFortressRequest, ListBuildingsByFortressResponse, ListBuildingsRequest,
-            ListBuildingsResponse, ListFortressesRequest, ListFortressesResponse,
-            building_service_server::BuildingService, fortress_service_server::FortressService,
+            GetFortressHistoryRequest, GetFortressHistoryResponse, GetFortressOverviewRequest,
+            GetFortressOverviewResponse, GetFortressRequest, GetFortressResponse,
+            GetFortressWoodRequest, GetFortressWoodResponse, GetFortressesRequest,
+            GetFortressesResponse, GetImproveBuildingCostsRequest, GetImproveBuildingCostsResponse,
+            ImproveBuildingRequest, ImproveBuildingResponse, ListBuildingsByFortressRequest,
+            ListBuildingsByFortressResponse, ListBuildingsRequest, ListBuildingsResponse,
+            ListCampsRequest, ListCampsResponse, ListFortressesRequest, ListFortressesResponse,
+            ListScoutReportsRequest, ListScoutReportsResponse, RaidCampRequest, RaidCampResponse,
+            RestoreFortressRequest, RestoreFortressResponse, ScoutFortressRequest,
+            ScoutFortressResponse, building_service_server::BuildingService,
+            fortress_service_server::FortressService,
         },
     },
+    visibility::{require_full_view, sees_everything, summarize, viewer},
 };
-use tonic::{Request, Response, Status};
-
-const FORTRESSES_PER_USER_LIMIT: usize = 5;
-const MAX_BUILDING_LEVEL: i32 = 20;
-const BASE_COST: i32 = 10;
-const GOLD_BONUS_BUILDING: &str = "bank";
-const FOOD_BONUS_BUILDING: &str = "farm";
-const WOOD_BONUS_BUILDING: &str = "sawmill";
-const ENERGY_BONUS_BUILDING: &str = "sanctuary";
-
-fn upgrade_cost(level: i32, base: i32, factor: f64) -> f64 {
-    let level = level.max(1);
-    f64::from(base) * factor.powf(f64::from(level - 1))
-}
+use rusty::{
+    clock::Clock,
+    error_details::with_error_info,
+    rng::Rng,
+    rules::{
+        BONUS_BUILDINGS, DAILY_REWARDS, ENERGY_BONUS_BUILDING, Economy, FOOD_BONUS_BUILDING,
+        FORTRESSES_PER_PLAYER, GOLD_BONUS_BUILDING, SECS_PER_DAY, WOOD_BONUS_BUILDING,
+        daily_reward, next_streak, utc_day,
+    },
+};
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::{Arc, Mutex, PoisonError},
+};
+use tonic::{Code, Request, Response, Status};
+
+const ECONOMY: Economy = Economy::LIVE;
+const MAX_BUILDING_LEVEL: i32 = ECONOMY.max_building_level;
+/// Limits of the crud-server batch reads and lists.
+const CRUD_BATCH_SIZE: usize = 100;
+const CRUD_PAGE_SIZE: i32 = 200;
+/// Energy spent by each scouting attempt, successful or not.
+const SCOUT_ENERGY_COST: i64 = 10;
+/// The odds of scouting depend on this building of both fortresses.
+const SCOUT_BUILDING: &str = ENERGY_BONUS_BUILDING;
+const SCOUT_MIN_CHANCE: f64 = 0.05;
+const SCOUT_MAX_CHANCE: f64 = 0.95;
+const SCOUT_CHANCE_PER_LEVEL: f64 = 0.05;
 
-fn optimize_factor(level_max: i32, base: i32, cost_max: i32) -> f64 {
-    (f64::from(cost_max) / f64::from(base)).powf(1.0 / (f64::from(level_max) - 1.0))
+/// Highest level among the buildings named `name`, 0 without any.
+fn building_level(buildings: &[Building], name: &str) -> i32 {
+    buildings
+        .iter()
+        .filter(|building| building.name == name)
+        .map(|building| building.level)
+        .max()
+        .unwrap_or(0)
 }
 
-#[allow(clippy::cast_possible_truncation)]
-fn get_costs(level: i32, level_max: i32) -> Costs {
-    let factor = optimize_factor(level_max, BASE_COST, i32::MAX);
-    let cost = upgrade_cost(level, BASE_COST, factor) as i32;
+/// Even odds between equal levels, [`SCOUT_CHANCE_PER_LEVEL`] more or less per
+/// level of difference. The chance of being detected is the complement.
+fn scout_success_chance(spy_level: i32, target_level: i32) -> f64 {
+    let difference = f64::from(spy_level) - f64::from(target_level);
+    SCOUT_CHANCE_PER_LEVEL
+        .mul_add(difference, 0.5)
+        .clamp(SCOUT_MIN_CHANCE, SCOUT_MAX_CHANCE)
+}
 
+const fn to_pb_costs(costs: rusty::Costs) -> Costs {
     Costs {
-        gold: cost / 2,
-        food: cost,
-        wood: cost / 5,
-        energy: cost / 10,
+        gold: costs.gold,
+        food: costs.food,
+        wood: costs.wood,
+        energy: costs.energy,
+    }
+}
+
+fn get_costs(level: i32) -> Costs {
+    to_pb_costs(ECONOMY.upgrade_costs(level))
+}
+
+/// What each collect adds: the base production plus the levels of the bonus
+/// buildings of that resource.
+fn get_production(buildings: &[Building]) -> Costs {
+    to_pb_costs(
+        ECONOMY.production(
+            buildings
+                .iter()
+                .map(|building| (building.name.as_str(), building.level)),
+        ),
+    )
+}
+
+/// The current streak of the player, whether `today` is still to be claimed
+/// and the streak the next claim reaches.
+const fn daily_reward_status(daily_reward: Option<&DailyReward>, today: i64) -> (i32, bool, i32) {
+    let Some(daily_reward) = daily_reward else {
+        return (0, true, 1);
+    };
+    let streak = daily_reward.streak;
+    match next_streak(streak, utc_day(daily_reward.last_claimed_at), today) {
+        None => (streak, false, streak.saturating_add(1)),
+        // A day was missed.
+        Some(1) => (0, true, 1),
+        Some(next) => (streak, true, next),
+    }
+}
+
+pub(crate) fn not_owner() -> Status {
+    with_error_info(
+        Code::PermissionDenied,
+        "Action refused: This fortress belongs to another player.".to_owned(),
+        "NOT_OWNER",
+        HashMap::new(),
+    )
+}
+
+pub(crate) fn login_required() -> Status {
+    with_error_info(
+        Code::Unauthenticated,
+        "You must be logged in.".to_owned(),
+        "LOGIN_REQUIRED",
+        HashMap::new(),
+    )
+}
+
+pub(crate) fn get_user<T>(request: &Request<T>) -> Result<Claims, Status> {
+    viewer(request).ok_or_else(login_required)
+}
+
+/// The live buildings of `fortress_ids`, grouped by fortress.
+async fn buildings_by_fortress(
+    crud_building_client: &BuildingServiceClient<tonic::transport::Channel>,
+    fortress_ids: &[i32],
+) -> Result<HashMap<i32, Vec<Building>>, Status> {
+    let mut grouped: HashMap<i32, Vec<Building>> = HashMap::new();
+    for chunk in fortress_ids.chunks(CRUD_BATCH_SIZE) {
+        let mut page_token = String::new();
+        loop {
+            let page = crud_building_client
+                .clone()
+                .list_buildings(crate::pb::crud::v2::ListBuildingsRequest {
+                    page_size: CRUD_PAGE_SIZE,
+                    page_token,
+                    fortress_ids: chunk.to_vec(),
+                    ..crate::pb::crud::v2::ListBuildingsRequest::default()
+                })
+                .await?
+                .into_inner();
+            for building in page.buildings {
+                grouped
+                    .entry(building.fortress_id)
+                    .or_default()
+                    .push(building);
+            }
+            if page.next_page_token.is_empty() {
+                break;
+            }
+            page_token = page.next_page_token;
+        }
     }
+
+    Ok(grouped)
 }
 
-fn get_user<T>(request: &Request<T>) -> Result<Claims, Status> {
-    request
-        .extensions()
-        .get::<Claims>()
-        .cloned()
-        .ok_or_else(|| Status::unauthenticated("You must be logged in."))
+/// Sum of the building levels of all the player's fortresses.
+async fn player_levels(
+    crud_building_client: &BuildingServiceClient<tonic::transport::Channel>,
+    owner_id: &str,
+) -> Result<i64, Status> {
+    let mut levels = 0_i64;
+    let mut page_token = String::new();
+    loop {
+        let page = crud_building_client
+            .clone()
+            .list_buildings(crate::pb::crud::v2::ListBuildingsRequest {
+                page_size: CRUD_PAGE_SIZE,
+                page_token,
+                owner_id: Some(owner_id.to_owned()),
+                ..crate::pb::crud::v2::ListBuildingsRequest::default()
+            })
+            .await?
+            .into_inner();
+        levels = levels.saturating_add(total_levels(&page.buildings));
+        if page.next_page_token.is_empty() {
+            return Ok(levels);
+        }
+        page_token = page.next_page_token;
+    }
+}
+
+/// Summaries of `fortresses`, in the same order, and the fortresses `viewer`
+/// sees everything of.
+async fn split_by_visibility(
+    crud_building_client: &BuildingServiceClient<tonic::transport::Channel>,
+    fortresses: Vec<Fortress>,
+    viewer: Option<&Claims>,
+) -> Result<(Vec<Fortress>, Vec<FortressSummary>), Status> {
+    let ids: Vec<i32> = fortresses.iter().map(|fortress| fortress.id).collect();
+    let buildings = buildings_by_fortress(crud_building_client, &ids).await?;
+    let summaries = fortresses
+        .iter()
+        .map(|fortress| {
+            let buildings = buildings.get(&fortress.id).map_or(&[][..], Vec::as_slice);
+            summarize(fortress, buildings)
+        })
+        .collect();
+    let visible = fortresses
+        .into_iter()
+        .filter(|fortress| sees_everything(viewer, fortress))
+        .collect();
+
+    Ok((visible, summaries))
 }
 
 pub struct MyBuildingService {
@@ -80,34 +248,27 @@ impl MyBuildingService {
         }
     }
 
-    async fn verify_building_ownership(
+    /// The building, if `viewer` sees everything of its fortress.
+    async fn visible_building(
         &self,
         building_id: i32,
-        user: &Claims,
-    ) -> Result<crate::pb::common::v1::Building, Status> {
-        let building = self
+        viewer: Option<&Claims>,
+    ) -> Result<Building, Status> {
+        let found = self
             .crud_building_client
             .clone()
-            .get_building(crate::pb::crud::v1::GetBuildingRequest { id: building_id })
+            .get_building_with_fortress(crate::pb::crud::v2::GetBuildingWithFortressRequest {
+                id: building_id,
+            })
             .await?
-            .into_inner()
+            .into_inner();
+        let building = found
             .building
             .ok_or_else(|| Status::not_found("Building not found"))?;
-        let fortress = self
-            .crud_fortress_client
-            .clone()
-            .get_fortress(crate::pb::crud::v1::GetFortressRequest {
-                id: building.fortress_id,
-            })
-            .await?
-            .into_inner()
+        let fortress = found
             .fortress
             .ok_or_else(|| Status::not_found("Fortress not found"))?;
-        if fortress.owner_id != user.sub && !user.is_admin() {
-            return Err(Status::permission_denied(
-                "Action refused: This fortress belongs to another player.",
-            ));
-        }
+        require_full_view(viewer, &fortress)?;
         Ok(building)
     }
 }
@@ -118,32 +279,80 @@ impl BuildingService for MyBuildingService {
         &self,
         request: Request<GetBuildingRequest>,
     ) -> Result<Response<GetBuildingResponse>, Status> {
-        let crud_request = Request::new(crate::pb::crud::v1::GetBuildingRequest {
-            id: request.into_inner().id,
-        });
+        let viewer = viewer(&request);
         let building = self
-            .crud_building_client
-            .clone()
-            .get_building(crud_request)
+            .visible_building(request.into_inner().id, viewer.as_ref())
             .await?;
         let response = GetBuildingResponse {
-            building: building.into_inner().building,
+            building: Some(building),
         };
         Ok(Response::new(response))
     }
 
+    async fn get_buildings(
+        &self,
+        request: Request<GetBuildingsRequest>,
+    ) -> Result<Response<GetBuildingsResponse>, Status> {
+        let viewer = viewer(&request);
+        let crud_request = Request::new(crate::pb::crud::v2::GetBuildingsRequest {
+            ids: request.into_inner().ids,
+        });
+        let mut buildings = self
+            .crud_building_client
+            .clone()
+            .get_buildings(crud_request)
+            .await?
+            .into_inner()
+            .buildings;
+        if !viewer.as_ref().is_some_and(Claims::is_admin) {
+            let fortress_ids: BTreeSet<i32> = buildings
+                .iter()
+                .map(|building| building.fortress_id)
+                .collect();
+            let visible: BTreeSet<i32> = self
+                .crud_fortress_client
+                .clone()
+                .get_fortresses(crate::pb::crud::v2::GetFortressesRequest {
+                    ids: fortress_ids.into_iter().collect(),
+                })
+                .await?
+                .into_inner()
+                .fortresses
+                .iter()
+                .filter(|fortress| sees_everything(viewer.as_ref(), fortress))
+                .map(|fortress| fortress.id)
+                .collect();
+            buildings.retain(|building| visible.contains(&building.fortress_id));
+        }
+        let response = GetBuildingsResponse { buildings };
+        Ok(Response::new(response))
+    }
+
     async fn list_buildings(
         &self,
-        _request: Request<ListBuildingsRequest>,
+        request: Request<ListBuildingsRequest>,
     ) -> Result<Response<ListBuildingsResponse>, Status> {
-        let crud_request = Request::new(crate::pb::crud::v1::ListBuildingsRequest {});
+        let user = get_user(&request)?;
+        let request = request.into_inner();
+        let crud_request = Request::new(crate::pb::crud::v2::ListBuildingsRequest {
+            page_size: request.page_size,
+            page_token: request.page_token,
+            name: request.name,
+            min_level: request.min_level,
+            sort_field: request.sort_field,
+            sort_direction: request.sort_direction,
+            owner_id: (!user.is_admin()).then_some(user.sub),
+            fortress_ids: Vec::new(),
+        });
         let buildings = self
             .crud_building_client
             .clone()
             .list_buildings(crud_request)
-            .await?;
+            .await?
+            .into_inner();
         let buildings = ListBuildingsResponse {
-            buildings: buildings.into_inner().buildings,
+            buildings: buildings.buildings,
+            next_page_token: buildings.next_page_token,
         };
         Ok(Response::new(buildings))
     }
@@ -152,16 +361,22 @@ impl BuildingService for MyBuildingService {
         &self,
         request: Request<ListBuildingsByFortressRequest>,
     ) -> Result<Response<ListBuildingsByFortressResponse>, Status> {
-        let crud_request = Request::new(crate::pb::crud::v1::ListBuildingsByFortressRequest {
-            fortress_id: request.into_inner().fortress_id,
+        let viewer = viewer(&request);
+        let crud_request = Request::new(crate::pb::crud::v2::GetFortressWithBuildingsRequest {
+            id: request.into_inner().fortress_id,
         });
-        let buildings = self
-            .crud_building_client
+        let found = self
+            .crud_fortress_client
             .clone()
-            .list_buildings_by_fortress(crud_request)
-            .await?;
+            .get_fortress_with_buildings(crud_request)
+            .await?
+            .into_inner();
+        let fortress = found
+            .fortress
+            .ok_or_else(|| Status::not_found("Fortress not found"))?;
+        require_full_view(viewer.as_ref(), &fortress)?;
         let buildings = ListBuildingsByFortressResponse {
-            buildings: buildings.into_inner().buildings,
+            buildings: found.buildings,
         };
         Ok(Response::new(buildings))
     }
@@ -171,14 +386,18 @@ impl BuildingService for MyBuildingService {
         request: Request<ImproveBuildingRequest>,
     ) -> Result<Response<ImproveBuildingResponse>, Status> {
         let user = get_user(&request)?;
-        let building_id = request.into_inner().id;
-        let building = self.verify_building_ownership(building_id, &user).await?;
-        let costs = get_costs(building.level, MAX_BUILDING_LEVEL);
+        let ImproveBuildingRequest {
+            id: building_id,
+            expected_version,
+        } = request.into_inner();
+        let building = self.visible_building(building_id, Some(&user)).await?;
+        let costs = get_costs(building.level);
         let upgrade_req = UpgradeBuildingAtomicRequest {
             building_id,
             costs: Some(costs),
             expected_building_level: Some(building.level),
             max_building_level: MAX_BUILDING_LEVEL,
+            expected_building_version: expected_version,
         };
         let upgraded = self
             .crud_building_client
@@ -197,58 +416,74 @@ impl BuildingService for MyBuildingService {
         &self,
         request: Request<GetImproveBuildingCostsRequest>,
     ) -> Result<Response<GetImproveBuildingCostsResponse>, Status> {
-        let crud_request = Request::new(crate::pb::crud::v1::GetBuildingRequest {
-            id: request.into_inner().id,
-        });
+        let viewer = viewer(&request);
         let building_level = self
-            .crud_building_client
-            .clone()
-            .get_building(crud_request)
+            .visible_building(request.into_inner().id, viewer.as_ref())
             .await?
-            .into_inner()
-            .building
-            .ok_or_else(|| Status::not_found("building not found"))?
             .level;
-        let costs = get_costs(building_level, MAX_BUILDING_LEVEL);
+        let costs = get_costs(building_level);
         let message = GetImproveBuildingCostsResponse { costs: Some(costs) };
         Ok(Response::new(message))
     }
 }
 
 pub struct MyFortressService {
-    crud_building_client: BuildingServiceClient<tonic::transport::Channel>,
     crud_fortress_client: FortressServiceClient<tonic::transport::Channel>,
+    crud_building_client: BuildingServiceClient<tonic::transport::Channel>,
+    /// Rolls the scouting outcomes.
+    rng: Arc<Mutex<Rng>>,
+    /// Decides when raided camps respawn.
+    clock: Arc<dyn Clock>,
 }
 
 impl MyFortressService {
     pub const fn new(
-        crud_building_client: BuildingServiceClient<tonic::transport::Channel>,
         crud_fortress_client: FortressServiceClient<tonic::transport::Channel>,
+        crud_building_client: BuildingServiceClient<tonic::transport::Channel>,
+        rng: Arc<Mutex<Rng>>,
+        clock: Arc<dyn Clock>,
     ) -> Self {
         Self {
-            crud_building_client,
             crud_fortress_client,
+            crud_building_client,
+            rng,
+            clock,
         }
     }
 
-    async fn verify_fortress_ownership(
+    async fn fortress_with_buildings(
         &self,
         fortress_id: i32,
-        user: &Claims,
-    ) -> Result<crate::pb::common::v1::Fortress, Status> {
+    ) -> Result<(Fortress, Vec<Building>), Status> {
+        let found = self
+            .crud_fortress_client
+            .clone()
+            .get_fortress_with_buildings(crate::pb::crud::v2::GetFortressWithBuildingsRequest {
+                id: fortress_id,
+            })
+            .await?
+            .into_inner();
+        let fortress = found
+            .fortress
+            .ok_or_else(|| Status::not_found("Fortress not found"))?;
+        Ok((fortress, found.buildings))
+    }
+
+    /// The fortress, if `viewer` sees everything of it.
+    async fn visible_fortress(
+        &self,
+        fortress_id: i32,
+        viewer: Option<&Claims>,
+    ) -> Result<Fortress, Status> {
         let fortress = self
             .crud_fortress_client
             .clone()
-            .get_fortress(crate::pb::crud::v1::GetFortressRequest { id: fortress_id })
+            .get_fortress(crate::pb::crud::v2::GetFortressRequest { id: fortress_id })
             .await?
             .into_inner()
             .fortress
             .ok_or_else(|| Status::not_found("Fortress not found"))?;
-        if fortress.owner_id != user.sub && !user.is_admin() {
-            return Err(Status::permission_denied(
-                "Action refused: This fortress belongs to another player.",
-            ));
-        }
+        require_full_view(viewer, &fortress)?;
         Ok(fortress)
     }
 }
@@ -261,84 +496,43 @@ impl FortressService for MyFortressService {
     ) -> Result<Response<CreateFortressResponse>, Status> {
         let user = get_user(&request)?;
 
-        if !user.is_admin() {
-            let list_fortresses_request = crate::pb::crud::v1::ListFortressesRequest {
-                owner_id: Some(user.sub.clone()),
-            };
-            let fortresses = self
-                .crud_fortress_client
-                .clone()
-                .list_fortresses(list_fortresses_request)
-                .await?
-                .into_inner()
-                .fortresses;
-            let count = fortresses.len();
-            if count >= FORTRESSES_PER_USER_LIMIT {
-                return Err(Status::resource_exhausted(format!(
-                    "You have reached the limit of {FORTRESSES_PER_USER_LIMIT} fortresses."
-                )));
-            }
-        }
-
         tracing::info!("Player {} creates a fortress", user.sub);
 
-        let create_fortress_request = crate::pb::crud::v1::CreateFortressRequest {
+        let create_fortress_request = CreateFortressWithBuildingsRequest {
             fortress: Some(NewFortress {
-                owner_id: user.sub,
+                owner_id: user.sub.clone(),
+                owner_name: user.preferred_username.clone().unwrap_or_default(),
                 gold: 0,
                 food: 0,
                 wood: 0,
                 energy: 0,
             }),
+            building_names: BONUS_BUILDINGS.map(ToOwned::to_owned).to_vec(),
+            max_fortresses_per_owner: (!user.is_admin()).then_some(FORTRESSES_PER_PLAYER),
         };
-        let fortress = self
+        let created = self
             .crud_fortress_client
             .clone()
-            .create_fortress(create_fortress_request)
-            .await?
-            .into_inner()
-            .fortress
-            .ok_or_else(|| Status::not_found("fortress not found"))?;
-        let new_buildings = [
-            NewBuilding {
-                name: GOLD_BONUS_BUILDING.to_owned(),
-                level: 0,
-                fortress_id: fortress.id,
-            },
-            NewBuilding {
-                name: FOOD_BONUS_BUILDING.to_owned(),
-                level: 0,
-                fortress_id: fortress.id,
-            },
-            NewBuilding {
-                name: WOOD_BONUS_BUILDING.to_owned(),
-                level: 0,
-                fortress_id: fortress.id,
-            },
-            NewBuilding {
-                name: ENERGY_BONUS_BUILDING.to_owned(),
-                level: 0,
-                fortress_id: fortress.id,
-            },
-        ];
-        let mut buildings = Vec::with_capacity(new_buildings.len());
-        for new_building in new_buildings {
-            let create_building_request = crate::pb::crud::v1::CreateBuildingRequest {
-                building: Some(new_building),
-            };
-            let building = self
-                .crud_building_client
-                .clone()
-                .create_building(create_building_request)
-                .await?
-                .into_inner()
-                .building
-                .ok_or_else(|| Status::not_found("building not found"))?;
-            buildings.push(building);
-        }
+            .create_fortress_with_buildings(create_fortress_request)
+            .await
+            .map_err(|status| {
+                if status.code() == Code::ResourceExhausted {
+                    with_error_info(
+                        Code::ResourceExhausted,
+                        format!(
+                            "You have reached the limit of {FORTRESSES_PER_PLAYER} fortresses."
+                        ),
+                        "FORTRESS_LIMIT_REACHED",
+                        HashMap::from([("limit".to_owned(), FORTRESSES_PER_PLAYER.to_string())]),
+                    )
+                } else {
+                    status
+                }
+            })?
+            .into_inner();
         let message = CreateFortressResponse {
-            fortress: Some(fortress),
-            buildings,
+            fortress: created.fortress,
+            buildings: created.buildings,
         };
 
         Ok(Response::new(message))
@@ -348,18 +542,94 @@ impl FortressService for MyFortressService {
         &self,
         request: Request<GetFortressRequest>,
     ) -> Result<Response<GetFortressResponse>, Status> {
-        let get_fortress_request = crate::pb::crud::v1::GetFortressRequest {
+        let viewer = viewer(&request);
+        let get_fortress_request = crate::pb::crud::v2::GetFortressWithBuildingsRequest {
             id: request.into_inner().id,
         };
-        let fortress = self
+        let found = self
             .crud_fortress_client
             .clone()
-            .get_fortress(get_fortress_request)
+            .get_fortress_with_buildings(get_fortress_request)
+            .await?
+            .into_inner();
+        let fortress = found
+            .fortress
+            .ok_or_else(|| Status::not_found("Fortress not found"))?;
+
+        let message = GetFortressResponse {
+            summary: Some(summarize(&fortress, &found.buildings)),
+            fortress: sees_everything(viewer.as_ref(), &fortress).then_some(fortress),
+        };
+
+        Ok(Response::new(message))
+    }
+
+    async fn get_fortresses(
+        &self,
+        request: Request<GetFortressesRequest>,
+    ) -> Result<Response<GetFortressesResponse>, Status> {
+        let viewer = viewer(&request);
+        let get_fortresses_request = crate::pb::crud::v2::GetFortressesRequest {
+            ids: request.into_inner().ids,
+        };
+        let fortresses = self
+            .crud_fortress_client
+            .clone()
+            .get_fortresses(get_fortresses_request)
             .await?
             .into_inner()
-            .fortress;
+            .fortresses;
+        let (fortresses, summaries) =
+            split_by_visibility(&self.crud_building_client, fortresses, viewer.as_ref()).await?;
 
-        let message = GetFortressResponse { fortress };
+        let message = GetFortressesResponse {
+            fortresses,
+            summaries,
+        };
+
+        Ok(Response::new(message))
+    }
+
+    async fn get_fortress_overview(
+        &self,
+        request: Request<GetFortressOverviewRequest>,
+    ) -> Result<Response<GetFortressOverviewResponse>, Status> {
+        let viewer = viewer(&request);
+        let get_fortress_request = crate::pb::crud::v2::GetFortressWithBuildingsRequest {
+            id: request.into_inner().id,
+        };
+        let found = self
+            .crud_fortress_client
+            .clone()
+            .get_fortress_with_buildings(get_fortress_request)
+            .await?
+            .into_inner();
+        let fortress = found
+            .fortress
+            .ok_or_else(|| Status::not_found("Fortress not found"))?;
+        let summary = Some(summarize(&fortress, &found.buildings));
+        if !sees_everything(viewer.as_ref(), &fortress) {
+            return Ok(Response::new(GetFortressOverviewResponse {
+                summary,
+                ..GetFortressOverviewResponse::default()
+            }));
+        }
+        let production = get_production(&found.buildings);
+        let buildings = found
+            .buildings
+            .into_iter()
+            .map(|building| BuildingOverview {
+                upgrade_costs: ECONOMY.next_upgrade_costs(building.level).map(to_pb_costs),
+                building: Some(building),
+            })
+            .collect();
+
+        let message = GetFortressOverviewResponse {
+            fortress: Some(fortress),
+            buildings,
+            production: Some(production),
+            summary,
+        };
 
         Ok(Response::new(message))
     }
@@ -370,9 +640,9 @@ impl FortressService for MyFortressService {
     ) -> Result<Response<DeleteFortressResponse>, Status> {
         let user = get_user(&request)?;
         let fortress_id = request.get_ref().id;
-        let _fortress = self.verify_fortress_ownership(fortress_id, &user).await?;
+        let _fortress = self.visible_fortress(fortress_id, Some(&user)).await?;
         let delete_fortress_request =
-            crate::pb::crud::v1::DeleteFortressRequest { id: fortress_id };
+            crate::pb::crud::v2::DeleteFortressRequest { id: fortress_id };
         let success = self
             .crud_fortress_client
             .clone()
@@ -386,32 +656,352 @@ impl FortressService for MyFortressService {
         Ok(Response::new(message))
     }
 
+    async fn restore_fortress(
+        &self,
+        request: Request<RestoreFortressRequest>,
+    ) -> Result<Response<RestoreFortressResponse>, Status> {
+        let user = get_user(&request)?;
+        let restore_fortress_request = crate::pb::crud::v2::RestoreFortressRequest {
+            id: request.get_ref().id,
+            max_fortresses_per_owner: (!user.is_admin()).then_some(FORTRESSES_PER_PLAYER),
+            owner_id: (!user.is_admin()).then_some(user.sub),
+        };
+        let fortress = self
+            .crud_fortress_client
+            .clone()
+            .restore_fortress(restore_fortress_request)
+            .await?
+            .into_inner()
+            .fortress;
+
+        let message = RestoreFortressResponse { fortress };
+
+        Ok(Response::new(message))
+    }
+
     async fn list_fortresses(
         &self,
         request: Request<ListFortressesRequest>,
     ) -> Result<Response<ListFortressesResponse>, Status> {
-        let fortresses = if request.get_ref().only_mine {
-            let user = get_user(&request)?;
-            let list_fortresses_request = crate::pb::crud::v1::ListFortressesRequest {
-                owner_id: Some(user.sub),
-            };
-            self.crud_fortress_client
-                .clone()
-                .list_fortresses(list_fortresses_request)
-                .await?
-                .into_inner()
-                .fortresses
+        let viewer = viewer(&request);
+        let only_mine = request.get_ref().only_mine;
+        let owner_id = if only_mine {
+            Some(get_user(&request)?.sub)
         } else {
-            let list_fortresses_request =
-                crate::pb::crud::v1::ListFortressesRequest { owner_id: None };
-            self.crud_fortress_client
+            None
+        };
+        let request = request.into_inner();
+        // The order would rank other players' wealth.
+        if request.sort_field() == FortressSortField::Gold
+            && !only_mine
+            && !viewer.as_ref().is_some_and(Claims::is_admin)
+        {
+            return Err(with_error_info(
+                Code::PermissionDenied,
+                "Only your own fortresses can be sorted by gold.".to_owned(),
+                "SORT_NOT_ALLOWED",
+                HashMap::new(),
+            ));
+        }
+        let list_fortresses_request = crate::pb::crud::v2::ListFortressesRequest {
+            owner_id,
+            page_size: request.page_size,
+            page_token: request.page_token,
+            sort_field: request.sort_field,
+            sort_direction: request.sort_direction,
+        };
+        let listed = self
+            .crud_fortress_client
+            .clone()
+            .list_fortresses(list_fortresses_request)
+            .await?
+            .into_inner();
+        let (fortresses, summaries) = split_by_visibility(
+            &self.crud_building_client,
+            listed.fortresses,
+            viewer.as_ref(),
+        )
+        .await?;
+        let message = ListFortressesResponse {
+            fortresses,
+            next_page_token: listed.next_page_token,
+            summaries,
+        };
+
+        Ok(Response::new(message))
+    }
+
+    async fn get_fortress_history(
+        &self,
+        request: Request<GetFortressHistoryRequest>,
+    ) -> Result<Response<GetFortressHistoryResponse>, Status> {
+        let user = get_user(&request)?;
+        let request = request.into_inner();
+        let _fortress = self
+            .visible_fortress(request.fortress_id, Some(&user))
+            .await?;
+        let history_request = crate::pb::crud::v2::GetFortressHistoryRequest {
+            fortress_id: request.fortress_id,
+            page_size: request.page_size,
+            page_token: request.page_token,
+        };
+        let history = self
+            .crud_fortress_client
+            .clone()
+            .get_fortress_history(history_request)
+            .await?
+            .into_inner();
+        let message = GetFortressHistoryResponse {
+            events: history.events,
+            next_page_token: history.next_page_token,
+        };
+
+        Ok(Response::new(message))
+    }
+
+    async fn scout_fortress(
+        &self,
+        request: Request<ScoutFortressRequest>,
+    ) -> Result<Response<ScoutFortressResponse>, Status> {
+        let user = get_user(&request)?;
+        let ScoutFortressRequest { spy_from, target } = request.into_inner();
+        if spy_from == target {
+            return Err(Status::invalid_argument("A fortress can't scout itself."));
+        }
+        let (spy, spy_buildings) = self.fortress_with_buildings(spy_from).await?;
+        require_full_view(Some(&user), &spy)?;
+        let (_target, target_buildings) = self.fortress_with_buildings(target).await?;
+        let chance = scout_success_chance(
+            building_level(&spy_buildings, SCOUT_BUILDING),
+            building_level(&target_buildings, SCOUT_BUILDING),
+        );
+        let (success, detected) = {
+            let mut rng = self.rng.lock().unwrap_or_else(PoisonError::into_inner);
+            (rng.chance(chance), rng.chance(1.0 - chance))
+        };
+
+        tracing::info!(
+            "Fortress {spy_from} scouts fortress {target}: success {success}, detected {detected}"
+        );
+
+        let scout_request = crate::pb::crud::v2::ScoutFortressRequest {
+            spy_fortress_id: spy_from,
+            target_fortress_id: target,
+            energy_cost: SCOUT_ENERGY_COST,
+            success,
+            detected,
+        };
+        let scouted = self
+            .crud_fortress_client
+            .clone()
+            .scout_fortress(scout_request)
+            .await?
+            .into_inner();
+        let message = ScoutFortressResponse {
+            success,
+            detected,
+            report: scouted.report,
+            fortress: scouted.spy_fortress,
+        };
+
+        Ok(Response::new(message))
+    }
+
+    async fn list_scout_reports(
+        &self,
+        request: Request<ListScoutReportsRequest>,
+    ) -> Result<Response<ListScoutReportsResponse>, Status> {
+        let user = get_user(&request)?;
+        let request = request.into_inner();
+        let _fortress = self
+            .visible_fortress(request.fortress_id, Some(&user))
+            .await?;
+        let list_request = crate::pb::crud::v2::ListScoutReportsRequest {
+            spy_fortress_id: request.fortress_id,
+            page_size: request.page_size,
+            page_token: request.page_token,
+        };
+        let listed = self
+            .crud_fortress_client
+            .clone()
+            .list_scout_reports(list_request)
+            .await?
+            .into_inner();
+        let message = ListScoutReportsResponse {
+            reports: listed.reports,
+            next_page_token: listed.next_page_token,
+        };
+
+        Ok(Response::new(message))
+    }
+
+    async fn list_camps(
+        &self,
+        request: Request<ListCampsRequest>,
+    ) -> Result<Response<ListCampsResponse>, Status> {
+        let user = get_user(&request)?;
+        let mut camps = self
+            .crud_fortress_client
+            .clone()
+            .list_camps(crate::pb::crud::v2::ListCampsRequest {
+                owner_id: user.sub.clone(),
+            })
+            .await?
+            .into_inner()
+            .camps;
+        let now = self.clock.unix_secs();
+        let due: Vec<i32> = (0..CAMPS_PER_PLAYER)
+            .filter(|&slot| {
+                camps
+                    .iter()
+                    .find(|camp| camp.slot == slot)
+                    .is_none_or(|camp| needs_respawn(camp, now))
+            })
+            .collect();
+        if due.is_empty() {
+            return Ok(Response::new(ListCampsResponse { camps }));
+        }
+
+        let levels = player_levels(&self.crud_building_client, &user.sub).await?;
+        for slot in due {
+            let (strength, loot) = {
+                let mut rng = self.rng.lock().unwrap_or_else(PoisonError::into_inner);
+                generate_camp(&mut rng, slot, levels)
+            };
+            let spawn_request = SpawnCampRequest {
+                owner_id: user.sub.clone(),
+                slot,
+                strength,
+                loot: Some(loot),
+            };
+            let camp = self
+                .crud_fortress_client
                 .clone()
-                .list_fortresses(list_fortresses_request)
+                .spawn_camp(spawn_request)
                 .await?
                 .into_inner()
-                .fortresses
+                .camp
+                .ok_or_else(|| Status::internal("Camp not spawned"))?;
+            camps.retain(|kept| kept.slot != slot);
+            camps.push(camp);
+        }
+        camps.sort_by_key(|camp| camp.slot);
+
+        Ok(Response::new(ListCampsResponse { camps }))
+    }
+
+    async fn raid_camp(
+        &self,
+        request: Request<RaidCampRequest>,
+    ) -> Result<Response<RaidCampResponse>, Status> {
+        let user = get_user(&request)?;
+        let RaidCampRequest {
+            camp_id,
+            fortress_id,
+        } = request.into_inner();
+        // Only the caller's camps can be raided.
+        let camp = self
+            .crud_fortress_client
+            .clone()
+            .list_camps(crate::pb::crud::v2::ListCampsRequest {
+                owner_id: user.sub.clone(),
+            })
+            .await?
+            .into_inner()
+            .camps
+            .into_iter()
+            .find(|camp| camp.id == camp_id)
+            .ok_or_else(|| Status::not_found("Camp not found"))?;
+        let (fortress, buildings) = self.fortress_with_buildings(fortress_id).await?;
+        require_full_view(Some(&user), &fortress)?;
+        let chance = raid_success_chance(total_levels(&buildings), camp.strength);
+        let success = self
+            .rng
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .chance(chance);
+
+        tracing::info!("Fortress {fortress_id} raids camp {camp_id}: success {success}");
+
+        let raid_request = crate::pb::crud::v2::RaidCampRequest {
+            camp_id,
+            fortress_id,
+            energy_cost: RAID_ENERGY_COST,
+            success,
+            respawn_after_secs: CAMP_RESPAWN_SECS,
+        };
+        let raided = self
+            .crud_fortress_client
+            .clone()
+            .raid_camp(raid_request)
+            .await?
+            .into_inner();
+        let message = RaidCampResponse {
+            success,
+            camp: raided.camp,
+            fortress: raided.fortress,
+        };
+
+        Ok(Response::new(message))
+    }
+
+    async fn get_daily_reward(
+        &self,
+        request: Request<GetDailyRewardRequest>,
+    ) -> Result<Response<GetDailyRewardResponse>, Status> {
+        let user = get_user(&request)?;
+        let claimed = self
+            .crud_fortress_client
+            .clone()
+            .get_daily_reward(crate::pb::crud::v2::GetDailyRewardRequest { owner_id: user.sub })
+            .await?
+            .into_inner()
+            .daily_reward;
+        let today = utc_day(self.clock.unix_secs());
+        let (streak, claimable, next) = daily_reward_status(claimed.as_ref(), today);
+        let message = GetDailyRewardResponse {
+            streak,
+            claimable,
+            next_reward: Some(to_pb_costs(daily_reward(&DAILY_REWARDS, next))),
+            next_day_at: today.saturating_add(1).saturating_mul(SECS_PER_DAY),
+        };
+
+        Ok(Response::new(message))
+    }
+
+    async fn claim_daily_reward(
+        &self,
+        request: Request<ClaimDailyRewardRequest>,
+    ) -> Result<Response<ClaimDailyRewardResponse>, Status> {
+        let user = get_user(&request)?;
+        let fortress_id = request.into_inner().fortress_id;
+        // Even admins only credit their own fortresses.
+        let fortress = self.visible_fortress(fortress_id, Some(&user)).await?;
+        if fortress.owner_id != user.sub {
+            return Err(not_owner());
+        }
+        let claim_request = CrudClaimDailyRewardRequest {
+            owner_id: user.sub,
+            fortress_id,
+            rewards: DAILY_REWARDS.map(to_pb_costs).to_vec(),
+        };
+        let claimed = self
+            .crud_fortress_client
+            .clone()
+            .claim_daily_reward(claim_request)
+            .await?
+            .into_inner();
+        let streak = claimed
+            .daily_reward
+            .map_or(0, |daily_reward| daily_reward.streak);
+
+        tracing::info!("Fortress {fortress_id} claims a daily reward: streak {streak}");
+
+        let message = ClaimDailyRewardResponse {
+            streak,
+            reward: claimed.reward,
+            fortress: claimed.fortress,
         };
-        let message = ListFortressesResponse { fortresses };
 
         Ok(Response::new(message))
     }
@@ -420,17 +1010,10 @@ impl FortressService for MyFortressService {
         &self,
         request: Request<GetFortressGoldRequest>,
     ) -> Result<Response<GetFortressGoldResponse>, Status> {
-        let get_fortress_request = crate::pb::crud::v1::GetFortressRequest {
-            id: request.into_inner().id,
-        };
+        let viewer = viewer(&request);
         let gold = self
-            .crud_fortress_client
-            .clone()
-            .get_fortress(get_fortress_request)
+            .visible_fortress(request.into_inner().id, viewer.as_ref())
             .await?
-            .into_inner()
-            .fortress
-            .ok_or_else(|| Status::not_found("fortress not found"))?
             .gold;
 
         let message = GetFortressGoldResponse { gold };
@@ -444,12 +1027,13 @@ impl FortressService for MyFortressService {
     ) -> Result<Response<CollectFortressGoldResponse>, Status> {
         let user = get_user(&request)?;
         let fortress_id = request.get_ref().id;
-        let _fortress = self.verify_fortress_ownership(fortress_id, &user).await?;
+        let _fortress = self.visible_fortress(fortress_id, Some(&user)).await?;
         let collect_request = CollectFortressResourceRequest {
             id: fortress_id,
             resource: ResourceKind::Gold as i32,
             bonus_building_name: GOLD_BONUS_BUILDING.to_owned(),
-            base: None,
+            base: Some(ECONOMY.base_production),
+            expected_fortress_version: request.get_ref().expected_version,
         };
         let fortress = self
             .crud_fortress_client
@@ -466,18 +1050,12 @@ impl FortressService for MyFortressService {
         &self,
         request: Request<GetFortressFoodRequest>,
     ) -> Result<Response<GetFortressFoodResponse>, Status> {
-        let get_fortress_request = crate::pb::crud::v1::GetFortressRequest {
-            id: request.into_inner().id,
-        };
+        let viewer = viewer(&request);
         let food = self
-            .crud_fortress_client
-            .clone()
-            .get_fortress(get_fortress_request)
+            .visible_fortress(request.into_inner().id, viewer.as_ref())
             .await?
-            .into_inner()
-            .fortress
-            .ok_or_else(|| Status::not_found("fortress not found"))?
             .food;
+
         let message = GetFortressFoodResponse { food };
 
         Ok(Response::new(message))
@@ -489,12 +1067,13 @@ impl FortressService for MyFortressService {
     ) -> Result<Response<CollectFortressFoodResponse>, Status> {
         let user = get_user(&request)?;
         let fortress_id = request.get_ref().id;
-        let _fortress = self.verify_fortress_ownership(fortress_id, &user).await?;
+        let _fortress = self.visible_fortress(fortress_id, Some(&user)).await?;
         let collect_request = CollectFortressResourceRequest {
             id: fortress_id,
             resource: ResourceKind::Food as i32,
             bonus_building_name: FOOD_BONUS_BUILDING.to_owned(),
-            base: None,
+            base: Some(ECONOMY.base_production),
+            expected_fortress_version: request.get_ref().expected_version,
         };
         let fortress = self
             .crud_fortress_client
@@ -511,18 +1090,12 @@ impl FortressService for MyFortressService {
         &self,
         request: Request<GetFortressWoodRequest>,
     ) -> Result<Response<GetFortressWoodResponse>, Status> {
-        let get_fortress_request = crate::pb::crud::v1::GetFortressRequest {
-            id: request.into_inner().id,
-        };
+        let viewer = viewer(&request);
         let wood = self
-            .crud_fortress_client
-            .clone()
-            .get_fortress(get_fortress_request)
+            .visible_fortress(request.into_inner().id, viewer.as_ref())
             .await?
-            .into_inner()
-            .fortress
-            .ok_or_else(|| Status::not_found("fortress not found"))?
             .wood;
+
         let message = GetFortressWoodResponse { wood };
 
         Ok(Response::new(message))
@@ -534,12 +1107,13 @@ impl FortressService for MyFortressService {
     ) -> Result<Response<CollectFortressWoodResponse>, Status> {
         let user = get_user(&request)?;
         let fortress_id = request.get_ref().id;
-        let _fortress = self.verify_fortress_ownership(fortress_id, &user).await?;
+        let _fortress = self.visible_fortress(fortress_id, Some(&user)).await?;
         let collect_request = CollectFortressResourceRequest {
             id: fortress_id,
             resource: ResourceKind::Wood as i32,
             bonus_building_name: WOOD_BONUS_BUILDING.to_owned(),
-            base: None,
+            base: Some(ECONOMY.base_production),
+            expected_fortress_version: request.get_ref().expected_version,
         };
         let fortress = self
             .crud_fortress_client
@@ -556,17 +1130,10 @@ impl FortressService for MyFortressService {
         &self,
         request: Request<GetFortressEnergyRequest>,
     ) -> Result<Response<GetFortressEnergyResponse>, Status> {
-        let get_fortress_request = crate::pb::crud::v1::GetFortressRequest {
-            id: request.into_inner().id,
-        };
+        let viewer = viewer(&request);
         let energy = self
-            .crud_fortress_client
-            .clone()
-            .get_fortress(get_fortress_request)
+            .visible_fortress(request.into_inner().id, viewer.as_ref())
             .await?
-            .into_inner()
-            .fortress
-            .ok_or_else(|| Status::not_found("fortress not found"))?
             .energy;
 
         let message = GetFortressEnergyResponse { energy };
@@ -580,12 +1147,13 @@ impl FortressService for MyFortressService {
     ) -> Result<Response<CollectFortressEnergyResponse>, Status> {
         let user = get_user(&request)?;
         let fortress_id = request.get_ref().id;
-        let _fortress = self.verify_fortress_ownership(fortress_id, &user).await?;
+        let _fortress = self.visible_fortress(fortress_id, Some(&user)).await?;
         let collect_request = CollectFortressResourceRequest {
             id: fortress_id,
             resource: ResourceKind::Energy as i32,
             bonus_building_name: ENERGY_BONUS_BUILDING.to_owned(),
-            base: None,
+            base: Some(ECONOMY.base_production),
+            expected_fortress_version: request.get_ref().expected_version,
         };
         let fortress = self
             .crud_fortress_client
@@ -603,70 +1171,49 @@ impl FortressService for MyFortressService {
 mod tests {
     use super::*;
 
-    #[test]
-    fn optimize_factor_works() {
-        let cost_max = i32::MAX;
-        let levels = [10, 50, 100, 200, 1000];
-
-        for level in levels {
-            let factor = optimize_factor(level, BASE_COST, cost_max);
-            println!("level max: {level}, base: {BASE_COST}, factor: {factor}");
-            assert!(factor >= 1.0);
+    fn building(name: &str, level: i32) -> Building {
+        Building {
+            name: name.to_owned(),
+            level,
+            ..Building::default()
         }
     }
 
-    #[allow(clippy::cast_possible_truncation)]
     #[test]
-    fn upgrade_cost_works() {
-        const FACTOR: f64 = 1.1012;
-        let result = upgrade_cost(0, BASE_COST, FACTOR) as i32;
-        assert_eq!(result, 10);
-        let result = upgrade_cost(1, BASE_COST, FACTOR) as i32;
-        assert_eq!(result, 10);
-        let result = upgrade_cost(2, BASE_COST, FACTOR) as i32;
-        assert_eq!(result, 11);
-        let result = upgrade_cost(3, BASE_COST, FACTOR) as i32;
-        assert_eq!(result, 12);
-        let result = upgrade_cost(4, BASE_COST, FACTOR) as i32;
-        assert_eq!(result, 13);
-        let result = upgrade_cost(5, BASE_COST, FACTOR) as i32;
-        assert_eq!(result, 14);
-        let result = upgrade_cost(50, BASE_COST, FACTOR) as i32;
-        assert_eq!(result, 1125);
-        let result = upgrade_cost(100, BASE_COST, FACTOR) as i32;
-        assert_eq!(result, 139_557);
-        let result = upgrade_cost(150, BASE_COST, FACTOR) as i32;
-        assert_eq!(result, 17_300_721);
-        let result = upgrade_cost(200, BASE_COST, FACTOR) as i32;
-        assert_eq!(result, 2_144_738_468);
-    }
-
-    #[allow(clippy::cast_possible_truncation)]
-    #[test]
-    fn upgrade_cost_overflow() {
-        const FACTOR: f64 = 1.1012;
-        let result = upgrade_cost(250, BASE_COST, FACTOR) as i32;
-        assert_eq!(result, i32::MAX);
-        let result = upgrade_cost(1000, BASE_COST, FACTOR) as i32;
-        assert_eq!(result, i32::MAX);
-        let result = upgrade_cost(i32::MAX, BASE_COST, FACTOR) as i32;
-        assert_eq!(result, i32::MAX);
+    fn scout_chance_follows_sanctuary_levels() {
+        let buildings = [
+            building(SCOUT_BUILDING, 4),
+            building(GOLD_BONUS_BUILDING, 9),
+        ];
+        assert_eq!(building_level(&buildings, SCOUT_BUILDING), 4);
+        assert_eq!(building_level(&buildings, FOOD_BONUS_BUILDING), 0);
+
+        assert!((scout_success_chance(3, 3) - 0.5).abs() < f64::EPSILON);
+        assert!((scout_success_chance(5, 3) - 0.6).abs() < 1e-9);
+        assert!((scout_success_chance(3, 5) - 0.4).abs() < 1e-9);
+        assert!((scout_success_chance(20, 0) - SCOUT_MAX_CHANCE).abs() < f64::EPSILON);
+        assert!((scout_success_chance(0, 20) - SCOUT_MIN_CHANCE).abs() < f64::EPSILON);
     }
 
-    #[allow(clippy::cast_possible_truncation)]
     #[test]
-    fn level_slice() {
-        let cost_max = i32::MAX;
-        let levels = [10, 50, 100, 200, 1000];
-
-        for level in levels {
-            let factor = optimize_factor(level, BASE_COST, cost_max);
-            let result_min = upgrade_cost(1, BASE_COST, factor) as i32;
-            let result_max = upgrade_cost(level, BASE_COST, factor) as i32;
-            println!("{}: {} -> {}: {}", 1, result_min, level, result_max);
-            assert!(BASE_COST <= result_min && result_min <= cost_max);
-            assert!(BASE_COST <= result_max && result_max <= cost_max);
-            assert!(result_min <= result_max);
-        }
+    fn daily_reward_status_follows_the_last_claim() {
+        let claimed_on = |day: i64, streak| DailyReward {
+            streak,
+            last_claimed_at: day * SECS_PER_DAY + 60,
+            ..DailyReward::default()
+        };
+        assert_eq!(daily_reward_status(None, 10), (0, true, 1));
+        assert_eq!(
+            daily_reward_status(Some(&claimed_on(10, 3)), 10),
+            (3, false, 4)
+        );
+        assert_eq!(
+            daily_reward_status(Some(&claimed_on(9, 3)), 10),
+            (3, true, 4)
+        );
+        assert_eq!(
+            daily_reward_status(Some(&claimed_on(8, 3)), 10),
+            (0, true, 1)
+        );
     }
 }
@@ -0,0 +1,136 @@
+//! Fog of war: who sees what of a fortress.
+//!
+//! The owner and admins see the exact resources and buildings, everyone else
+//! only gets a [`FortressSummary`]. Every call that returns fortress details
+//! goes through [`sees_everything`] or [`require_full_view`].
+
+use crate::{
+    auth::Claims,
+    pb::common::v2::{Building, Fortress, FortressSummary, WealthBand},
+    service::{login_required, not_owner},
+};
+use tonic::{Request, Status};
+
+const LEVELS_PER_TIER: i64 = 10;
+/// Upper bounds, exclusive, of the total resources of each band.
+const WEALTH_BANDS: [(i64, WealthBand); 3] = [
+    (1_000, WealthBand::Poor),
+    (100_000, WealthBand::Modest),
+    (10_000_000, WealthBand::Wealthy),
+];
+
+/// The logged in player, if any.
+pub fn viewer<T>(request: &Request<T>) -> Option<Claims> {
+    request.extensions().get::<Claims>().cloned()
+}
+
+/// Whether `viewer` gets the full view of `fortress`. Members of the owner's
+/// alliance belong here once alliances exist.
+pub fn sees_everything(viewer: Option<&Claims>, fortress: &Fortress) -> bool {
+    viewer.is_some_and(|viewer| viewer.sub == fortress.owner_id || viewer.is_admin())
+}
+
+pub fn require_full_view(viewer: Option<&Claims>, fortress: &Fortress) -> Result<(), Status> {
+    match viewer {
+        None => Err(login_required()),
+        Some(_) if sees_everything(viewer, fortress) => Ok(()),
+        Some(_) => Err(not_owner()),
+    }
+}
+
+/// Sum of the building levels, in steps of [`LEVELS_PER_TIER`].
+pub fn level_tier(buildings: &[Building]) -> i32 {
+    let levels = buildings.iter().fold(0_i64, |sum, building| {
+        sum.saturating_add(i64::from(building.level))
+    });
+    i32::try_from(levels / LEVELS_PER_TIER).unwrap_or(i32::MAX)
+}
+
+pub fn wealth_band(fortress: &Fortress) -> WealthBand {
+    let total = [fortress.gold, fortress.food, fortress.wood, fortress.energy]
+        .into_iter()
+        .fold(0, i64::saturating_add);
+    WEALTH_BANDS
+        .into_iter()
+        .find(|&(limit, _)| total < limit)
+        .map_or(WealthBand::Opulent, |(_, band)| band)
+}
+
+/// `buildings` are the buildings of `fortress`.
+pub fn summarize(fortress: &Fortress, buildings: &[Building]) -> FortressSummary {
+    FortressSummary {
+        id: fortress.id,
+        owner_name: fortress.owner_name.clone(),
+        level_tier: level_tier(buildings),
+        wealth_band: wealth_band(fortress).into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn player(sub: &str, roles: &[&str]) -> Claims {
+        Claims {
+            sub: sub.to_owned(),
+            preferred_username: None,
+            email: None,
+            roles: roles.iter().map(|&role| role.to_owned()).collect(),
+        }
+    }
+
+    fn fortress(owner_id: &str, gold: i64) -> Fortress {
+        Fortress {
+            owner_id: owner_id.to_owned(),
+            gold,
+            ..Fortress::default()
+        }
+    }
+
+    #[test]
+    fn only_owner_and_admins_see_everything() {
+        let fortress = fortress("owner", 0);
+        assert!(sees_everything(Some(&player("owner", &[])), &fortress));
+        assert!(sees_everything(
+            Some(&player("admin", &["admin"])),
+            &fortress
+        ));
+        assert!(!sees_everything(Some(&player("other", &[])), &fortress));
+        assert!(!sees_everything(None, &fortress));
+
+        let denied = require_full_view(Some(&player("other", &[])), &fortress);
+        assert_eq!(
+            denied.map_err(|status| status.code()),
+            Err(tonic::Code::PermissionDenied)
+        );
+        let anonymous = require_full_view(None, &fortress);
+        assert_eq!(
+            anonymous.map_err(|status| status.code()),
+            Err(tonic::Code::Unauthenticated)
+        );
+    }
+
+    #[test]
+    fn summary_rounds_levels_and_resources() {
+        let buildings: Vec<Building> = [5, 9, 7]
+            .into_iter()
+            .map(|level| Building {
+                level,
+                ..Building::default()
+            })
+            .collect();
+        assert_eq!(level_tier(&buildings), 2);
+        assert_eq!(level_tier(&[]), 0);
+
+        assert_eq!(wealth_band(&fortress("owner", 999)), WealthBand::Poor);
+        assert_eq!(wealth_band(&fortress("owner", 1_000)), WealthBand::Modest);
+        assert_eq!(
+            wealth_band(&fortress("owner", 99_999_999)),
+            WealthBand::Opulent
+        );
+        assert_eq!(
+            wealth_band(&fortress("owner", i64::MAX)),
+            WealthBand::Opulent
+        );
+    }
+}
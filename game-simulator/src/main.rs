@@ -0,0 +1,286 @@
+//! Offline economy simulator: plays simulated players against the game rules
+//! to see how a change of the economy numbers plays out.
+
+mod sim;
+mod strategy;
+
+use clap::{Parser, ValueEnum};
+use rusty::{Costs, rules::Economy};
+use serde_json::json;
+use sim::Report;
+use std::str::FromStr;
+use strategy::Player;
+
+/// A resource and its cost divisor, written `resource=divisor`.
+#[derive(Clone, Copy)]
+struct Divisor {
+    resource: Resource,
+    divisor: i64,
+}
+
+#[derive(ValueEnum, Clone, Copy)]
+enum Resource {
+    Gold,
+    Food,
+    Wood,
+    Energy,
+}
+
+impl FromStr for Divisor {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (resource, divisor) = s
+            .split_once('=')
+            .ok_or_else(|| format!("expected resource=divisor, got '{s}'"))?;
+        let divisor = divisor
+            .parse()
+            .ok()
+            .filter(|&divisor| divisor > 0)
+            .ok_or_else(|| format!("invalid divisor '{divisor}', expected a positive integer"))?;
+        Ok(Self {
+            resource: Resource::from_str(resource, true)?,
+            divisor,
+        })
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy)]
+enum Format {
+    /// One row per player with the time to reach quarters of the max level
+    Table,
+    Json,
+    /// One row per sample or milestone, see `--series`
+    Csv,
+}
+
+#[derive(ValueEnum, Clone, Copy)]
+enum Series {
+    /// Resources and levels of every player at each sample
+    Samples,
+    /// Every upgrade of every player
+    Milestones,
+}
+
+/// Plays simulated players against the game rules over simulated time.
+///
+/// The economy defaults to the one of the game server, each flag overriding
+/// one of its numbers.
+#[derive(Parser)]
+#[command(version)]
+struct Args {
+    /// Simulated players, as `strategy@collects_per_hour`
+    #[arg(
+        short,
+        long = "player",
+        value_delimiter = ',',
+        default_value = "greedy-cheapest@60,balanced@60,balanced@3600"
+    )]
+    players: Vec<Player>,
+    /// Simulated duration
+    #[arg(long, default_value_t = 24 * 7)]
+    hours: u64,
+    /// Time between two samples of the fortresses
+    #[arg(long, default_value_t = 60)]
+    sample_minutes: u64,
+    #[arg(long)]
+    max_building_level: Option<i32>,
+    /// Cost of the first upgrade, before the split between resources
+    #[arg(long)]
+    base_cost: Option<i32>,
+    /// Cost of the upgrade to the max level, before the split
+    #[arg(long)]
+    max_cost: Option<i32>,
+    /// What a resource costs, as the upgrade cost divided by its divisor
+    #[arg(long, value_delimiter = ',', value_name = "RESOURCE=DIVISOR")]
+    cost_divisors: Vec<Divisor>,
+    /// What a collect yields on top of the levels of the bonus buildings
+    #[arg(long)]
+    base_production: Option<i64>,
+    #[arg(short, long, value_enum, default_value_t = Format::Table)]
+    output: Format,
+    /// Rows of the CSV output
+    #[arg(long, value_enum, default_value_t = Series::Samples)]
+    series: Series,
+}
+
+impl Args {
+    fn economy(&self) -> Economy {
+        let live = Economy::LIVE;
+        let mut cost_divisors: Costs = live.cost_divisors;
+        for Divisor { resource, divisor } in &self.cost_divisors {
+            match resource {
+                Resource::Gold => cost_divisors.gold = *divisor,
+                Resource::Food => cost_divisors.food = *divisor,
+                Resource::Wood => cost_divisors.wood = *divisor,
+                Resource::Energy => cost_divisors.energy = *divisor,
+            }
+        }
+        Economy {
+            max_building_level: self.max_building_level.unwrap_or(live.max_building_level),
+            base_cost: self.base_cost.unwrap_or(live.base_cost),
+            max_cost: self.max_cost.unwrap_or(live.max_cost),
+            cost_divisors,
+            base_production: self.base_production.unwrap_or(live.base_production),
+        }
+    }
+}
+
+/// `1d 3h`, `45m`, or `-` when never reached.
+fn duration(secs: Option<u64>) -> String {
+    let Some(secs) = secs else {
+        return "-".to_owned();
+    };
+    let (days, hours, minutes) = (secs / 86_400, secs % 86_400 / 3600, secs % 3600 / 60);
+    match (days, hours) {
+        (0, 0) => format!("{minutes}m"),
+        (0, _) => format!("{hours}h {minutes}m"),
+        _ => format!("{days}d {hours}h"),
+    }
+}
+
+fn table(economy: &Economy, reports: &[Report]) -> String {
+    let max = economy.max_building_level;
+    let levels: Vec<i32> = [max / 4, max / 2, max * 3 / 4, max]
+        .into_iter()
+        .filter(|&level| level > 0)
+        .collect();
+    let header = ["PLAYER", "COLLECTS", "UPGRADES", "LEVELS"]
+        .into_iter()
+        .map(ToOwned::to_owned)
+        .chain(levels.iter().map(|level| format!("ALL AT {level}")))
+        .chain(std::iter::once("RESOURCES".to_owned()))
+        .collect();
+    let rows: Vec<Vec<String>> = std::iter::once(header)
+        .chain(reports.iter().map(|report| {
+            let last = report.samples.last();
+            let levels_cell = last.map_or_else(String::new, |s| {
+                format!("{}/{}/{}/{}", s.bank, s.farm, s.sawmill, s.sanctuary)
+            });
+            let resources_cell = last.map_or_else(String::new, |s| {
+                format!("{}/{}/{}/{}", s.gold, s.food, s.wood, s.energy)
+            });
+            [
+                report.player.clone(),
+                report.collects.to_string(),
+                report.upgrades.to_string(),
+                levels_cell,
+            ]
+            .into_iter()
+            .chain(levels.iter().map(|&level| {
+                let index = usize::try_from(level - 1).ok();
+                duration(index.and_then(|index| report.level_times_secs.get(index).copied()))
+            }))
+            .chain(std::iter::once(resources_cell))
+            .collect()
+        }))
+        .collect();
+
+    let mut widths: Vec<usize> = Vec::new();
+    for row in &rows {
+        for (i, cell) in row.iter().enumerate() {
+            match widths.get_mut(i) {
+                Some(width) => *width = (*width).max(cell.len()),
+                None => widths.push(cell.len()),
+            }
+        }
+    }
+    rows.iter()
+        .map(|row| {
+            row.iter()
+                .zip(&widths)
+                .map(|(cell, width)| format!("{cell:<width$}"))
+                .collect::<Vec<_>>()
+                .join("  ")
+                .trim_end()
+                .to_owned()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn csv(reports: &[Report], series: Series) -> String {
+    let mut lines = Vec::new();
+    match series {
+        Series::Samples => {
+            lines.push(
+                "player,time_secs,gold,food,wood,energy,bank,farm,sawmill,sanctuary".to_owned(),
+            );
+            for report in reports {
+                lines.extend(report.samples.iter().map(|s| {
+                    format!(
+                        "{},{},{},{},{},{},{},{},{},{}",
+                        report.player,
+                        s.time_secs,
+                        s.gold,
+                        s.food,
+                        s.wood,
+                        s.energy,
+                        s.bank,
+                        s.farm,
+                        s.sawmill,
+                        s.sanctuary
+                    )
+                }));
+            }
+        }
+        Series::Milestones => {
+            lines.push("player,building,level,time_secs".to_owned());
+            for report in reports {
+                lines.extend(report.milestones.iter().map(|m| {
+                    format!(
+                        "{},{},{},{}",
+                        report.player, m.building, m.level, m.time_secs
+                    )
+                }));
+            }
+        }
+    }
+    lines.join("\n")
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+    let economy = args.economy();
+    if economy.max_building_level < 2
+        || economy.base_cost <= 0
+        || economy.max_cost < economy.base_cost
+    {
+        return Err(
+            "The economy needs a max level of at least 2 and 0 < base cost <= max cost".into(),
+        );
+    }
+
+    let duration_secs = args.hours.saturating_mul(3600);
+    let sample_secs = args.sample_minutes.saturating_mul(60);
+    let reports: Vec<Report> = args
+        .players
+        .iter()
+        .map(|&player| sim::simulate(&economy, player, duration_secs, sample_secs))
+        .collect();
+
+    match args.output {
+        Format::Table => println!("{}", table(&economy, &reports)),
+        Format::Json => println!(
+            "{}",
+            json!({
+                "economy": {
+                    "max_building_level": economy.max_building_level,
+                    "base_cost": economy.base_cost,
+                    "max_cost": economy.max_cost,
+                    "cost_divisors": {
+                        "gold": economy.cost_divisors.gold,
+                        "food": economy.cost_divisors.food,
+                        "wood": economy.cost_divisors.wood,
+                        "energy": economy.cost_divisors.energy,
+                    },
+                    "base_production": economy.base_production,
+                },
+                "hours": args.hours,
+                "players": reports,
+            })
+        ),
+        Format::Csv => println!("{}", csv(&reports, args.series)),
+    }
+    Ok(())
+}
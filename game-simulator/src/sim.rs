@@ -0,0 +1,190 @@
+//! Plays a player against the economy rules over simulated time, without any
+//! server.
+
+use crate::strategy::{Fortress, Player};
+use rusty::rules::{BONUS_BUILDINGS, Economy};
+use serde::Serialize;
+
+const SECS_PER_HOUR: u64 = 3600;
+
+/// The fortress at a point of the simulated time.
+#[derive(Serialize)]
+pub struct Sample {
+    pub time_secs: u64,
+    pub gold: i64,
+    pub food: i64,
+    pub wood: i64,
+    pub energy: i64,
+    pub bank: i32,
+    pub farm: i32,
+    pub sawmill: i32,
+    pub sanctuary: i32,
+}
+
+/// A building reaching a level.
+#[derive(Serialize)]
+pub struct Milestone {
+    pub building: &'static str,
+    pub level: i32,
+    pub time_secs: u64,
+}
+
+#[derive(Serialize)]
+pub struct Report {
+    pub player: String,
+    pub collects: u64,
+    pub upgrades: u64,
+    /// When every building reached each level, the index being the level
+    /// minus one. Levels not reached within the simulation are left out.
+    pub level_times_secs: Vec<u64>,
+    pub milestones: Vec<Milestone>,
+    pub samples: Vec<Sample>,
+}
+
+const fn sample(fortress: &Fortress, time_secs: u64) -> Sample {
+    let [bank, farm, sawmill, sanctuary] = fortress.levels;
+    Sample {
+        time_secs,
+        gold: fortress.resources.gold,
+        food: fortress.resources.food,
+        wood: fortress.resources.wood,
+        energy: fortress.resources.energy,
+        bank,
+        farm,
+        sawmill,
+        sanctuary,
+    }
+}
+
+/// Collects every resource at the pace of the player for `duration_secs`,
+/// letting the strategy upgrade after each collect, and records the fortress
+/// every `sample_secs`.
+pub fn simulate(economy: &Economy, player: Player, duration_secs: u64, sample_secs: u64) -> Report {
+    let interval = (SECS_PER_HOUR / player.collects_per_hour).max(1);
+    let sample_secs = sample_secs.max(1);
+    let mut strategy = player.strategy.strategy();
+    let mut fortress = Fortress::default();
+    let mut report = Report {
+        player: player.name(),
+        collects: 0,
+        upgrades: 0,
+        level_times_secs: Vec::new(),
+        milestones: Vec::new(),
+        samples: Vec::new(),
+    };
+
+    let mut next_sample = 0;
+    let mut time = 0;
+    while time <= duration_secs {
+        while next_sample <= time {
+            report.samples.push(sample(&fortress, next_sample));
+            next_sample += sample_secs;
+        }
+
+        let production = fortress.production(economy);
+        let resources = &mut fortress.resources;
+        resources.gold = resources.gold.saturating_add(production.gold);
+        resources.food = resources.food.saturating_add(production.food);
+        resources.wood = resources.wood.saturating_add(production.wood);
+        resources.energy = resources.energy.saturating_add(production.energy);
+        report.collects += 1;
+
+        while let Some(building) = strategy.next_upgrade(&fortress, economy) {
+            // A strategy asking for what cannot be done waits for the next
+            // collect, as the server would refuse it.
+            let Some(costs) = fortress
+                .upgrade_costs(economy, building)
+                .filter(|costs| fortress.can_afford(costs))
+            else {
+                break;
+            };
+            let (Some(level), Some(&name)) = (
+                fortress.levels.get_mut(building),
+                BONUS_BUILDINGS.get(building),
+            ) else {
+                break;
+            };
+            *level += 1;
+            let level = *level;
+            let resources = &mut fortress.resources;
+            resources.gold -= costs.gold;
+            resources.food -= costs.food;
+            resources.wood -= costs.wood;
+            resources.energy -= costs.energy;
+            report.upgrades += 1;
+            report.milestones.push(Milestone {
+                building: name,
+                level,
+                time_secs: time,
+            });
+
+            let lowest = fortress.levels.iter().copied().min().unwrap_or(0);
+            while i32::try_from(report.level_times_secs.len()).is_ok_and(|reached| reached < lowest)
+            {
+                report.level_times_secs.push(time);
+            }
+        }
+
+        time += interval;
+    }
+    while next_sample <= duration_secs {
+        report.samples.push(sample(&fortress, next_sample));
+        next_sample += sample_secs;
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strategy::Builtin;
+
+    const DAY_SECS: u64 = 24 * SECS_PER_HOUR;
+
+    #[test]
+    fn collector_only_accumulates_the_base_production() {
+        let player = Player {
+            strategy: Builtin::Collector,
+            collects_per_hour: 60,
+        };
+
+        let report = simulate(&Economy::LIVE, player, SECS_PER_HOUR, 600);
+
+        // One collect at each minute, both ends included.
+        assert_eq!(report.collects, 61);
+        assert_eq!(report.upgrades, 0);
+        assert_eq!(report.samples.len(), 7);
+        assert!(report.samples.iter().all(|sample| sample.bank == 0));
+        let last = report
+            .samples
+            .last()
+            .map(|sample| (sample.time_secs, sample.gold));
+        assert_eq!(last, Some((SECS_PER_HOUR, 60)));
+    }
+
+    #[test]
+    fn upgrades_are_paid_and_recorded() {
+        let player = Player {
+            strategy: Builtin::Balanced,
+            collects_per_hour: 60,
+        };
+
+        let report = simulate(&Economy::LIVE, player, DAY_SECS, DAY_SECS);
+
+        assert_eq!(
+            usize::try_from(report.upgrades).ok(),
+            Some(report.milestones.len())
+        );
+        assert!(!report.level_times_secs.is_empty());
+        assert!(report.level_times_secs.is_sorted());
+        let first = report.milestones.first().map(|m| (m.building, m.level));
+        assert_eq!(first, Some(("bank", 1)));
+        assert!(
+            report
+                .samples
+                .iter()
+                .all(|s| s.gold >= 0 && s.food >= 0 && s.wood >= 0 && s.energy >= 0)
+        );
+    }
+}
@@ -0,0 +1,163 @@
+//! How simulated players spend their resources.
+
+use clap::ValueEnum;
+use rusty::{
+    Costs,
+    rules::{self, BONUS_BUILDINGS, Economy, Resource},
+};
+use std::str::FromStr;
+
+/// Collects made per hour when a player does not say.
+const DEFAULT_COLLECTS_PER_HOUR: u64 = 60;
+
+/// A simulated fortress, as created by the game server.
+#[derive(Debug, Clone, Default)]
+pub struct Fortress {
+    pub resources: Costs,
+    /// Level of each building of [`BONUS_BUILDINGS`], in the same order.
+    pub levels: [i32; BONUS_BUILDINGS.len()],
+}
+
+impl Fortress {
+    pub fn production(&self, economy: &Economy) -> Costs {
+        economy.production(BONUS_BUILDINGS.into_iter().zip(self.levels))
+    }
+
+    /// What upgrading `building` costs, `None` at the max level.
+    pub fn upgrade_costs(&self, economy: &Economy, building: usize) -> Option<Costs> {
+        economy.next_upgrade_costs(*self.levels.get(building)?)
+    }
+
+    pub const fn can_afford(&self, costs: &Costs) -> bool {
+        rules::can_afford(&self.resources, costs)
+    }
+
+    /// The buildings whose next level the fortress can pay for right now,
+    /// with that cost.
+    fn affordable(&self, economy: &Economy) -> impl Iterator<Item = (usize, Costs)> {
+        (0..self.levels.len()).filter_map(move |building| {
+            self.upgrade_costs(economy, building)
+                .filter(|costs| self.can_afford(costs))
+                .map(|costs| (building, costs))
+        })
+    }
+}
+
+const fn total(costs: &Costs) -> i64 {
+    costs
+        .gold
+        .saturating_add(costs.food)
+        .saturating_add(costs.wood)
+        .saturating_add(costs.energy)
+}
+
+pub trait Strategy {
+    /// The building to upgrade next, as an index of [`BONUS_BUILDINGS`], or
+    /// `None` to wait for the next collect. Asked again after each upgrade.
+    fn next_upgrade(&mut self, fortress: &Fortress, economy: &Economy) -> Option<usize>;
+}
+
+/// Never upgrades, the baseline of the resource curves.
+pub struct Collector;
+
+impl Strategy for Collector {
+    fn next_upgrade(&mut self, _fortress: &Fortress, _economy: &Economy) -> Option<usize> {
+        None
+    }
+}
+
+/// Upgrades the building whose next level is the cheapest among the
+/// affordable ones.
+pub struct GreedyCheapest;
+
+impl Strategy for GreedyCheapest {
+    fn next_upgrade(&mut self, fortress: &Fortress, economy: &Economy) -> Option<usize> {
+        fortress
+            .affordable(economy)
+            .min_by_key(|(building, costs)| (total(costs), *building))
+            .map(|(building, _)| building)
+    }
+}
+
+/// Upgrades the building of the least produced resource, saving up for it
+/// rather than upgrading anything else.
+pub struct Balanced;
+
+impl Strategy for Balanced {
+    fn next_upgrade(&mut self, fortress: &Fortress, economy: &Economy) -> Option<usize> {
+        let production = fortress.production(economy);
+        let (building, _) = BONUS_BUILDINGS
+            .into_iter()
+            .enumerate()
+            .filter(|&(building, _)| fortress.upgrade_costs(economy, building).is_some())
+            .filter_map(|(building, name)| {
+                Resource::produced_by(name).map(|resource| (building, resource.amount(&production)))
+            })
+            .min_by_key(|&(building, amount)| (amount, building))?;
+        fortress
+            .upgrade_costs(economy, building)
+            .filter(|costs| fortress.can_afford(costs))
+            .map(|_| building)
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum Builtin {
+    /// Never upgrades
+    Collector,
+    /// Upgrades the cheapest affordable building
+    GreedyCheapest,
+    /// Saves up for the building of the least produced resource
+    Balanced,
+}
+
+impl Builtin {
+    pub fn strategy(self) -> Box<dyn Strategy> {
+        match self {
+            Self::Collector => Box::new(Collector),
+            Self::GreedyCheapest => Box::new(GreedyCheapest),
+            Self::Balanced => Box::new(Balanced),
+        }
+    }
+}
+
+/// A simulated player, written `strategy@collects_per_hour`.
+#[derive(Clone, Copy, Debug)]
+pub struct Player {
+    pub strategy: Builtin,
+    pub collects_per_hour: u64,
+}
+
+impl Player {
+    /// The name of the player in reports, as written on the command line.
+    pub fn name(&self) -> String {
+        let strategy = self
+            .strategy
+            .to_possible_value()
+            .map(|value| value.get_name().to_owned())
+            .unwrap_or_default();
+        format!("{strategy}@{}", self.collects_per_hour)
+    }
+}
+
+impl FromStr for Player {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (strategy, collects_per_hour) = s
+            .split_once('@')
+            .map_or((s, None), |(strategy, rate)| (strategy, Some(rate)));
+        let collects_per_hour =
+            collects_per_hour.map_or(Ok(DEFAULT_COLLECTS_PER_HOUR), |rate| {
+                rate.parse()
+                    .map_err(|e| format!("invalid collects per hour '{rate}': {e}"))
+            })?;
+        if collects_per_hour == 0 {
+            return Err("a player collects at least once per hour".to_owned());
+        }
+        Ok(Self {
+            strategy: Builtin::from_str(strategy, true)?,
+            collects_per_hour,
+        })
+    }
+}
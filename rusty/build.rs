@@ -0,0 +1,26 @@
+fn main() {
+    // `embed_migrations!` doesn't notice new migration directories by itself.
+    println!("cargo:rerun-if-changed=migrations");
+    println!("cargo:rerun-if-changed=migrations-sqlite");
+    #[cfg(feature = "grpc")]
+    if let Err(error) = compile_rpc_protos() {
+        panic!("cannot compile the google.rpc protos: {error}");
+    }
+}
+
+/// The `google.rpc` types behind `error_details`.
+#[cfg(feature = "grpc")]
+fn compile_rpc_protos() -> Result<(), Box<dyn std::error::Error>> {
+    println!("cargo:rerun-if-changed=../proto/google");
+    tonic_prost_build::configure()
+        .build_client(false)
+        .build_server(false)
+        .compile_protos(
+            &[
+                "../proto/google/rpc/error_details.proto",
+                "../proto/google/rpc/status.proto",
+            ],
+            &["../proto"],
+        )?;
+    Ok(())
+}
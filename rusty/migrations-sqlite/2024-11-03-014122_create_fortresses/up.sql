@@ -0,0 +1,10 @@
+-- Your SQL goes here
+
+CREATE TABLE fortresses (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    owner_id TEXT NOT NULL,
+    gold INTEGER NOT NULL,
+    food INTEGER NOT NULL,
+    wood INTEGER NOT NULL,
+    energy INTEGER NOT NULL
+);
@@ -0,0 +1,8 @@
+-- Your SQL goes here
+
+CREATE TABLE buildings (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    name TEXT NOT NULL,
+    level INTEGER NOT NULL,
+    fortress_id INTEGER NOT NULL REFERENCES fortresses(id)
+);
@@ -0,0 +1,14 @@
+-- This file should undo anything in `up.sql`
+
+CREATE TABLE buildings_new (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    name TEXT NOT NULL,
+    level INTEGER NOT NULL,
+    fortress_id INTEGER NOT NULL REFERENCES fortresses(id)
+);
+INSERT INTO buildings_new (id, name, level, fortress_id)
+    SELECT id, name, level, fortress_id FROM buildings;
+DROP TABLE buildings;
+ALTER TABLE buildings_new RENAME TO buildings;
+
+ALTER TABLE fortresses DROP COLUMN deleted_at;
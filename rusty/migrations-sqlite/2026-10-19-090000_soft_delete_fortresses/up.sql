@@ -0,0 +1,15 @@
+-- Your SQL goes here
+
+ALTER TABLE fortresses ADD COLUMN deleted_at TIMESTAMP;
+
+-- SQLite can't alter a foreign key, the table has to be rebuilt.
+CREATE TABLE buildings_new (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    name TEXT NOT NULL,
+    level INTEGER NOT NULL,
+    fortress_id INTEGER NOT NULL REFERENCES fortresses(id) ON DELETE CASCADE
+);
+INSERT INTO buildings_new (id, name, level, fortress_id)
+    SELECT id, name, level, fortress_id FROM buildings;
+DROP TABLE buildings;
+ALTER TABLE buildings_new RENAME TO buildings;
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+
+ALTER TABLE buildings DROP COLUMN version;
+ALTER TABLE fortresses DROP COLUMN version;
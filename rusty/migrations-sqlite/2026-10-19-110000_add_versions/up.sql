@@ -0,0 +1,4 @@
+-- Your SQL goes here
+
+ALTER TABLE fortresses ADD COLUMN version INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE buildings ADD COLUMN version INTEGER NOT NULL DEFAULT 0;
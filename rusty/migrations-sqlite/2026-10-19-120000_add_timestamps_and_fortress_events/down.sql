@@ -0,0 +1,11 @@
+-- This file should undo anything in `up.sql`
+
+DROP TABLE fortress_events;
+
+DROP TRIGGER buildings_set_updated_at;
+DROP TRIGGER fortresses_set_updated_at;
+
+ALTER TABLE buildings DROP COLUMN updated_at;
+ALTER TABLE buildings DROP COLUMN created_at;
+ALTER TABLE fortresses DROP COLUMN updated_at;
+ALTER TABLE fortresses DROP COLUMN created_at;
@@ -0,0 +1,36 @@
+-- Your SQL goes here
+
+-- SQLite only accepts constant defaults on added columns, existing rows are
+-- backfilled right after.
+ALTER TABLE fortresses ADD COLUMN created_at TIMESTAMP NOT NULL DEFAULT '1970-01-01 00:00:00';
+ALTER TABLE fortresses ADD COLUMN updated_at TIMESTAMP NOT NULL DEFAULT '1970-01-01 00:00:00';
+ALTER TABLE buildings ADD COLUMN created_at TIMESTAMP NOT NULL DEFAULT '1970-01-01 00:00:00';
+ALTER TABLE buildings ADD COLUMN updated_at TIMESTAMP NOT NULL DEFAULT '1970-01-01 00:00:00';
+UPDATE fortresses SET created_at = CURRENT_TIMESTAMP, updated_at = CURRENT_TIMESTAMP;
+UPDATE buildings SET created_at = CURRENT_TIMESTAMP, updated_at = CURRENT_TIMESTAMP;
+
+-- Same behavior as `diesel_manage_updated_at` on Postgres.
+CREATE TRIGGER fortresses_set_updated_at AFTER UPDATE ON fortresses
+    FOR EACH ROW WHEN NEW.updated_at IS OLD.updated_at
+BEGIN
+    UPDATE fortresses SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.id;
+END;
+CREATE TRIGGER buildings_set_updated_at AFTER UPDATE ON buildings
+    FOR EACH ROW WHEN NEW.updated_at IS OLD.updated_at
+BEGIN
+    UPDATE buildings SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.id;
+END;
+
+-- Append-only history, kept once its fortress is purged.
+CREATE TABLE fortress_events (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    fortress_id INTEGER REFERENCES fortresses(id) ON DELETE SET NULL,
+    building_id INTEGER REFERENCES buildings(id) ON DELETE SET NULL,
+    kind TEXT NOT NULL,
+    gold INTEGER NOT NULL,
+    food INTEGER NOT NULL,
+    wood INTEGER NOT NULL,
+    energy INTEGER NOT NULL,
+    created_at TIMESTAMP NOT NULL
+);
+CREATE INDEX fortress_events_fortress_id_id_idx ON fortress_events (fortress_id, id);
@@ -0,0 +1,51 @@
+-- This file should undo anything in `up.sql`
+
+PRAGMA foreign_keys = OFF;
+BEGIN;
+
+DROP INDEX fortress_events_building_id_idx;
+DROP INDEX fortresses_owner_id_idx;
+DROP INDEX buildings_fortress_id_name_key;
+
+CREATE TABLE fortresses_old (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    owner_id TEXT NOT NULL,
+    gold INTEGER NOT NULL,
+    food INTEGER NOT NULL,
+    wood INTEGER NOT NULL,
+    energy INTEGER NOT NULL,
+    deleted_at TIMESTAMP,
+    version INTEGER NOT NULL DEFAULT 0,
+    created_at TIMESTAMP NOT NULL DEFAULT '1970-01-01 00:00:00',
+    updated_at TIMESTAMP NOT NULL DEFAULT '1970-01-01 00:00:00'
+);
+INSERT INTO fortresses_old SELECT * FROM fortresses;
+DROP TABLE fortresses;
+ALTER TABLE fortresses_old RENAME TO fortresses;
+
+CREATE TABLE buildings_old (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    name TEXT NOT NULL,
+    level INTEGER NOT NULL,
+    fortress_id INTEGER NOT NULL REFERENCES fortresses(id) ON DELETE CASCADE,
+    version INTEGER NOT NULL DEFAULT 0,
+    created_at TIMESTAMP NOT NULL DEFAULT '1970-01-01 00:00:00',
+    updated_at TIMESTAMP NOT NULL DEFAULT '1970-01-01 00:00:00'
+);
+INSERT INTO buildings_old SELECT * FROM buildings;
+DROP TABLE buildings;
+ALTER TABLE buildings_old RENAME TO buildings;
+
+CREATE TRIGGER fortresses_set_updated_at AFTER UPDATE ON fortresses
+    FOR EACH ROW WHEN NEW.updated_at IS OLD.updated_at
+BEGIN
+    UPDATE fortresses SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.id;
+END;
+CREATE TRIGGER buildings_set_updated_at AFTER UPDATE ON buildings
+    FOR EACH ROW WHEN NEW.updated_at IS OLD.updated_at
+BEGIN
+    UPDATE buildings SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.id;
+END;
+
+COMMIT;
+PRAGMA foreign_keys = ON;
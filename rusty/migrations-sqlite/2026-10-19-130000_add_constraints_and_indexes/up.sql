@@ -0,0 +1,69 @@
+-- Your SQL goes here
+
+-- SQLite can't add a CHECK constraint, both tables have to be rebuilt. Foreign
+-- keys are off meanwhile, otherwise dropping the old tables would cascade.
+PRAGMA foreign_keys = OFF;
+BEGIN;
+
+-- Existing rows must satisfy the new rules: keep the most upgraded building
+-- of each name in a fortress and clamp negative values to zero.
+DELETE FROM buildings
+    WHERE EXISTS (
+        SELECT 1 FROM buildings AS kept
+        WHERE buildings.fortress_id = kept.fortress_id
+            AND buildings.name = kept.name
+            AND (buildings.level, kept.id) < (kept.level, buildings.id)
+    );
+
+CREATE TABLE fortresses_new (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    owner_id TEXT NOT NULL,
+    gold INTEGER NOT NULL CONSTRAINT fortresses_gold_check CHECK (gold >= 0),
+    food INTEGER NOT NULL CONSTRAINT fortresses_food_check CHECK (food >= 0),
+    wood INTEGER NOT NULL CONSTRAINT fortresses_wood_check CHECK (wood >= 0),
+    energy INTEGER NOT NULL CONSTRAINT fortresses_energy_check CHECK (energy >= 0),
+    deleted_at TIMESTAMP,
+    version INTEGER NOT NULL DEFAULT 0,
+    created_at TIMESTAMP NOT NULL DEFAULT '1970-01-01 00:00:00',
+    updated_at TIMESTAMP NOT NULL DEFAULT '1970-01-01 00:00:00'
+);
+INSERT INTO fortresses_new
+    SELECT id, owner_id, MAX(gold, 0), MAX(food, 0), MAX(wood, 0), MAX(energy, 0),
+        deleted_at, version, created_at, updated_at
+    FROM fortresses;
+DROP TABLE fortresses;
+ALTER TABLE fortresses_new RENAME TO fortresses;
+
+CREATE TABLE buildings_new (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    name TEXT NOT NULL,
+    level INTEGER NOT NULL CONSTRAINT buildings_level_check CHECK (level >= 0),
+    fortress_id INTEGER NOT NULL REFERENCES fortresses(id) ON DELETE CASCADE,
+    version INTEGER NOT NULL DEFAULT 0,
+    created_at TIMESTAMP NOT NULL DEFAULT '1970-01-01 00:00:00',
+    updated_at TIMESTAMP NOT NULL DEFAULT '1970-01-01 00:00:00'
+);
+INSERT INTO buildings_new
+    SELECT id, name, MAX(level, 0), fortress_id, version, created_at, updated_at
+    FROM buildings;
+DROP TABLE buildings;
+ALTER TABLE buildings_new RENAME TO buildings;
+
+-- Triggers went away with the old tables.
+CREATE TRIGGER fortresses_set_updated_at AFTER UPDATE ON fortresses
+    FOR EACH ROW WHEN NEW.updated_at IS OLD.updated_at
+BEGIN
+    UPDATE fortresses SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.id;
+END;
+CREATE TRIGGER buildings_set_updated_at AFTER UPDATE ON buildings
+    FOR EACH ROW WHEN NEW.updated_at IS OLD.updated_at
+BEGIN
+    UPDATE buildings SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.id;
+END;
+
+CREATE UNIQUE INDEX buildings_fortress_id_name_key ON buildings (fortress_id, name);
+CREATE INDEX fortresses_owner_id_idx ON fortresses (owner_id);
+CREATE INDEX fortress_events_building_id_idx ON fortress_events (building_id);
+
+COMMIT;
+PRAGMA foreign_keys = ON;
@@ -0,0 +1,31 @@
+-- This file should undo anything in `up.sql`
+
+-- SQLite can't drop a column holding a foreign key, the table has to be
+-- rebuilt. Foreign keys are off meanwhile, as in the constraints migration.
+PRAGMA foreign_keys = OFF;
+BEGIN;
+
+CREATE TABLE fortress_events_old (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    fortress_id INTEGER REFERENCES fortresses(id) ON DELETE SET NULL,
+    building_id INTEGER REFERENCES buildings(id) ON DELETE SET NULL,
+    kind TEXT NOT NULL,
+    gold INTEGER NOT NULL,
+    food INTEGER NOT NULL,
+    wood INTEGER NOT NULL,
+    energy INTEGER NOT NULL,
+    created_at TIMESTAMP NOT NULL
+);
+INSERT INTO fortress_events_old
+    SELECT id, fortress_id, building_id, kind, gold, food, wood, energy, created_at
+    FROM fortress_events;
+DROP TABLE fortress_events;
+ALTER TABLE fortress_events_old RENAME TO fortress_events;
+CREATE INDEX fortress_events_fortress_id_id_idx ON fortress_events (fortress_id, id);
+CREATE INDEX fortress_events_building_id_idx ON fortress_events (building_id);
+
+DROP TABLE scout_report_buildings;
+DROP TABLE scout_reports;
+
+COMMIT;
+PRAGMA foreign_keys = ON;
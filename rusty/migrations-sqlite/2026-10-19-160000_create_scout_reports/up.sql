@@ -0,0 +1,32 @@
+-- Your SQL goes here
+
+-- No implicit transaction, see `down.sql`.
+BEGIN;
+
+-- Snapshots of a target fortress, taken by successful scouting.
+CREATE TABLE scout_reports (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    spy_fortress_id INTEGER NOT NULL REFERENCES fortresses(id) ON DELETE CASCADE,
+    target_fortress_id INTEGER NOT NULL REFERENCES fortresses(id) ON DELETE CASCADE,
+    gold INTEGER NOT NULL,
+    food INTEGER NOT NULL,
+    wood INTEGER NOT NULL,
+    energy INTEGER NOT NULL,
+    created_at TIMESTAMP NOT NULL
+);
+CREATE INDEX scout_reports_spy_fortress_id_id_idx ON scout_reports (spy_fortress_id, id);
+CREATE INDEX scout_reports_target_fortress_id_idx ON scout_reports (target_fortress_id);
+
+CREATE TABLE scout_report_buildings (
+    report_id INTEGER NOT NULL REFERENCES scout_reports(id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    level INTEGER NOT NULL,
+    PRIMARY KEY (report_id, name)
+);
+
+-- The other side of events involving two fortresses, such as scouting.
+ALTER TABLE fortress_events
+    ADD COLUMN other_fortress_id INTEGER REFERENCES fortresses(id) ON DELETE SET NULL;
+CREATE INDEX fortress_events_other_fortress_id_idx ON fortress_events (other_fortress_id);
+
+COMMIT;
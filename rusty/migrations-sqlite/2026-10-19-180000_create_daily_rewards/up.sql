@@ -0,0 +1,12 @@
+-- Your SQL goes here
+
+-- Daily login reward streak of each player.
+CREATE TABLE daily_rewards (
+    owner_id TEXT PRIMARY KEY NOT NULL,
+    -- Consecutive UTC days with a claim, up to `last_claimed_at`.
+    streak INTEGER NOT NULL CONSTRAINT daily_rewards_streak_check CHECK (streak >= 1),
+    last_claimed_at TIMESTAMP NOT NULL,
+    version INTEGER NOT NULL DEFAULT 0,
+    created_at TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP NOT NULL
+);
@@ -0,0 +1,12 @@
+-- This file should undo anything in `up.sql`
+
+CREATE TRIGGER fortresses_set_updated_at AFTER UPDATE ON fortresses
+    FOR EACH ROW WHEN NEW.updated_at IS OLD.updated_at
+BEGIN
+    UPDATE fortresses SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.id;
+END;
+CREATE TRIGGER buildings_set_updated_at AFTER UPDATE ON buildings
+    FOR EACH ROW WHEN NEW.updated_at IS OLD.updated_at
+BEGIN
+    UPDATE buildings SET updated_at = CURRENT_TIMESTAMP WHERE id = NEW.id;
+END;
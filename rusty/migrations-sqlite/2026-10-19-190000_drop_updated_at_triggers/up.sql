@@ -0,0 +1,6 @@
+-- Your SQL goes here
+
+-- crud-server sets `updated_at` from the game clock on every update, which the
+-- triggers replaced with the wall clock whenever the value didn't change.
+DROP TRIGGER fortresses_set_updated_at;
+DROP TRIGGER buildings_set_updated_at;
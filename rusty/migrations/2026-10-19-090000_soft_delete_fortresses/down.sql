@@ -0,0 +1,8 @@
+-- This file should undo anything in `up.sql`
+
+ALTER TABLE buildings
+    DROP CONSTRAINT buildings_fortress_id_fkey,
+    ADD CONSTRAINT buildings_fortress_id_fkey
+        FOREIGN KEY (fortress_id) REFERENCES fortresses(id);
+
+ALTER TABLE fortresses DROP COLUMN deleted_at;
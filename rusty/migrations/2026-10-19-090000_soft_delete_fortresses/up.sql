@@ -0,0 +1,8 @@
+-- Your SQL goes here
+
+ALTER TABLE fortresses ADD COLUMN deleted_at TIMESTAMP;
+
+ALTER TABLE buildings
+    DROP CONSTRAINT buildings_fortress_id_fkey,
+    ADD CONSTRAINT buildings_fortress_id_fkey
+        FOREIGN KEY (fortress_id) REFERENCES fortresses(id) ON DELETE CASCADE;
@@ -0,0 +1,13 @@
+-- This file should undo anything in `up.sql`
+
+UPDATE fortresses SET
+    gold = LEAST(gold, 2147483647),
+    food = LEAST(food, 2147483647),
+    wood = LEAST(wood, 2147483647),
+    energy = LEAST(energy, 2147483647);
+
+ALTER TABLE fortresses
+    ALTER COLUMN gold TYPE INTEGER,
+    ALTER COLUMN food TYPE INTEGER,
+    ALTER COLUMN wood TYPE INTEGER,
+    ALTER COLUMN energy TYPE INTEGER;
@@ -0,0 +1,8 @@
+-- Your SQL goes here
+
+-- SQLite INTEGER columns already hold 64-bit values, only Postgres needs this.
+ALTER TABLE fortresses
+    ALTER COLUMN gold TYPE BIGINT,
+    ALTER COLUMN food TYPE BIGINT,
+    ALTER COLUMN wood TYPE BIGINT,
+    ALTER COLUMN energy TYPE BIGINT;
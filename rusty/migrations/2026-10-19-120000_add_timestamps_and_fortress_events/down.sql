@@ -0,0 +1,9 @@
+-- This file should undo anything in `up.sql`
+
+DROP TABLE fortress_events;
+
+DROP TRIGGER set_updated_at ON buildings;
+DROP TRIGGER set_updated_at ON fortresses;
+
+ALTER TABLE buildings DROP COLUMN created_at, DROP COLUMN updated_at;
+ALTER TABLE fortresses DROP COLUMN created_at, DROP COLUMN updated_at;
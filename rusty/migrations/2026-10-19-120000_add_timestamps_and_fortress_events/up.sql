@@ -0,0 +1,25 @@
+-- Your SQL goes here
+
+ALTER TABLE fortresses
+    ADD COLUMN created_at TIMESTAMP NOT NULL DEFAULT (now() AT TIME ZONE 'utc'),
+    ADD COLUMN updated_at TIMESTAMP NOT NULL DEFAULT (now() AT TIME ZONE 'utc');
+ALTER TABLE buildings
+    ADD COLUMN created_at TIMESTAMP NOT NULL DEFAULT (now() AT TIME ZONE 'utc'),
+    ADD COLUMN updated_at TIMESTAMP NOT NULL DEFAULT (now() AT TIME ZONE 'utc');
+
+SELECT diesel_manage_updated_at('fortresses');
+SELECT diesel_manage_updated_at('buildings');
+
+-- Append-only history, kept once its fortress is purged.
+CREATE TABLE fortress_events (
+    id BIGSERIAL PRIMARY KEY,
+    fortress_id INTEGER REFERENCES fortresses(id) ON DELETE SET NULL,
+    building_id INTEGER REFERENCES buildings(id) ON DELETE SET NULL,
+    kind VARCHAR NOT NULL,
+    gold BIGINT NOT NULL,
+    food BIGINT NOT NULL,
+    wood BIGINT NOT NULL,
+    energy BIGINT NOT NULL,
+    created_at TIMESTAMP NOT NULL
+);
+CREATE INDEX fortress_events_fortress_id_id_idx ON fortress_events (fortress_id, id);
@@ -0,0 +1,13 @@
+-- This file should undo anything in `up.sql`
+
+DROP INDEX fortress_events_building_id_idx;
+DROP INDEX fortresses_owner_id_idx;
+
+ALTER TABLE buildings
+    DROP CONSTRAINT buildings_fortress_id_name_key,
+    DROP CONSTRAINT buildings_level_check;
+ALTER TABLE fortresses
+    DROP CONSTRAINT fortresses_energy_check,
+    DROP CONSTRAINT fortresses_wood_check,
+    DROP CONSTRAINT fortresses_food_check,
+    DROP CONSTRAINT fortresses_gold_check;
@@ -0,0 +1,28 @@
+-- Your SQL goes here
+
+-- Existing rows must satisfy the new rules: keep the most upgraded building
+-- of each name in a fortress and clamp negative values to zero.
+DELETE FROM buildings
+    USING buildings AS kept
+    WHERE buildings.fortress_id = kept.fortress_id
+        AND buildings.name = kept.name
+        AND (buildings.level, kept.id) < (kept.level, buildings.id);
+UPDATE fortresses
+    SET gold = GREATEST(gold, 0),
+        food = GREATEST(food, 0),
+        wood = GREATEST(wood, 0),
+        energy = GREATEST(energy, 0)
+    WHERE LEAST(gold, food, wood, energy) < 0;
+UPDATE buildings SET level = 0 WHERE level < 0;
+
+ALTER TABLE fortresses
+    ADD CONSTRAINT fortresses_gold_check CHECK (gold >= 0),
+    ADD CONSTRAINT fortresses_food_check CHECK (food >= 0),
+    ADD CONSTRAINT fortresses_wood_check CHECK (wood >= 0),
+    ADD CONSTRAINT fortresses_energy_check CHECK (energy >= 0);
+ALTER TABLE buildings
+    ADD CONSTRAINT buildings_level_check CHECK (level >= 0),
+    ADD CONSTRAINT buildings_fortress_id_name_key UNIQUE (fortress_id, name);
+
+CREATE INDEX fortresses_owner_id_idx ON fortresses (owner_id);
+CREATE INDEX fortress_events_building_id_idx ON fortress_events (building_id);
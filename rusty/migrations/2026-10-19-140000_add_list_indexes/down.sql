@@ -0,0 +1,7 @@
+-- This file should undo anything in `up.sql`
+
+DROP INDEX buildings_name_id_idx;
+DROP INDEX buildings_level_id_idx;
+DROP INDEX fortresses_gold_id_idx;
+DROP INDEX fortresses_owner_id_id_idx;
+CREATE INDEX fortresses_owner_id_idx ON fortresses (owner_id);
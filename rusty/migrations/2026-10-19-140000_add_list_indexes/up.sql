@@ -0,0 +1,9 @@
+-- Your SQL goes here
+
+-- Keyset pagination orders by a sort key then by id, these indexes let
+-- each page start right after the cursor instead of sorting every row.
+DROP INDEX fortresses_owner_id_idx;
+CREATE INDEX fortresses_owner_id_id_idx ON fortresses (owner_id, id);
+CREATE INDEX fortresses_gold_id_idx ON fortresses (gold, id);
+CREATE INDEX buildings_level_id_idx ON buildings (level, id);
+CREATE INDEX buildings_name_id_idx ON buildings (name, id);
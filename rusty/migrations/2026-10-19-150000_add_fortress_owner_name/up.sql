@@ -0,0 +1,5 @@
+-- Your SQL goes here
+
+-- Display name shown to other players, fortresses created before it was
+-- recorded have none.
+ALTER TABLE fortresses ADD COLUMN owner_name VARCHAR NOT NULL DEFAULT '';
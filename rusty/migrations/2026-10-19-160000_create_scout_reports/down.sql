@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+
+ALTER TABLE fortress_events DROP COLUMN other_fortress_id;
+DROP TABLE scout_report_buildings;
+DROP TABLE scout_reports;
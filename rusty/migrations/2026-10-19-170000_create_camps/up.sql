@@ -0,0 +1,19 @@
+-- Your SQL goes here
+
+-- Barbarian camps, generated by game-server for each player.
+CREATE TABLE camps (
+    id SERIAL PRIMARY KEY,
+    owner_id VARCHAR NOT NULL,
+    slot INTEGER NOT NULL CONSTRAINT camps_slot_check CHECK (slot >= 0),
+    strength INTEGER NOT NULL CONSTRAINT camps_strength_check CHECK (strength >= 0),
+    gold BIGINT NOT NULL CONSTRAINT camps_gold_check CHECK (gold >= 0),
+    food BIGINT NOT NULL CONSTRAINT camps_food_check CHECK (food >= 0),
+    wood BIGINT NOT NULL CONSTRAINT camps_wood_check CHECK (wood >= 0),
+    energy BIGINT NOT NULL CONSTRAINT camps_energy_check CHECK (energy >= 0),
+    -- Set once raided, the camp can be replaced from then on.
+    respawn_at TIMESTAMP,
+    version BIGINT NOT NULL DEFAULT 0,
+    created_at TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP NOT NULL,
+    CONSTRAINT camps_owner_id_slot_key UNIQUE (owner_id, slot)
+);
@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+
+SELECT diesel_manage_updated_at('fortresses');
+SELECT diesel_manage_updated_at('buildings');
@@ -0,0 +1,6 @@
+-- Your SQL goes here
+
+-- crud-server sets `updated_at` from the game clock on every update, which the
+-- triggers replaced with the wall clock whenever the value didn't change.
+DROP TRIGGER set_updated_at ON fortresses;
+DROP TRIGGER set_updated_at ON buildings;
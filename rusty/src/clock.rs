@@ -0,0 +1,136 @@
+//! Time of the game, read through a [`Clock`] so that tests can stop it and QA
+//! can move it forward.
+
+use std::{
+    sync::{
+        Mutex, PoisonError,
+        atomic::{AtomicI64, Ordering},
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+pub trait Clock: Send + Sync {
+    fn now(&self) -> SystemTime;
+
+    /// Seconds since the Unix epoch, negative before it.
+    fn unix_secs(&self) -> i64 {
+        match self.now().duration_since(UNIX_EPOCH) {
+            Ok(since) => i64::try_from(since.as_secs()).unwrap_or(i64::MAX),
+            Err(before) => {
+                i64::try_from(before.duration().as_secs()).map_or(i64::MIN, |secs| -secs)
+            }
+        }
+    }
+}
+
+/// The time of the operating system.
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// Another clock shifted by an offset which can change while the server runs.
+pub struct SkewedClock<C = SystemClock> {
+    inner: C,
+    skew_secs: AtomicI64,
+}
+
+impl<C: Clock> SkewedClock<C> {
+    pub const fn new(inner: C) -> Self {
+        Self {
+            inner,
+            skew_secs: AtomicI64::new(0),
+        }
+    }
+
+    /// Seconds added to the inner clock, negative to go back in time.
+    pub fn skew(&self) -> i64 {
+        self.skew_secs.load(Ordering::Relaxed)
+    }
+
+    pub fn set_skew(&self, skew_secs: i64) {
+        self.skew_secs.store(skew_secs, Ordering::Relaxed);
+    }
+}
+
+impl<C: Clock> Clock for SkewedClock<C> {
+    fn now(&self) -> SystemTime {
+        let now = self.inner.now();
+        let skew = Duration::from_secs(self.skew().unsigned_abs());
+        let skewed = if self.skew() >= 0 {
+            now.checked_add(skew)
+        } else {
+            now.checked_sub(skew)
+        };
+        skewed.unwrap_or(now)
+    }
+}
+
+/// A clock which only moves when told to, for tests.
+pub struct FakeClock {
+    now: Mutex<SystemTime>,
+}
+
+impl FakeClock {
+    #[must_use]
+    pub const fn new(now: SystemTime) -> Self {
+        Self {
+            now: Mutex::new(now),
+        }
+    }
+
+    /// Starts at `secs` after the Unix epoch.
+    #[must_use]
+    pub fn at_unix_secs(secs: u64) -> Self {
+        Self::new(
+            UNIX_EPOCH
+                .checked_add(Duration::from_secs(secs))
+                .unwrap_or(UNIX_EPOCH),
+        )
+    }
+
+    pub fn advance(&self, by: Duration) {
+        let mut now = self.now.lock().unwrap_or_else(PoisonError::into_inner);
+        *now = now.checked_add(by).unwrap_or(*now);
+    }
+
+    pub fn set(&self, to: SystemTime) {
+        *self.now.lock().unwrap_or_else(PoisonError::into_inner) = to;
+    }
+}
+
+impl Clock for FakeClock {
+    fn now(&self) -> SystemTime {
+        *self.now.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fake_clock_only_moves_when_told_to() {
+        let clock = FakeClock::at_unix_secs(1_000);
+        assert_eq!(clock.unix_secs(), 1_000);
+
+        clock.advance(Duration::from_mins(1));
+        assert_eq!(clock.unix_secs(), 1_060);
+        clock.set(UNIX_EPOCH);
+        assert_eq!(clock.unix_secs(), 0);
+    }
+
+    #[test]
+    fn skew_moves_the_inner_clock_both_ways() {
+        let clock = SkewedClock::new(FakeClock::at_unix_secs(1_000));
+        assert_eq!(clock.unix_secs(), 1_000);
+
+        clock.set_skew(3_600);
+        assert_eq!(clock.unix_secs(), 4_600);
+        clock.set_skew(-2_000);
+        assert_eq!(clock.unix_secs(), -1_000);
+    }
+}
@@ -0,0 +1,80 @@
+use diesel::{
+    connection::SimpleConnection,
+    prelude::*,
+    r2d2::{self, ManageConnection, R2D2Connection},
+};
+use diesel_migrations::{EmbeddedMigrations, MigrationHarness, embed_migrations};
+use std::error::Error;
+
+const SQLITE_SCHEME: &str = "sqlite://";
+const POSTGRES_MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations/");
+const SQLITE_MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations-sqlite/");
+
+#[derive(diesel::MultiConnection)]
+pub enum AnyConnection {
+    Postgresql(PgConnection),
+    Sqlite(SqliteConnection),
+}
+
+/// Opens a connection to the backend selected by the scheme of `database_url`:
+/// `sqlite://<path>` for `SQLite`, anything else is handed to Postgres.
+///
+/// # Errors
+///
+/// Returns an error if the database can't be reached or configured.
+pub fn establish(database_url: &str) -> ConnectionResult<AnyConnection> {
+    let Some(path) = database_url.strip_prefix(SQLITE_SCHEME) else {
+        return PgConnection::establish(database_url).map(AnyConnection::Postgresql);
+    };
+    let mut conn = SqliteConnection::establish(path)?;
+    // Foreign keys are off by default in SQLite, and concurrent writers
+    // should wait for the lock instead of failing right away.
+    conn.batch_execute("PRAGMA foreign_keys = ON; PRAGMA busy_timeout = 5000;")
+        .map_err(|e| ConnectionError::BadConnection(e.to_string()))?;
+
+    Ok(AnyConnection::Sqlite(conn))
+}
+
+/// Applies the migrations matching the backend of `conn`.
+///
+/// # Errors
+///
+/// Returns an error if a migration fails to apply.
+pub fn run_pending_migrations(
+    conn: &mut AnyConnection,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    match conn {
+        AnyConnection::Postgresql(conn) => conn.run_pending_migrations(POSTGRES_MIGRATIONS),
+        AnyConnection::Sqlite(conn) => conn.run_pending_migrations(SQLITE_MIGRATIONS),
+    }?;
+
+    Ok(())
+}
+
+pub struct AnyConnectionManager {
+    database_url: String,
+}
+
+impl AnyConnectionManager {
+    #[must_use]
+    pub const fn new(database_url: String) -> Self {
+        Self { database_url }
+    }
+}
+
+impl ManageConnection for AnyConnectionManager {
+    type Connection = AnyConnection;
+    type Error = r2d2::Error;
+
+    fn connect(&self) -> Result<Self::Connection, Self::Error> {
+        establish(&self.database_url).map_err(r2d2::Error::ConnectionError)
+    }
+
+    fn is_valid(&self, conn: &mut Self::Connection) -> Result<(), Self::Error> {
+        conn.ping().map_err(r2d2::Error::QueryError)
+    }
+
+    fn has_broken(&self, conn: &mut Self::Connection) -> bool {
+        std::thread::panicking() || conn.is_broken()
+    }
+}
@@ -0,0 +1,84 @@
+//! `google.rpc.ErrorInfo` details of the statuses sent by the servers.
+
+#[allow(clippy::pedantic, clippy::nursery)]
+mod pb {
+    tonic::include_proto!("google.rpc");
+}
+
+pub use pb::ErrorInfo;
+
+use pb::Status as RpcStatus;
+use prost::Message;
+use std::collections::HashMap;
+use tonic::{Code, Status};
+
+/// `ErrorInfo.domain` of the errors raised by the game servers.
+const DOMAIN: &str = "rusty-kingdom";
+const ERROR_INFO_TYPE_URL: &str = "type.googleapis.com/google.rpc.ErrorInfo";
+
+/// Status carrying a `google.rpc.ErrorInfo`, so clients can tell errors apart
+/// by `reason` instead of parsing messages.
+#[must_use]
+// `ErrorInfo.metadata` only takes the default hasher.
+#[allow(clippy::implicit_hasher)]
+pub fn with_error_info(
+    code: Code,
+    message: String,
+    reason: &str,
+    metadata: HashMap<String, String>,
+) -> Status {
+    let info = ErrorInfo {
+        reason: reason.to_owned(),
+        domain: DOMAIN.to_owned(),
+        metadata,
+    };
+    let details = RpcStatus {
+        code: code.into(),
+        message: message.clone(),
+        details: vec![prost_types::Any {
+            type_url: ERROR_INFO_TYPE_URL.to_owned(),
+            value: info.encode_to_vec(),
+        }],
+    };
+
+    Status::with_details(code, message, details.encode_to_vec().into())
+}
+
+/// The `ErrorInfo` detail of `status`, if it has one.
+#[must_use]
+pub fn error_info(status: &Status) -> Option<ErrorInfo> {
+    let any = RpcStatus::decode(status.details())
+        .ok()?
+        .details
+        .into_iter()
+        .find(|any| any.type_url == ERROR_INFO_TYPE_URL)?;
+
+    ErrorInfo::decode(any.value.as_slice()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn error_info_reads_back_the_details() {
+        let metadata = HashMap::from([("fortress_id".to_owned(), "1".to_owned())]);
+        let status = with_error_info(
+            Code::NotFound,
+            "fortress not found".to_owned(),
+            "FORTRESS_NOT_FOUND",
+            metadata.clone(),
+        );
+
+        assert_eq!(status.code(), Code::NotFound);
+        assert_eq!(
+            error_info(&status),
+            Some(ErrorInfo {
+                reason: "FORTRESS_NOT_FOUND".to_owned(),
+                domain: DOMAIN.to_owned(),
+                metadata,
+            })
+        );
+        assert_eq!(error_info(&Status::not_found("plain")), None);
+    }
+}
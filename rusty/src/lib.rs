@@ -1,21 +1,43 @@
+#[cfg(feature = "db")]
 use models::{NewBuilding, NewFortress};
 
+pub mod clock;
+#[cfg(feature = "db")]
+pub mod connection;
+#[cfg(feature = "grpc")]
+pub mod error_details;
+#[cfg(feature = "db")]
 pub mod models;
+pub mod rng;
+pub mod rules;
+#[cfg(feature = "db")]
 pub mod schema;
 
 // TODO: create a `Resources` structure and refactor this with `Fortress` resources
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub struct Costs {
-    pub gold: i32,
-    pub food: i32,
-    pub wood: i32,
-    pub energy: i32,
+    pub gold: i64,
+    pub food: i64,
+    pub wood: i64,
+    pub energy: i64,
 }
 
+impl Costs {
+    pub const ZERO: Self = Self {
+        gold: 0,
+        food: 0,
+        wood: 0,
+        energy: 0,
+    };
+}
+
+#[cfg(feature = "db")]
 impl NewFortress {
     #[must_use]
     pub const fn new(owner_id: String) -> Self {
         Self {
             owner_id,
+            owner_name: String::new(),
             gold: 0,
             food: 0,
             wood: 0,
@@ -24,6 +46,7 @@ impl NewFortress {
     }
 }
 
+#[cfg(feature = "db")]
 impl NewBuilding {
     #[must_use]
     pub const fn new(name: String, fortress_id: i32) -> Self {
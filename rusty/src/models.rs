@@ -1,37 +1,47 @@
-use crate::schema::{buildings, fortresses};
+use crate::schema::{
+    buildings, camps, daily_rewards, fortress_events, fortresses, scout_report_buildings,
+    scout_reports,
+};
 use diesel::prelude::*;
+use time::PrimitiveDateTime;
 
-#[derive(Queryable, Identifiable, Selectable, PartialEq, Eq)]
+#[derive(Queryable, Identifiable, Selectable, Clone, Debug, PartialEq, Eq)]
 #[diesel(table_name = fortresses)]
 pub struct Fortress {
     pub id: i32,
     pub owner_id: String,
-    pub gold: i32,
-    pub food: i32,
-    pub wood: i32,
-    pub energy: i32,
+    pub gold: i64,
+    pub food: i64,
+    pub wood: i64,
+    pub energy: i64,
+    pub deleted_at: Option<PrimitiveDateTime>,
+    pub version: i64,
+    pub created_at: PrimitiveDateTime,
+    pub updated_at: PrimitiveDateTime,
+    pub owner_name: String,
 }
 
 #[derive(Insertable)]
 #[diesel(table_name = fortresses)]
 pub struct NewFortress {
     pub owner_id: String,
-    pub gold: i32,
-    pub food: i32,
-    pub wood: i32,
-    pub energy: i32,
+    pub owner_name: String,
+    pub gold: i64,
+    pub food: i64,
+    pub wood: i64,
+    pub energy: i64,
 }
 
 #[derive(AsChangeset)]
 #[diesel(table_name = fortresses)]
 pub struct UpdateFortress {
-    pub gold: Option<i32>,
-    pub food: Option<i32>,
-    pub wood: Option<i32>,
-    pub energy: Option<i32>,
+    pub gold: Option<i64>,
+    pub food: Option<i64>,
+    pub wood: Option<i64>,
+    pub energy: Option<i64>,
 }
 
-#[derive(Queryable, Selectable, Identifiable, Associations, PartialEq, Eq)]
+#[derive(Queryable, Selectable, Identifiable, Associations, Clone, Debug, PartialEq, Eq)]
 #[diesel(belongs_to(Fortress))]
 #[diesel(table_name = buildings)]
 pub struct Building {
@@ -39,6 +49,9 @@ pub struct Building {
     pub name: String,
     pub level: i32,
     pub fortress_id: i32,
+    pub version: i64,
+    pub created_at: PrimitiveDateTime,
+    pub updated_at: PrimitiveDateTime,
 }
 
 #[derive(Insertable)]
@@ -56,3 +69,110 @@ pub struct UpdateBuilding {
     pub level: Option<i32>,
     pub fortress_id: Option<i32>,
 }
+
+#[derive(Queryable, Selectable, Identifiable, Associations, Clone, Debug, PartialEq, Eq)]
+#[diesel(belongs_to(Fortress))]
+#[diesel(table_name = fortress_events)]
+pub struct FortressEvent {
+    pub id: i64,
+    /// Unset once the fortress is purged.
+    pub fortress_id: Option<i32>,
+    pub building_id: Option<i32>,
+    pub kind: String,
+    pub gold: i64,
+    pub food: i64,
+    pub wood: i64,
+    pub energy: i64,
+    pub created_at: PrimitiveDateTime,
+    pub other_fortress_id: Option<i32>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = fortress_events)]
+pub struct NewFortressEvent {
+    pub fortress_id: i32,
+    pub building_id: Option<i32>,
+    pub kind: String,
+    pub gold: i64,
+    pub food: i64,
+    pub wood: i64,
+    pub energy: i64,
+    pub created_at: PrimitiveDateTime,
+    pub other_fortress_id: Option<i32>,
+}
+
+#[derive(Queryable, Selectable, Identifiable, Clone, Debug, PartialEq, Eq)]
+#[diesel(table_name = scout_reports)]
+pub struct ScoutReport {
+    pub id: i64,
+    pub spy_fortress_id: i32,
+    pub target_fortress_id: i32,
+    pub gold: i64,
+    pub food: i64,
+    pub wood: i64,
+    pub energy: i64,
+    pub created_at: PrimitiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = scout_reports)]
+pub struct NewScoutReport {
+    pub spy_fortress_id: i32,
+    pub target_fortress_id: i32,
+    pub gold: i64,
+    pub food: i64,
+    pub wood: i64,
+    pub energy: i64,
+    pub created_at: PrimitiveDateTime,
+}
+
+#[derive(
+    Queryable, Selectable, Insertable, Identifiable, Associations, Clone, Debug, PartialEq, Eq,
+)]
+#[diesel(belongs_to(ScoutReport, foreign_key = report_id))]
+#[diesel(table_name = scout_report_buildings, primary_key(report_id, name))]
+pub struct ScoutReportBuilding {
+    pub report_id: i64,
+    pub name: String,
+    pub level: i32,
+}
+
+#[derive(Queryable, Selectable, Identifiable, Clone, Debug, PartialEq, Eq)]
+#[diesel(table_name = camps)]
+pub struct Camp {
+    pub id: i32,
+    pub owner_id: String,
+    pub slot: i32,
+    pub strength: i32,
+    pub gold: i64,
+    pub food: i64,
+    pub wood: i64,
+    pub energy: i64,
+    pub respawn_at: Option<PrimitiveDateTime>,
+    pub version: i64,
+    pub created_at: PrimitiveDateTime,
+    pub updated_at: PrimitiveDateTime,
+}
+
+#[derive(Insertable, AsChangeset, Clone)]
+#[diesel(table_name = camps)]
+pub struct NewCamp {
+    pub owner_id: String,
+    pub slot: i32,
+    pub strength: i32,
+    pub gold: i64,
+    pub food: i64,
+    pub wood: i64,
+    pub energy: i64,
+}
+
+#[derive(Queryable, Selectable, Clone, Debug, PartialEq, Eq)]
+#[diesel(table_name = daily_rewards)]
+pub struct DailyReward {
+    pub owner_id: String,
+    pub streak: i32,
+    pub last_claimed_at: PrimitiveDateTime,
+    pub version: i64,
+    pub created_at: PrimitiveDateTime,
+    pub updated_at: PrimitiveDateTime,
+}
@@ -0,0 +1,61 @@
+//! Seedable pseudo-random numbers for game rules, so a given seed always
+//! replays the same outcomes.
+
+/// `SplitMix64`: tiny and fast, not suitable for anything security related.
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    #[must_use]
+    pub const fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub const fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in `[0, 1)`.
+    #[allow(clippy::cast_precision_loss)]
+    pub fn next_f64(&mut self) -> f64 {
+        // The 53 high bits fill the mantissa exactly.
+        (self.next_u64() >> 11) as f64 / (1_u64 << 53) as f64
+    }
+
+    /// `true` with the given probability, clamped to `[0, 1]`.
+    pub fn chance(&mut self, probability: f64) -> bool {
+        self.next_f64() < probability.clamp(0.0, 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_same_sequence() {
+        let mut a = Rng::new(42);
+        let mut b = Rng::new(42);
+        let mut c = Rng::new(43);
+        let first: Vec<u64> = std::iter::repeat_with(|| a.next_u64()).take(8).collect();
+        let second: Vec<u64> = std::iter::repeat_with(|| b.next_u64()).take(8).collect();
+        let other: Vec<u64> = std::iter::repeat_with(|| c.next_u64()).take(8).collect();
+        assert_eq!(first, second);
+        assert_ne!(first, other);
+    }
+
+    #[test]
+    fn chance_respects_bounds() {
+        let mut rng = Rng::new(7);
+        assert!((0..1000).all(|_| rng.chance(1.0)));
+        assert!(!(0..1000).any(|_| rng.chance(0.0)));
+        let hits = (0..10_000).filter(|_| rng.chance(0.25)).count();
+        assert!((2_000..3_000).contains(&hits), "{hits} hits");
+    }
+}
@@ -0,0 +1,379 @@
+//! Rules of the game: limits, what upgrades cost and what collects yield.
+//!
+//! Pure functions without I/O, shared by the servers, the simulator and the
+//! web client, which previews costs without asking the server.
+
+use crate::Costs;
+
+/// Fortresses a player may own, admins excepted.
+pub const FORTRESSES_PER_PLAYER: i32 = 5;
+
+pub const GOLD_BONUS_BUILDING: &str = "bank";
+pub const FOOD_BONUS_BUILDING: &str = "farm";
+pub const WOOD_BONUS_BUILDING: &str = "sawmill";
+pub const ENERGY_BONUS_BUILDING: &str = "sanctuary";
+/// The buildings of a new fortress, one per resource.
+pub const BONUS_BUILDINGS: [&str; 4] = [
+    GOLD_BONUS_BUILDING,
+    FOOD_BONUS_BUILDING,
+    WOOD_BONUS_BUILDING,
+    ENERGY_BONUS_BUILDING,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resource {
+    Gold,
+    Food,
+    Wood,
+    Energy,
+}
+
+impl Resource {
+    pub const ALL: [Self; 4] = [Self::Gold, Self::Food, Self::Wood, Self::Energy];
+
+    /// The building whose levels add to the collects of the resource.
+    #[must_use]
+    pub const fn bonus_building(self) -> &'static str {
+        match self {
+            Self::Gold => GOLD_BONUS_BUILDING,
+            Self::Food => FOOD_BONUS_BUILDING,
+            Self::Wood => WOOD_BONUS_BUILDING,
+            Self::Energy => ENERGY_BONUS_BUILDING,
+        }
+    }
+
+    /// The resource whose collects `building` adds to, if any.
+    #[must_use]
+    pub fn produced_by(building: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|resource| resource.bonus_building() == building)
+    }
+
+    /// The amount of the resource in `costs`.
+    #[must_use]
+    pub const fn amount(self, costs: &Costs) -> i64 {
+        match self {
+            Self::Gold => costs.gold,
+            Self::Food => costs.food,
+            Self::Wood => costs.wood,
+            Self::Energy => costs.energy,
+        }
+    }
+
+    /// Change of `amount` in this resource only.
+    #[must_use]
+    pub const fn delta(self, amount: i64) -> Costs {
+        let mut delta = Costs::ZERO;
+        *self.amount_mut(&mut delta) = amount;
+        delta
+    }
+
+    const fn amount_mut(self, costs: &mut Costs) -> &mut i64 {
+        match self {
+            Self::Gold => &mut costs.gold,
+            Self::Food => &mut costs.food,
+            Self::Wood => &mut costs.wood,
+            Self::Energy => &mut costs.energy,
+        }
+    }
+}
+
+/// What a collect yields: `base` plus the levels of the bonus buildings of
+/// the resource, saturating instead of overflowing.
+pub fn collect_yield(base: i64, bonus_levels: impl IntoIterator<Item = i32>) -> i64 {
+    bonus_levels
+        .into_iter()
+        .fold(base, |sum, level| sum.saturating_add(i64::from(level)))
+}
+
+/// Whether `resources` cover every resource of `costs`.
+#[must_use]
+pub const fn can_afford(resources: &Costs, costs: &Costs) -> bool {
+    resources.gold >= costs.gold
+        && resources.food >= costs.food
+        && resources.wood >= costs.wood
+        && resources.energy >= costs.energy
+}
+
+pub const SECS_PER_DAY: i64 = 24 * 60 * 60;
+
+const fn daily_bundle(times: i64) -> Costs {
+    Costs {
+        gold: 50 * times,
+        food: 50 * times,
+        wood: 50 * times,
+        energy: 10 * times,
+    }
+}
+
+/// Daily login bundles by day of the streak. The week ends on a bigger one,
+/// which longer streaks keep getting every day.
+pub const DAILY_REWARDS: [Costs; 7] = [
+    daily_bundle(1),
+    daily_bundle(2),
+    daily_bundle(3),
+    daily_bundle(4),
+    daily_bundle(5),
+    daily_bundle(6),
+    daily_bundle(10),
+];
+
+/// Days since the Unix epoch of a Unix timestamp, in seconds. Days start at
+/// midnight UTC.
+#[must_use]
+pub const fn utc_day(unix_secs: i64) -> i64 {
+    unix_secs.div_euclid(SECS_PER_DAY)
+}
+
+/// The streak reached by claiming on `today`.
+///
+/// One more day than `streak` when its last claim, on `last_day`, was
+/// yesterday, a new streak after a missed day, `None` when today is already
+/// claimed.
+#[must_use]
+pub const fn next_streak(streak: i32, last_day: i64, today: i64) -> Option<i32> {
+    if last_day >= today {
+        None
+    } else if last_day == today.saturating_sub(1) {
+        Some(streak.saturating_add(1))
+    } else {
+        Some(1)
+    }
+}
+
+/// The bundle of day `streak` of a streak, the last of `rewards` once past
+/// their end.
+#[must_use]
+pub fn daily_reward(rewards: &[Costs], streak: i32) -> Costs {
+    let day = usize::try_from(streak.saturating_sub(1)).unwrap_or(0);
+    rewards
+        .get(day)
+        .or_else(|| rewards.last())
+        .copied()
+        .unwrap_or(Costs::ZERO)
+}
+
+/// The numbers the economy is tuned with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Economy {
+    pub max_building_level: i32,
+    /// Cost of the first upgrade, before the split between resources.
+    pub base_cost: i32,
+    /// Cost of the upgrade to the max level, before the split.
+    pub max_cost: i32,
+    /// Each resource costs the upgrade cost divided by its divisor.
+    pub cost_divisors: Costs,
+    /// What a collect yields on top of the levels of the bonus buildings.
+    pub base_production: i64,
+}
+
+impl Economy {
+    /// The economy of the game server.
+    pub const LIVE: Self = Self {
+        max_building_level: 20,
+        base_cost: 10,
+        max_cost: i32::MAX,
+        cost_divisors: Costs {
+            gold: 2,
+            food: 1,
+            wood: 5,
+            energy: 10,
+        },
+        base_production: 1,
+    };
+
+    /// What upgrading a building from `level` costs.
+    ///
+    /// Float to integer casts saturate, so out of range costs can't wrap
+    /// around.
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn upgrade_costs(&self, level: i32) -> Costs {
+        let factor = optimize_factor(self.max_building_level, self.base_cost, self.max_cost);
+        let cost = upgrade_cost(level, self.base_cost, factor) as i64;
+        let split = |divisor: i64| cost.checked_div(divisor).unwrap_or(cost);
+
+        Costs {
+            gold: split(self.cost_divisors.gold),
+            food: split(self.cost_divisors.food),
+            wood: split(self.cost_divisors.wood),
+            energy: split(self.cost_divisors.energy),
+        }
+    }
+
+    /// What upgrading a building from `level` costs, `None` once it reached
+    /// the max level.
+    #[must_use]
+    pub fn next_upgrade_costs(&self, level: i32) -> Option<Costs> {
+        (level < self.max_building_level).then(|| self.upgrade_costs(level))
+    }
+
+    /// What each collect adds: the base production plus the levels of the
+    /// bonus buildings of that resource, from `(name, level)` pairs.
+    pub fn production<'a>(&self, buildings: impl IntoIterator<Item = (&'a str, i32)>) -> Costs {
+        let mut production = Costs::ZERO;
+        let buildings: Vec<(&str, i32)> = buildings.into_iter().collect();
+        for resource in Resource::ALL {
+            *resource.amount_mut(&mut production) = collect_yield(
+                self.base_production,
+                buildings
+                    .iter()
+                    .filter(|(name, _)| *name == resource.bonus_building())
+                    .map(|&(_, level)| level),
+            );
+        }
+        production
+    }
+}
+
+#[must_use]
+pub fn upgrade_cost(level: i32, base: i32, factor: f64) -> f64 {
+    let level = level.max(1);
+    f64::from(base) * factor.powf(f64::from(level - 1))
+}
+
+/// The growth factor which takes the cost from `base` at the first level to
+/// `cost_max` at `level_max`.
+#[must_use]
+pub fn optimize_factor(level_max: i32, base: i32, cost_max: i32) -> f64 {
+    (f64::from(cost_max) / f64::from(base)).powf(1.0 / (f64::from(level_max) - 1.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE_COST: i32 = Economy::LIVE.base_cost;
+
+    #[test]
+    fn production_adds_bonus_levels() {
+        let buildings = [
+            (GOLD_BONUS_BUILDING, 3),
+            (GOLD_BONUS_BUILDING, 2),
+            (WOOD_BONUS_BUILDING, 1),
+            ("tavern", 9),
+        ];
+        let production = Economy::LIVE.production(buildings);
+        assert_eq!(
+            production,
+            Costs {
+                gold: 6,
+                food: 1,
+                wood: 2,
+                energy: 1,
+            }
+        );
+        assert_eq!(collect_yield(i64::MAX, [1]), i64::MAX);
+        assert_eq!(Resource::produced_by("sanctuary"), Some(Resource::Energy));
+        assert_eq!(Resource::produced_by("tavern"), None);
+        assert_eq!(Resource::Wood.amount(&Resource::Wood.delta(-3)), -3);
+        assert_eq!(Resource::Wood.delta(-3).gold, 0);
+    }
+
+    #[test]
+    fn no_upgrade_past_the_max_level() {
+        let economy = Economy::LIVE;
+        let max = economy.max_building_level;
+        assert!(economy.next_upgrade_costs(max - 1).is_some());
+        assert_eq!(economy.next_upgrade_costs(max), None);
+
+        let costs = economy.upgrade_costs(0);
+        assert!(can_afford(&costs, &costs));
+        assert!(!can_afford(
+            &Costs {
+                food: costs.food - 1,
+                ..costs
+            },
+            &costs
+        ));
+    }
+
+    #[test]
+    fn optimize_factor_works() {
+        let cost_max = i32::MAX;
+        let levels = [10, 50, 100, 200, 1000];
+
+        for level in levels {
+            let factor = optimize_factor(level, BASE_COST, cost_max);
+            println!("level max: {level}, base: {BASE_COST}, factor: {factor}");
+            assert!(factor >= 1.0);
+        }
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    #[test]
+    fn upgrade_cost_works() {
+        const FACTOR: f64 = 1.1012;
+        let result = upgrade_cost(0, BASE_COST, FACTOR) as i32;
+        assert_eq!(result, 10);
+        let result = upgrade_cost(1, BASE_COST, FACTOR) as i32;
+        assert_eq!(result, 10);
+        let result = upgrade_cost(2, BASE_COST, FACTOR) as i32;
+        assert_eq!(result, 11);
+        let result = upgrade_cost(3, BASE_COST, FACTOR) as i32;
+        assert_eq!(result, 12);
+        let result = upgrade_cost(4, BASE_COST, FACTOR) as i32;
+        assert_eq!(result, 13);
+        let result = upgrade_cost(5, BASE_COST, FACTOR) as i32;
+        assert_eq!(result, 14);
+        let result = upgrade_cost(50, BASE_COST, FACTOR) as i32;
+        assert_eq!(result, 1125);
+        let result = upgrade_cost(100, BASE_COST, FACTOR) as i32;
+        assert_eq!(result, 139_557);
+        let result = upgrade_cost(150, BASE_COST, FACTOR) as i32;
+        assert_eq!(result, 17_300_721);
+        let result = upgrade_cost(200, BASE_COST, FACTOR) as i32;
+        assert_eq!(result, 2_144_738_468);
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    #[test]
+    fn upgrade_cost_overflow() {
+        const FACTOR: f64 = 1.1012;
+        let result = upgrade_cost(250, BASE_COST, FACTOR) as i32;
+        assert_eq!(result, i32::MAX);
+        let result = upgrade_cost(1000, BASE_COST, FACTOR) as i32;
+        assert_eq!(result, i32::MAX);
+        let result = upgrade_cost(i32::MAX, BASE_COST, FACTOR) as i32;
+        assert_eq!(result, i32::MAX);
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    #[test]
+    fn level_slice() {
+        let cost_max = i32::MAX;
+        let levels = [10, 50, 100, 200, 1000];
+
+        for level in levels {
+            let factor = optimize_factor(level, BASE_COST, cost_max);
+            let result_min = upgrade_cost(1, BASE_COST, factor) as i32;
+            let result_max = upgrade_cost(level, BASE_COST, factor) as i32;
+            println!("{}: {} -> {}: {}", 1, result_min, level, result_max);
+            assert!(BASE_COST <= result_min && result_min <= cost_max);
+            assert!(BASE_COST <= result_max && result_max <= cost_max);
+            assert!(result_min <= result_max);
+        }
+    }
+
+    #[test]
+    fn streaks_follow_consecutive_days() {
+        assert_eq!(utc_day(SECS_PER_DAY - 1), 0);
+        assert_eq!(utc_day(-1), -1);
+
+        assert_eq!(next_streak(3, 9, 10), Some(4));
+        assert_eq!(next_streak(3, 10, 10), None);
+        assert_eq!(next_streak(3, 7, 10), Some(1));
+        assert_eq!(next_streak(i32::MAX, 9, 10), Some(i32::MAX));
+    }
+
+    #[test]
+    fn daily_reward_grows_with_the_streak_then_stays() {
+        assert_eq!(daily_reward(&DAILY_REWARDS, 1), daily_bundle(1));
+        assert_eq!(daily_reward(&DAILY_REWARDS, 2).gold, 100);
+        assert_eq!(daily_reward(&DAILY_REWARDS, 7), daily_bundle(10));
+        assert_eq!(daily_reward(&DAILY_REWARDS, 30), daily_bundle(10));
+        assert_eq!(daily_reward(&DAILY_REWARDS, 0), daily_bundle(1));
+        assert_eq!(daily_reward(&[], 3), Costs::ZERO);
+    }
+}
@@ -6,6 +6,52 @@ diesel::table! {
         name -> Varchar,
         level -> Int4,
         fortress_id -> Int4,
+        version -> Int8,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    camps (id) {
+        id -> Int4,
+        owner_id -> Varchar,
+        slot -> Int4,
+        strength -> Int4,
+        gold -> Int8,
+        food -> Int8,
+        wood -> Int8,
+        energy -> Int8,
+        respawn_at -> Nullable<Timestamp>,
+        version -> Int8,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    daily_rewards (owner_id) {
+        owner_id -> Varchar,
+        streak -> Int4,
+        last_claimed_at -> Timestamp,
+        version -> Int8,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    fortress_events (id) {
+        id -> Int8,
+        fortress_id -> Nullable<Int4>,
+        building_id -> Nullable<Int4>,
+        kind -> Varchar,
+        gold -> Int8,
+        food -> Int8,
+        wood -> Int8,
+        energy -> Int8,
+        created_at -> Timestamp,
+        other_fortress_id -> Nullable<Int4>,
     }
 }
 
@@ -13,13 +59,49 @@ diesel::table! {
     fortresses (id) {
         id -> Int4,
         owner_id -> Varchar,
-        gold -> Int4,
-        food -> Int4,
-        wood -> Int4,
-        energy -> Int4,
+        gold -> Int8,
+        food -> Int8,
+        wood -> Int8,
+        energy -> Int8,
+        deleted_at -> Nullable<Timestamp>,
+        version -> Int8,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+        owner_name -> Varchar,
+    }
+}
+
+diesel::table! {
+    scout_report_buildings (report_id, name) {
+        report_id -> Int8,
+        name -> Varchar,
+        level -> Int4,
+    }
+}
+
+diesel::table! {
+    scout_reports (id) {
+        id -> Int8,
+        spy_fortress_id -> Int4,
+        target_fortress_id -> Int4,
+        gold -> Int8,
+        food -> Int8,
+        wood -> Int8,
+        energy -> Int8,
+        created_at -> Timestamp,
     }
 }
 
 diesel::joinable!(buildings -> fortresses (fortress_id));
+diesel::joinable!(fortress_events -> buildings (building_id));
+diesel::joinable!(scout_report_buildings -> scout_reports (report_id));
 
-diesel::allow_tables_to_appear_in_same_query!(buildings, fortresses);
+diesel::allow_tables_to_appear_in_same_query!(
+    buildings,
+    camps,
+    daily_rewards,
+    fortress_events,
+    fortresses,
+    scout_report_buildings,
+    scout_reports,
+);